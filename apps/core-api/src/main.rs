@@ -1,4 +1,4 @@
-use axum::{routing::{get, post}, Router, extract::State};
+use axum::{routing::{get, post}, Router, extract::State, Extension};
 use tracing_subscriber::EnvFilter;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -33,12 +33,18 @@ async fn main() {
         refresh_secret: std::env::var("REFRESH_SECRET").unwrap_or_else(|_| "change-me-refresh".to_string()),
     });
     
-    let app = Router::new()
+    let mut app = Router::new()
         .merge(auth::router())
         .route("/tokens/mint", post(mint_token))
         .route("/tokens/refresh", post(refresh_token))
         .route("/tokens/revoke", post(revoke_token))
         .with_state(token_state);
+
+    // Verificador de X-Ubl-Assertion (policy-proxy) para o extractor PolicyAssertion
+    match policy_assertion::AssertionVerifier::from_env().expect("invalid POLICY_ASSERTION_PUBKEY_PEM_B64") {
+        Some(verifier) => app = app.layer(Extension(Arc::new(verifier))),
+        None => tracing::warn!("POLICY_ASSERTION_PUBKEY_PEM_B64 not set; PolicyAssertion will reject requests"),
+    }
     
     let addr = SocketAddr::from(([127,0,0,1], 9458));
     tracing::info!("Core API listening on {}", addr);
@@ -0,0 +1,32 @@
+-- IdentityStore (Postgres) — derivado de schemas/auth_p0.sql
+-- Mesmas diferenças da versão SQLite; INTEGER de tempo vira BIGINT, BLOB vira BYTEA.
+
+CREATE TABLE IF NOT EXISTS users (
+  id            TEXT PRIMARY KEY,   -- user:{uuid} | agent:{uuid}
+  username      TEXT UNIQUE,        -- opcional
+  email         TEXT UNIQUE,
+  tenant        TEXT NOT NULL DEFAULT 'ubl',
+  created_at    BIGINT NOT NULL DEFAULT (extract(epoch FROM now())::bigint)
+);
+
+CREATE TABLE IF NOT EXISTS passkeys (
+  id              TEXT PRIMARY KEY,              -- credentialId (base64url)
+  record_id       TEXT NOT NULL UNIQUE,          -- WebAuthnCredential.id (ulid)
+  user_id         TEXT NOT NULL REFERENCES users(id),
+  public_key      BYTEA NOT NULL,                -- P-256 SEC1
+  sign_count      BIGINT NOT NULL DEFAULT 0,
+  transports      TEXT,                          -- csv
+  created_at      BIGINT NOT NULL DEFAULT (extract(epoch FROM now())::bigint)
+);
+CREATE INDEX IF NOT EXISTS idx_passkeys_user ON passkeys(user_id);
+
+CREATE TABLE IF NOT EXISTS sessions (
+  id           TEXT PRIMARY KEY,                 -- cookie sid
+  user_id      TEXT NOT NULL REFERENCES users(id),
+  csrf         TEXT NOT NULL,                    -- anti-CSRF
+  ip_hash      TEXT,                             -- fingerprint: hash(UA || prefixo do IP)
+  expires_at   BIGINT NOT NULL,                  -- unix ts
+  created_at   BIGINT NOT NULL DEFAULT (extract(epoch FROM now())::bigint)
+);
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires ON sessions(expires_at);
@@ -0,0 +1,36 @@
+-- IdentityStore (SQLite / D1) — derivado de schemas/auth_p0.sql
+-- Diferenças: users ganha email/tenant (Subject), passkeys guarda a chave em
+-- SEC1 (já convertida do COSE no registro) e o id do registro (ulid).
+-- Demais tabelas do P0 (refresh, revogação, device flow) entram quando o gateway as usar.
+
+CREATE TABLE IF NOT EXISTS users (
+  id            TEXT PRIMARY KEY,   -- user:{uuid} | agent:{uuid}
+  username      TEXT UNIQUE,        -- opcional
+  email         TEXT UNIQUE,
+  tenant        TEXT NOT NULL DEFAULT 'ubl',
+  created_at    INTEGER NOT NULL DEFAULT (unixepoch())
+);
+
+CREATE TABLE IF NOT EXISTS passkeys (
+  id              TEXT PRIMARY KEY,              -- credentialId (base64url)
+  record_id       TEXT NOT NULL UNIQUE,          -- WebAuthnCredential.id (ulid)
+  user_id         TEXT NOT NULL,
+  public_key      BLOB NOT NULL,                 -- P-256 SEC1
+  sign_count      INTEGER NOT NULL DEFAULT 0,
+  transports      TEXT,                          -- csv
+  created_at      INTEGER NOT NULL DEFAULT (unixepoch()),
+  FOREIGN KEY(user_id) REFERENCES users(id)
+);
+CREATE INDEX IF NOT EXISTS idx_passkeys_user ON passkeys(user_id);
+
+CREATE TABLE IF NOT EXISTS sessions (
+  id           TEXT PRIMARY KEY,                 -- cookie sid
+  user_id      TEXT NOT NULL,
+  csrf         TEXT NOT NULL,                    -- anti-CSRF
+  ip_hash      TEXT,                             -- fingerprint: hash(UA || prefixo do IP)
+  expires_at   INTEGER NOT NULL,                 -- unix ts
+  created_at   INTEGER NOT NULL DEFAULT (unixepoch()),
+  FOREIGN KEY(user_id) REFERENCES users(id)
+);
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires ON sessions(expires_at);
@@ -1,74 +1,196 @@
 //! Auth routes: /auth/passkey/*, /session, /auth/logout
+//!
+//! Registration needs the identity in the policy-proxy's signed assertion (the
+//! passkey is bound to that email's subject); login uses discoverable credentials. Challenges live
+//! in a TTL cache and are single-use: the ceremony is found by the challenge
+//! echoed in clientDataJSON and removed before verification.
 
-use axum::{extract::State, http::StatusCode, response::Json, routing::{get, post}, Router};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Json,
+    routing::{get, post},
+    Extension, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::RngCore;
+use serde::Deserialize;
 use serde_json::json;
-use crate::identity::{IdentityStore, Session};
-use crate::mcp::router::McpErr;
+use sha2::{Digest, Sha256};
+use crate::identity::webauthn::{self, AuthenticationResponse, RegistrationResponse, RelyingParty, CHALLENGE_TTL_SEC};
+use crate::identity::{generate_csrf_token, IdentityStore, Session, WebAuthnCredential};
+use crate::internal::PolicyAssertion;
+use policy_assertion::step_up::{StepUpSigner, COOKIE as STEP_UP_COOKIE};
+
+/// Session lifetime (seconds)
+pub const SESSION_TTL_SEC: i64 = 8 * 3600;
+
+type Rejection = (StatusCode, Json<serde_json::Value>);
+
+#[derive(Clone)]
+enum Pending {
+    Register { subject_id: String },
+    Login { return_to: Option<String> },
+}
+
+#[derive(Clone)]
+pub struct AuthState<S: IdentityStore> {
+    pub store: S,
+    pub rp: Arc<RelyingParty>,
+    pending: moka::future::Cache<String, Pending>,
+}
 
-pub fn routes<S: IdentityStore + Clone + Send + Sync + 'static>() -> Router<S> {
+pub fn routes<S: IdentityStore + Clone + Send + Sync + 'static>(store: S, rp: RelyingParty) -> Router {
+    let pending = moka::future::Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(CHALLENGE_TTL_SEC))
+        .build();
     Router::new()
-        .route("/auth/passkey/register", get(passkey_register))
-        .route("/auth/passkey/finish", post(passkey_finish))
-        .route("/session", get(get_session))
-        .route("/auth/logout", post(logout))
-}
-
-async fn passkey_register() -> Json<serde_json::Value> {
-    // WebAuthn registration options
-    // TODO: Implement with webauthn-rs
-    Json(json!({
-        "publicKey": {
-            "rp": { "name": "UBL Agency", "id": "app.ubl.agency" },
-            "user": { "id": "placeholder", "name": "user", "displayName": "User" },
-            "challenge": "placeholder",
-            "pubKeyCredParams": [{ "type": "public-key", "alg": -7 }]
+        .route("/auth/passkey/register", get(passkey_register::<S>))
+        .route("/auth/passkey/finish", post(passkey_finish::<S>))
+        .route("/auth/passkey/login", get(passkey_login::<S>))
+        .route("/auth/passkey/login/finish", post(passkey_login_finish::<S>))
+        .route("/session", get(get_session::<S>))
+        .route("/auth/logout", post(logout::<S>))
+        .with_state(AuthState { store, rp: Arc::new(rp), pending })
+}
+
+fn reject(status: StatusCode, token: &str, remediation: impl ToString) -> Rejection {
+    (status, Json(json!({"token": token, "remediation": [remediation.to_string()]})))
+}
+
+fn internal(e: anyhow::Error) -> Rejection {
+    tracing::error!("auth store: {:#}", e);
+    reject(StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL", "Retry later")
+}
+
+fn now_epoch() -> i64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+async fn passkey_register<S: IdentityStore + Clone>(
+    State(st): State<AuthState<S>>,
+    PolicyAssertion(assertion): PolicyAssertion,
+) -> Result<Json<serde_json::Value>, Rejection> {
+    let email = assertion.who
+        .ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", "Cloudflare Access login required to register a passkey"))?;
+    let subject = match st.store.get_subject_by_email(&email).await.map_err(internal)? {
+        Some(s) => s,
+        None => st.store.create_subject(Some(email.clone()), assertion.tenant).await.map_err(internal)?,
+    };
+    let challenge = webauthn::new_challenge();
+    st.pending.insert(challenge.clone(), Pending::Register { subject_id: subject.id.clone() }).await;
+    Ok(Json(st.rp.creation_options(&challenge, subject.id.as_bytes(), &email, &[])))
+}
+
+async fn passkey_finish<S: IdentityStore + Clone>(
+    State(st): State<AuthState<S>>,
+    headers: HeaderMap,
+    Json(resp): Json<RegistrationResponse>,
+) -> Result<(StatusCode, HeaderMap, Json<serde_json::Value>), Rejection> {
+    let challenge = webauthn::client_challenge(&resp.response.client_data_json)
+        .map_err(|e| reject(StatusCode::BAD_REQUEST, "INVALID_PARAMS", e))?;
+    let Some(Pending::Register { subject_id }) = st.pending.remove(&challenge).await else {
+        return Err(reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", "Unknown or expired challenge; restart registration"));
+    };
+    let cred = st.rp.verify_registration(&resp, &challenge)
+        .map_err(|e| reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", format!("{:#}", e)))?;
+    if st.store.get_webauthn_credential(&cred.credential_id).await.map_err(internal)?.is_some() {
+        return Err(reject(StatusCode::CONFLICT, "CONFLICT", "Credential already registered"));
+    }
+    st.store.save_webauthn_credential(WebAuthnCredential {
+        id: ulid::Ulid::new().to_string(),
+        subject_id: subject_id.clone(),
+        credential_id: cred.credential_id.clone(),
+        public_key: cred.public_key,
+        counter: cred.counter,
+        created_at: now_epoch(),
+    }).await.map_err(internal)?;
+
+    let (session, cookie) = start_session(&st.store, &subject_id, &headers).await.map_err(internal)?;
+    let mut h = HeaderMap::new();
+    h.append(header::SET_COOKIE, cookie);
+    Ok((StatusCode::CREATED, h, Json(json!({
+        "sub": subject_id,
+        "credential_id": URL_SAFE_NO_PAD.encode(&cred.credential_id),
+        "fmt": cred.fmt,
+        "csrf_token": session.csrf_token,
+    }))))
+}
+
+#[derive(Deserialize)]
+struct LoginQuery {
+    return_to: Option<String>,
+}
+
+async fn passkey_login<S: IdentityStore + Clone>(
+    State(st): State<AuthState<S>>,
+    Query(q): Query<LoginQuery>,
+) -> Json<serde_json::Value> {
+    let challenge = webauthn::new_challenge();
+    let return_to = q.return_to.filter(|p| is_local_path(p));
+    st.pending.insert(challenge.clone(), Pending::Login { return_to }).await;
+    Json(st.rp.request_options(&challenge))
+}
+
+async fn passkey_login_finish<S: IdentityStore + Clone>(
+    State(st): State<AuthState<S>>,
+    headers: HeaderMap,
+    step_up: Option<Extension<Arc<StepUpSigner>>>,
+    Json(resp): Json<AuthenticationResponse>,
+) -> Result<(HeaderMap, Json<serde_json::Value>), Rejection> {
+    let unauthorized = |msg: String| reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg);
+    let challenge = webauthn::client_challenge(&resp.response.client_data_json)
+        .map_err(|e| reject(StatusCode::BAD_REQUEST, "INVALID_PARAMS", e))?;
+    let Some(Pending::Login { return_to }) = st.pending.remove(&challenge).await else {
+        return Err(unauthorized("Unknown or expired challenge; restart login".into()));
+    };
+    let raw_id = webauthn::b64(&resp.raw_id).map_err(|e| reject(StatusCode::BAD_REQUEST, "INVALID_PARAMS", e))?;
+    let mut cred = st.store.get_webauthn_credential(&raw_id).await.map_err(internal)?
+        .ok_or_else(|| unauthorized("Unknown credential; register a passkey first".into()))?;
+    cred.counter = st.rp.verify_authentication(&resp, &challenge, &cred.public_key, cred.counter)
+        .map_err(|e| unauthorized(format!("{:#}", e)))?;
+    // userHandle (discoverable credential) tem de ser o dono da credencial
+    if let Some(handle) = resp.response.user_handle.as_deref().filter(|h| !h.is_empty()) {
+        if webauthn::b64(handle).ok().as_deref() != Some(cred.subject_id.as_bytes()) {
+            return Err(unauthorized("userHandle does not match the credential owner".into()));
+        }
+    }
+    st.store.save_webauthn_credential(cred.clone()).await.map_err(internal)?;
+
+    let (session, cookie) = start_session(&st.store, &cred.subject_id, &headers).await.map_err(internal)?;
+    let mut h = HeaderMap::new();
+    h.append(header::SET_COOKIE, cookie);
+    if let Some(Extension(signer)) = step_up {
+        let email = st.store.get_subject(&cred.subject_id).await.map_err(internal)?.and_then(|s| s.email);
+        if let Some(email) = email {
+            let c = step_up_cookie(&signer, &email, &st.rp.id, &resp.raw_id).map_err(internal)?;
+            h.append(header::SET_COOKIE, HeaderValue::from_str(&c).map_err(|e| internal(e.into()))?);
         }
-    }))
-}
-
-async fn passkey_finish(
-    State(store): State<impl IdentityStore>,
-) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
-    // TODO: Implement WebAuthn finish flow
-    // For now: placeholder
-    Err((
-        StatusCode::NOT_IMPLEMENTED,
-        Json(json!({"token": "NOT_IMPLEMENTED", "remediation": ["WebAuthn finish not yet implemented"]})),
-    ))
-}
-
-async fn get_session(
-    State(store): State<impl IdentityStore>,
-    cookies: axum::extract::TypedHeader<axum::headers::Cookie>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    let sid = jar.get("sid").map(|c| c.value()).ok_or_else(|| {
-        (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({"token": "UNAUTHORIZED", "remediation": ["Login required"]})),
-        )
-    })?;
-
-    let session = store.get_session(sid).await.map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"token": "INTERNAL", "remediation": ["Retry later"]})),
-        )
-    })?;
-
-    let session = session.ok_or_else(|| {
-        (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({"token": "UNAUTHORIZED", "remediation": ["Session expired"]})),
-        )
-    })?;
+    }
+    Ok((h, Json(json!({
+        "sub": cred.subject_id,
+        "csrf_token": session.csrf_token,
+        "return_to": return_to,
+    }))))
+}
+
+async fn get_session<S: IdentityStore + Clone>(
+    State(st): State<AuthState<S>>,
+    jar: axum_extra::extract::CookieJar,
+) -> Result<Json<serde_json::Value>, Rejection> {
+    let sid = jar.get("sid").map(|c| c.value().to_string())
+        .ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", "Login required"))?;
+
+    let session = st.store.get_session(&sid).await.map_err(internal)?
+        .ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", "Session expired"))?;
 
     // Check expiration
-    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-    if now > session.expires_at {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(json!({"token": "UNAUTHORIZED", "remediation": ["Session expired"]})),
-        ));
+    if now_epoch() > session.expires_at {
+        return Err(reject(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", "Session expired"));
     }
 
     Ok(Json(json!({
@@ -79,12 +201,143 @@ async fn get_session(
     })))
 }
 
-async fn logout(
-    State(store): State<impl IdentityStore>,
+async fn logout<S: IdentityStore + Clone>(
+    State(st): State<AuthState<S>>,
     jar: axum_extra::extract::CookieJar,
-) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
-    if let Some(sid) = jar.get("sid").map(|c| c.value()) {
-        let _ = store.delete_session(sid).await;
+) -> Result<StatusCode, Rejection> {
+    if let Some(sid) = jar.get("sid").map(|c| c.value().to_string()) {
+        let _ = st.store.delete_session(&sid).await;
     }
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// New session for `subject_id` + its `sid` Set-Cookie
+async fn start_session<S: IdentityStore>(store: &S, subject_id: &str, headers: &HeaderMap) -> anyhow::Result<(Session, HeaderValue)> {
+    let mut sid = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut sid);
+    let session = Session {
+        sid: URL_SAFE_NO_PAD.encode(sid),
+        subject_id: subject_id.into(),
+        fingerprint: fingerprint(headers),
+        expires_at: now_epoch() + SESSION_TTL_SEC,
+        csrf_token: generate_csrf_token(),
+    };
+    store.save_session(session.clone()).await?;
+    let cookie = format!("sid={}; Path=/; Max-Age={}; Secure; HttpOnly; SameSite=Lax", session.sid, SESSION_TTL_SEC);
+    Ok((session, HeaderValue::from_str(&cookie)?))
+}
+
+/// UA + IP prefix (/24 or /48), hashed
+fn fingerprint(headers: &HeaderMap) -> String {
+    let get = |k: &str| headers.get(k).and_then(|v| v.to_str().ok()).unwrap_or("");
+    let ip = get("CF-Connecting-IP");
+    let prefix = match ip.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(v4)) => { let o = v4.octets(); format!("{}.{}.{}", o[0], o[1], o[2]) }
+        Ok(std::net::IpAddr::V6(v6)) => { let s = v6.segments(); format!("{:x}:{:x}:{:x}", s[0], s[1], s[2]) }
+        Err(_) => String::new(),
+    };
+    hex::encode(&Sha256::digest(format!("{}|{}", get("user-agent"), prefix).as_bytes())[..16])
+}
+
+/// Only same-origin paths (`/x`, not `//host` or `/\host`)
+fn is_local_path(p: &str) -> bool {
+    p.starts_with('/') && !p.starts_with("//") && !p.contains('\\')
+}
+
+/// Set-Cookie da prova de step-up depois de uma asserção WebAuthn: com ele o
+/// policy-proxy reavalia o chip com `auth.method = webauthn` (saídas challenge).
+pub(crate) fn step_up_cookie(signer: &StepUpSigner, sub: &str, rp_id: &str, cred: &str) -> anyhow::Result<String> {
+    let token = signer.sign(sub, rp_id, cred)?;
+    Ok(format!("{}={}; Path=/; Max-Age={}; Secure; HttpOnly; SameSite=Strict", STEP_UP_COOKIE, token, signer.ttl_sec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::webauthn::soft::SoftAuthenticator;
+    use crate::identity::MemoryIdentityStore;
+    use axum::body::Body;
+    use axum::http::Request;
+    use policy_assertion::{AssertionClaims, AssertionSigner, AssertionVerifier};
+    use tower::ServiceExt;
+
+    async fn call(app: &Router, req: Request<Body>) -> (StatusCode, HeaderMap, serde_json::Value) {
+        let resp = app.clone().oneshot(req).await.unwrap();
+        let (parts, body) = resp.into_parts();
+        let bytes = axum::body::to_bytes(body, 1 << 20).await.unwrap();
+        (parts.status, parts.headers, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    fn post_json(uri: &str, body: &serde_json::Value) -> Request<Body> {
+        Request::post(uri).header(header::CONTENT_TYPE, "application/json").body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn register_then_login_with_a_software_authenticator() {
+        let rp = RelyingParty::new("app.ubl.agency", "UBL Agency");
+        let store = MemoryIdentityStore::new();
+        let signer = Arc::new(StepUpSigner::new(ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]), "gateway-v1".into()));
+        let proxy = AssertionSigner::new(ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]), "proxy-v1".into());
+        let verifier = Arc::new(AssertionVerifier::new().with_key("proxy-v1", proxy.verifying_key()));
+        let app = routes(store.clone(), rp.clone()).layer(Extension(signer)).layer(Extension(verifier));
+        let mut auth = SoftAuthenticator::new(&rp, 1);
+
+        // registro exige a asserção do policy-proxy; headers do Access sozinhos não bastam
+        let req = Request::get("/auth/passkey/register")
+            .header("Cf-Access-Authenticated-User-Email", "dan@ubl.agency")
+            .header("Cf-Access-Groups", "ubl-ops")
+            .body(Body::empty()).unwrap();
+        let (status, _, _) = call(&app, req).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let claims = AssertionClaims {
+            iss: String::new(), iat: 0, exp: 0,
+            jti: "8a1f-GRU".into(),
+            tenant: "ubl".into(),
+            decision: "allow_standard_access".into(),
+            trigger: "W_ZeroTrust_Standard".into(),
+            who: Some("dan@ubl.agency".into()),
+            groups: vec!["ubl-ops".into()],
+            chip: "c77f".into(),
+            did: Some("GET /auth/passkey/register".into()),
+        };
+        let req = Request::get("/auth/passkey/register")
+            .header(policy_assertion::HEADER, proxy.sign(claims).unwrap())
+            .body(Body::empty()).unwrap();
+        let (status, _, options) = call(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let challenge = options["publicKey"]["challenge"].as_str().unwrap().to_string();
+        let user_id = webauthn::b64(options["publicKey"]["user"]["id"].as_str().unwrap()).unwrap();
+
+        let registration = auth.register(&challenge, "packed");
+        let (status, h, body) = call(&app, post_json("/auth/passkey/finish", &registration)).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        assert!(h[header::SET_COOKIE].to_str().unwrap().starts_with("sid="));
+        // challenge é de uso único
+        let (status, _, _) = call(&app, post_json("/auth/passkey/finish", &registration)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (_, _, options) = call(&app, Request::get("/auth/passkey/login?return_to=/admin/deploy").body(Body::empty()).unwrap()).await;
+        let challenge = options["publicKey"]["challenge"].as_str().unwrap().to_string();
+        let assertion = auth.login(&challenge, &user_id);
+        let (status, h, body) = call(&app, post_json("/auth/passkey/login/finish", &assertion)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["return_to"], "/admin/deploy");
+        let cookies: Vec<_> = h.get_all(header::SET_COOKIE).iter().map(|v| v.to_str().unwrap().to_string()).collect();
+        assert!(cookies[0].starts_with("sid=") && cookies[1].starts_with("ubl_step_up="));
+        let sid = cookies[0].split(';').next().unwrap().to_string();
+        let session = store.get_session(sid.trim_start_matches("sid=")).await.unwrap().unwrap();
+        assert_eq!(session.csrf_token, body["csrf_token"]);
+        assert_eq!(store.get_webauthn_credential(&auth.credential_id).await.unwrap().unwrap().counter, 1);
+
+        let (status, _, me) = call(&app, Request::get("/session").header(header::COOKIE, sid).body(Body::empty()).unwrap()).await;
+        assert_eq!((status, &me["sub"]), (StatusCode::OK, &body["sub"]));
+
+        // contador que não sobe (autenticador clonado) é recusado
+        let (_, _, options) = call(&app, Request::get("/auth/passkey/login").body(Body::empty()).unwrap()).await;
+        auth.counter = 0;
+        let replay = auth.login(options["publicKey"]["challenge"].as_str().unwrap(), &user_id);
+        let (status, _, body) = call(&app, post_json("/auth/passkey/login/finish", &replay)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(body["remediation"][0].as_str().unwrap().contains("counter"));
+    }
+}
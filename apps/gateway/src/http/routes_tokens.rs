@@ -1,11 +1,14 @@
 //! Token routes: /tokens/mint, /tokens/refresh, /tokens/revoke
+//!
+//! Every route requires the policy-proxy's signed `X-Ubl-Assertion`; the
+//! caller's identity, groups and tenant come from it, never from forwarded
+//! `X-Who`/`Cf-Access-*` headers.
 
 use axum::{extract::State, http::StatusCode, response::Json, routing::post, Router};
 use serde_json::json;
-use crate::identity::{TokenManager, MintRequest, MintResponse, TokenScope, AbacContext, evaluate_abac, AbacDecision, IdentityStore};
+use crate::identity::{TokenManager, MintRequest, MintResponse, AbacContext, evaluate_abac, AbacDecision, IdentityStore};
 use crate::identity::access::map_groups_to_roles;
-use crate::identity::access::extract_access_identity;
-use crate::mcp::router::McpErr;
+use crate::internal::PolicyAssertion;
 
 #[derive(Clone)]
 pub struct AppState<S: IdentityStore> {
@@ -16,7 +19,7 @@ pub struct AppState<S: IdentityStore> {
 pub fn routes<S: IdentityStore + Clone + Send + Sync + 'static>(
     store: S,
     token_mgr: TokenManager,
-) -> Router<AppState<S>> {
+) -> Router {
     Router::new()
         .route("/tokens/mint", post(mint_token))
         .route("/tokens/refresh", post(refresh_token))
@@ -29,7 +32,7 @@ pub fn routes<S: IdentityStore + Clone + Send + Sync + 'static>(
 
 async fn mint_token<S: IdentityStore>(
     State(state): State<AppState<S>>,
-    headers: axum::http::HeaderMap,
+    PolicyAssertion(assertion): PolicyAssertion,
     Json(req): Json<MintRequest>,
 ) -> Result<Json<MintResponse>, (StatusCode, Json<serde_json::Value>)> {
     // Validate client_id
@@ -40,21 +43,27 @@ async fn mint_token<S: IdentityStore>(
         ));
     }
 
-    // Extract identity from Access headers
-    let access_identity = extract_access_identity(&headers)
+    // Identity as decided and signed by the policy-proxy
+    let who = assertion.who.clone()
         .ok_or_else(|| {
             (
                 StatusCode::UNAUTHORIZED,
-                Json(json!({"token": "UNAUTHORIZED", "remediation": ["Cloudflare Access required"]})),
+                Json(json!({"token": "UNAUTHORIZED", "remediation": ["Authenticated identity required"]})),
             )
         })?;
 
+    // Same subject passkeys attach to (created on first use)
+    let subject = match state.store.get_subject_by_email(&who).await.map_err(internal)? {
+        Some(s) => s,
+        None => state.store.create_subject(Some(who.clone()), assertion.tenant.clone()).await.map_err(internal)?,
+    };
+
     // Map groups to roles
-    let roles = map_groups_to_roles(&access_identity.groups);
+    let roles = map_groups_to_roles(&assertion.groups);
 
     // Evaluate ABAC
     let abac_ctx = AbacContext {
-        tenant: "ubl".to_string(), // TODO: from session/subject
+        tenant: assertion.tenant.clone(),
         roles: roles.clone(),
         session_type: req.session_type.clone(),
         requested_scope: req.scope.clone(),
@@ -81,23 +90,27 @@ async fn mint_token<S: IdentityStore>(
     };
 
     let response = state.token_mgr.mint(
-        access_identity.sub,
+        subject.id,
         scope,
         req.client_id,
         Some(roles),
         ttl_sec,
-    ).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"token": "INTERNAL", "remediation": ["Retry later"]})),
-        )
-    })?;
+    ).map_err(internal)?;
 
     Ok(Json(response))
 }
 
+fn internal(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("tokens: {:#}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"token": "INTERNAL", "remediation": ["Retry later"]})),
+    )
+}
+
 async fn refresh_token<S: IdentityStore>(
     State(_state): State<AppState<S>>,
+    _assertion: PolicyAssertion,
     Json(req): Json<serde_json::Value>,
 ) -> Result<Json<MintResponse>, (StatusCode, Json<serde_json::Value>)> {
     // TODO: Implement refresh token validation and mint new token
@@ -109,6 +122,7 @@ async fn refresh_token<S: IdentityStore>(
 
 async fn revoke_token<S: IdentityStore>(
     State(_state): State<AppState<S>>,
+    _assertion: PolicyAssertion,
     Json(req): Json<serde_json::Value>,
 ) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
     // TODO: Extract jti from token, call revoke
@@ -117,3 +131,72 @@ async fn revoke_token<S: IdentityStore>(
         Json(json!({"token": "NOT_IMPLEMENTED", "remediation": ["Revoke not yet implemented"]})),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::MemoryIdentityStore;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use axum::Extension;
+    use policy_assertion::{AssertionClaims, AssertionSigner, AssertionVerifier, HEADER};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn claims(who: &str) -> AssertionClaims {
+        AssertionClaims {
+            iss: String::new(), iat: 0, exp: 0,
+            jti: "8a1f-GRU".into(),
+            tenant: "ubl".into(),
+            decision: "allow_standard_access".into(),
+            trigger: "W_ZeroTrust_Standard".into(),
+            who: Some(who.into()),
+            groups: vec!["ubl-ops".into()],
+            chip: "c77f".into(),
+            did: Some("POST /tokens/mint".into()),
+        }
+    }
+
+    fn mint(assertion: Option<&str>) -> Request<Body> {
+        let body = json!({"scope": {"tenant": "ubl", "session_type": "work", "tools": ["ubl.read"]}, "session_type": "work", "client_id": "cli"});
+        let mut req = Request::post("/tokens/mint").header(header::CONTENT_TYPE, "application/json");
+        if let Some(a) = assertion {
+            req = req.header(HEADER, a);
+        }
+        req.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn status(app: &Router, req: Request<Body>) -> StatusCode {
+        app.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn mint_requires_a_valid_policy_assertion() {
+        let proxy = AssertionSigner::new(ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]), "proxy-v1".into());
+        let verifier = AssertionVerifier::new().with_key("proxy-v1", proxy.verifying_key());
+        let store = MemoryIdentityStore::new();
+        let app = routes(store.clone(), TokenManager::generate("current".into()).unwrap())
+            .layer(Extension(Arc::new(verifier)));
+
+        // Cloudflare Access headers alone are no longer trusted
+        let spoofed = mint(None);
+        let (mut parts, body) = spoofed.into_parts();
+        parts.headers.insert("Cf-Access-Authenticated-User-Email", "dan@ubl.agency".parse().unwrap());
+        parts.headers.insert("Cf-Access-Groups", "ubl-ops".parse().unwrap());
+        assert_eq!(status(&app, Request::from_parts(parts, body)).await, StatusCode::UNAUTHORIZED);
+
+        // signed by a key the gateway does not trust
+        let rogue = AssertionSigner::new(ed25519_dalek::SigningKey::from_bytes(&[8u8; 32]), "proxy-v1".into());
+        assert_eq!(status(&app, mint(Some(&rogue.sign(claims("dan@ubl.agency")).unwrap()))).await, StatusCode::UNAUTHORIZED);
+
+        // payload swapped under a valid signature
+        let token = proxy.sign(claims("dan@ubl.agency")).unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+        let swapped = base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, serde_json::to_vec(&claims("eve@ubl.agency")).unwrap());
+        assert_eq!(status(&app, mint(Some(&format!("{}.{}.{}", parts[0], swapped, parts[2])))).await, StatusCode::UNAUTHORIZED);
+
+        assert!(store.get_subject_by_email("dan@ubl.agency").await.unwrap().is_none());
+        assert_eq!(status(&app, mint(Some(&token))).await, StatusCode::OK);
+        assert!(store.get_subject_by_email("dan@ubl.agency").await.unwrap().is_some());
+    }
+}
@@ -2,7 +2,9 @@ pub mod tokens;
 pub mod access;
 pub mod abac;
 pub mod storage;
+pub mod sql;
 pub mod csrf;
+pub mod webauthn;
 
 pub use tokens::*;
 pub use access::*;
@@ -1,5 +1,5 @@
 //! Storage abstraction for identities, WebAuthn credentials, and session binds
-//! Supports D1 (Cloudflare) or Postgres
+//! Backends: memory (dev/tests), SQLite (D1-compatible schema) and Postgres — see `sql`
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -33,6 +33,7 @@ pub struct Session {
 
 #[async_trait::async_trait]
 pub trait IdentityStore: Send + Sync {
+    async fn get_subject(&self, id: &str) -> anyhow::Result<Option<Subject>>;
     async fn get_subject_by_email(&self, email: &str) -> anyhow::Result<Option<Subject>>;
     async fn create_subject(&self, email: Option<String>, tenant: String) -> anyhow::Result<Subject>;
     async fn get_webauthn_credential(&self, credential_id: &[u8]) -> anyhow::Result<Option<WebAuthnCredential>>;
@@ -40,9 +41,28 @@ pub trait IdentityStore: Send + Sync {
     async fn get_session(&self, sid: &str) -> anyhow::Result<Option<Session>>;
     async fn save_session(&self, session: Session) -> anyhow::Result<()>;
     async fn delete_session(&self, sid: &str) -> anyhow::Result<()>;
+    /// Removes sessions with `expires_at < now`; returns how many.
+    async fn sweep_expired_sessions(&self, now: i64) -> anyhow::Result<u64>;
+}
+
+/// Sweeps expired sessions every `every` for the lifetime of the process.
+pub fn spawn_session_sweeper<S: IdentityStore + 'static>(store: S, every: std::time::Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(every);
+        loop {
+            tick.tick().await;
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+            match store.sweep_expired_sessions(now).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!(swept = n, "expired sessions removed"),
+                Err(e) => tracing::warn!("session sweep failed: {:#}", e),
+            }
+        }
+    })
 }
 
 // In-memory implementation for testing
+#[derive(Clone, Default)]
 pub struct MemoryIdentityStore {
     subjects: std::sync::Arc<tokio::sync::RwLock<std::collections::HashMap<String, Subject>>>,
     credentials: std::sync::Arc<tokio::sync::RwLock<std::collections::HashMap<Vec<u8>, WebAuthnCredential>>>,
@@ -61,6 +81,10 @@ impl MemoryIdentityStore {
 
 #[async_trait::async_trait]
 impl IdentityStore for MemoryIdentityStore {
+    async fn get_subject(&self, id: &str) -> anyhow::Result<Option<Subject>> {
+        Ok(self.subjects.read().await.get(id).cloned())
+    }
+
     async fn get_subject_by_email(&self, email: &str) -> anyhow::Result<Option<Subject>> {
         let subjects = self.subjects.read().await;
         Ok(subjects.values().find(|s| s.email.as_ref() == Some(&email.to_string())).cloned())
@@ -102,4 +126,80 @@ impl IdentityStore for MemoryIdentityStore {
         self.sessions.write().await.remove(sid);
         Ok(())
     }
+
+    async fn sweep_expired_sessions(&self, now: i64) -> anyhow::Result<u64> {
+        let mut sessions = self.sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|_, s| s.expires_at >= now);
+        Ok((before - sessions.len()) as u64)
+    }
+}
+
+/// Behaviour every backend must share; each backend's tests call `run`.
+#[cfg(test)]
+pub(crate) mod conformance {
+    use super::*;
+
+    pub async fn run<S: IdentityStore>(store: &S) {
+        let now = 1_700_000_000;
+        let email = format!("{}@ubl.agency", Uuid::new_v4());
+        let dan = store.create_subject(Some(email.clone()), "ubl".into()).await.unwrap();
+        assert!(dan.id.starts_with("user:") && dan.created_at > 0);
+        let found = store.get_subject_by_email(&email).await.unwrap().unwrap();
+        assert_eq!((found.id.as_str(), found.tenant.as_str(), found.created_at), (dan.id.as_str(), "ubl", dan.created_at));
+        assert_eq!(store.get_subject(&dan.id).await.unwrap().unwrap().email.as_deref(), Some(email.as_str()));
+        assert!(store.get_subject("user:nobody").await.unwrap().is_none());
+        assert!(store.get_subject_by_email("nobody@ubl.agency").await.unwrap().is_none());
+        let agent = store.create_subject(None, "lab".into()).await.unwrap();
+        assert_ne!(agent.id, dan.id);
+        assert_eq!(store.get_subject(&agent.id).await.unwrap().unwrap().email, None);
+
+        // credencial: ida e volta byte a byte; salvar de novo atualiza o contador
+        let mut cred = WebAuthnCredential {
+            id: ulid::Ulid::new().to_string(),
+            subject_id: dan.id.clone(),
+            credential_id: Uuid::new_v4().as_bytes().to_vec(),
+            public_key: vec![4; 65],
+            counter: 0,
+            created_at: now,
+        };
+        store.save_webauthn_credential(cred.clone()).await.unwrap();
+        cred.counter = 7;
+        store.save_webauthn_credential(cred.clone()).await.unwrap();
+        let got = store.get_webauthn_credential(&cred.credential_id).await.unwrap().unwrap();
+        assert_eq!(
+            (got.id, got.subject_id, got.public_key, got.counter, got.created_at),
+            (cred.id, cred.subject_id, cred.public_key, 7, now),
+        );
+        assert!(store.get_webauthn_credential(b"unknown").await.unwrap().is_none());
+
+        // sessões: varredura tira só as vencidas
+        let session = |sid: &str, expires_at| Session {
+            sid: sid.into(),
+            subject_id: dan.id.clone(),
+            fingerprint: "fp".into(),
+            expires_at,
+            csrf_token: format!("csrf-{}", sid),
+        };
+        store.save_session(session("live", now + 3600)).await.unwrap();
+        store.save_session(session("edge", now)).await.unwrap();
+        store.save_session(session("old", now - 1)).await.unwrap();
+        let got = store.get_session("live").await.unwrap().unwrap();
+        assert_eq!((got.subject_id, got.fingerprint, got.csrf_token, got.expires_at), (dan.id.clone(), "fp".into(), "csrf-live".into(), now + 3600));
+        assert_eq!(store.sweep_expired_sessions(now).await.unwrap(), 1);
+        assert!(store.get_session("old").await.unwrap().is_none());
+        assert!(store.get_session("edge").await.unwrap().is_some());
+        assert_eq!(store.sweep_expired_sessions(now).await.unwrap(), 0);
+        store.delete_session("live").await.unwrap();
+        assert!(store.get_session("live").await.unwrap().is_none());
+        assert_eq!(store.sweep_expired_sessions(now + 3601).await.unwrap(), 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn memory_store_conformance() {
+        super::conformance::run(&super::MemoryIdentityStore::new()).await;
+    }
 }
@@ -11,7 +11,7 @@ use p256::pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey, LineEndin
 use base64::{engine::general_purpose, Engine as _};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TokenScope {
     pub tenant: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -58,6 +58,7 @@ pub struct RefreshRequest {
     pub refresh_token: String,
 }
 
+#[derive(Clone)]
 pub struct TokenManager {
     signing_key: SigningKey,
     verifying_key: VerifyingKey,
@@ -66,7 +67,7 @@ pub struct TokenManager {
 
 impl TokenManager {
     pub fn new(signing_key: SigningKey, kid: String) -> Self {
-        let verifying_key = signing_key.verifying_key();
+        let verifying_key = *signing_key.verifying_key();
         Self {
             signing_key,
             verifying_key,
@@ -76,7 +77,7 @@ impl TokenManager {
 
     pub fn from_pem(pem_bytes: &[u8], kid: String) -> anyhow::Result<Self> {
         let signing_key = SigningKey::from_pkcs8_pem(std::str::from_utf8(pem_bytes)?)?;
-        let verifying_key = signing_key.verifying_key();
+        let verifying_key = *signing_key.verifying_key();
         Ok(Self {
             signing_key,
             verifying_key,
@@ -87,7 +88,7 @@ impl TokenManager {
     pub fn generate(kid: String) -> anyhow::Result<Self> {
         use rand_core::OsRng;
         let signing_key = SigningKey::random(&mut OsRng);
-        let verifying_key = signing_key.verifying_key();
+        let verifying_key = *signing_key.verifying_key();
         Ok(Self {
             signing_key,
             verifying_key,
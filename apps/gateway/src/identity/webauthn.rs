@@ -0,0 +1,501 @@
+//! WebAuthn ceremonies (passkeys): creation/request options, attestation
+//! verification (`none`, `packed` self or x5c) and assertion verification with
+//! signature counter checks. ES256 (COSE -7) only — what platform
+//! authenticators ship. Attestation certificates are not chained to a trust
+//! anchor (no FIDO MDS): `packed` proves possession, not the device model.
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ciborium::Value as Cbor;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use x509_cert::der::{Decode, Encode};
+
+pub const COSE_ES256: i64 = -7;
+/// Pending challenges expire after this (seconds)
+pub const CHALLENGE_TTL_SEC: u64 = 300;
+const TIMEOUT_MS: u64 = 60_000;
+
+const FLAG_UP: u8 = 0x01;
+const FLAG_UV: u8 = 0x04;
+const FLAG_AT: u8 = 0x40;
+
+#[derive(Debug, Clone)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+    /// Expected `origin` in clientDataJSON
+    pub origin: String,
+    /// Require the UV flag (biometric/PIN), not just presence
+    pub require_uv: bool,
+}
+
+/// Credential verified at registration, ready for `IdentityStore::save_webauthn_credential`
+#[derive(Debug, Clone)]
+pub struct NewCredential {
+    pub credential_id: Vec<u8>,
+    /// SEC1 uncompressed P-256 point
+    pub public_key: Vec<u8>,
+    pub counter: u32,
+    pub fmt: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationResponse {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationResponse {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(default)]
+    cross_origin: bool,
+}
+
+struct AuthData {
+    rp_id_hash: [u8; 32],
+    flags: u8,
+    counter: u32,
+    /// (credential id, SEC1 public key) when the AT flag is set
+    credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl RelyingParty {
+    pub fn new(id: &str, name: &str) -> Self {
+        Self { id: id.into(), name: name.into(), origin: format!("https://{}", id), require_uv: true }
+    }
+
+    /// `WEBAUTHN_RP_ID` (default app.ubl.agency), `WEBAUTHN_RP_NAME`, `WEBAUTHN_ORIGIN` (default https://{rp_id})
+    pub fn from_env() -> Self {
+        let id = std::env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "app.ubl.agency".into());
+        let mut rp = Self::new(&id, &std::env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "UBL Agency".into()));
+        if let Ok(origin) = std::env::var("WEBAUTHN_ORIGIN") {
+            rp.origin = origin;
+        }
+        rp
+    }
+
+    /// `PublicKeyCredentialCreationOptions` (binary fields base64url)
+    pub fn creation_options(&self, challenge: &str, user_id: &[u8], name: &str, exclude: &[Vec<u8>]) -> serde_json::Value {
+        json!({
+            "publicKey": {
+                "rp": { "name": self.name, "id": self.id },
+                "user": { "id": URL_SAFE_NO_PAD.encode(user_id), "name": name, "displayName": name },
+                "challenge": challenge,
+                "pubKeyCredParams": [{ "type": "public-key", "alg": COSE_ES256 }],
+                "timeout": TIMEOUT_MS,
+                "attestation": "none",
+                "authenticatorSelection": {
+                    "residentKey": "preferred",
+                    "userVerification": self.user_verification(),
+                },
+                "excludeCredentials": exclude.iter()
+                    .map(|id| json!({ "type": "public-key", "id": URL_SAFE_NO_PAD.encode(id) }))
+                    .collect::<Vec<_>>(),
+            }
+        })
+    }
+
+    /// `PublicKeyCredentialRequestOptions`; empty allowCredentials = discoverable credentials
+    pub fn request_options(&self, challenge: &str) -> serde_json::Value {
+        json!({
+            "publicKey": {
+                "challenge": challenge,
+                "rpId": self.id,
+                "timeout": TIMEOUT_MS,
+                "userVerification": self.user_verification(),
+                "allowCredentials": [],
+            }
+        })
+    }
+
+    pub fn verify_registration(&self, resp: &RegistrationResponse, challenge: &str) -> Result<NewCredential> {
+        check_credential_ids(&resp.kind, &resp.id, &resp.raw_id)?;
+        let client_data_hash = self.check_client_data(&resp.response.client_data_json, "webauthn.create", challenge)?;
+        let att: Cbor = ciborium::from_reader(b64(&resp.response.attestation_object)?.as_slice())
+            .context("attestationObject is not CBOR")?;
+        let att = att.as_map().context("attestationObject is not a map")?;
+        let fmt = get(att, &Cbor::Text("fmt".into())).and_then(Cbor::as_text).context("missing fmt")?;
+        let stmt = get(att, &Cbor::Text("attStmt".into())).and_then(Cbor::as_map).context("missing attStmt")?;
+        let raw = get(att, &Cbor::Text("authData".into())).and_then(Cbor::as_bytes).context("missing authData")?;
+
+        let auth_data = parse_auth_data(raw)?;
+        self.check_auth_data(&auth_data)?;
+        let (credential_id, public_key) = auth_data.credential.clone().context("authData has no attested credential (AT flag)")?;
+        if URL_SAFE_NO_PAD.encode(&credential_id) != resp.raw_id.trim_end_matches('=') {
+            bail!("rawId does not match the attested credential id");
+        }
+
+        match fmt {
+            "none" => {
+                if !stmt.is_empty() {
+                    bail!("attestation fmt none must have an empty attStmt");
+                }
+            }
+            "packed" => {
+                let alg = get(stmt, &Cbor::Text("alg".into())).and_then(cbor_int).context("packed attStmt without alg")?;
+                if alg != COSE_ES256 as i128 {
+                    bail!("packed attestation alg {} is not ES256", alg);
+                }
+                let sig = get(stmt, &Cbor::Text("sig".into())).and_then(Cbor::as_bytes).context("packed attStmt without sig")?;
+                let key = match get(stmt, &Cbor::Text("x5c".into())) {
+                    // full attestation: signed by the leaf certificate's key
+                    Some(Cbor::Array(chain)) => {
+                        let leaf = chain.first().and_then(Cbor::as_bytes).context("empty x5c")?;
+                        let cert = x509_cert::Certificate::from_der(leaf).context("invalid attestation certificate")?;
+                        let spki = cert.tbs_certificate.subject_public_key_info.to_der()?;
+                        VerifyingKey::from_public_key_der(&spki).map_err(|_| anyhow::anyhow!("attestation certificate key is not P-256"))?
+                    }
+                    // self attestation: signed by the credential itself
+                    None => VerifyingKey::from_sec1_bytes(&public_key)?,
+                    Some(_) => bail!("x5c must be an array of certificates"),
+                };
+                verify_es256(&key, &[raw, client_data_hash.as_slice()].concat(), sig).context("packed attestation signature")?;
+            }
+            other => bail!("unsupported attestation format {:?} (accepted: none, packed)", other),
+        }
+        Ok(NewCredential { credential_id, public_key, counter: auth_data.counter, fmt: fmt.into() })
+    }
+
+    /// Returns the new signature counter to store.
+    pub fn verify_authentication(&self, resp: &AuthenticationResponse, challenge: &str, public_key: &[u8], stored_counter: u32) -> Result<u32> {
+        check_credential_ids(&resp.kind, &resp.id, &resp.raw_id)?;
+        let client_data_hash = self.check_client_data(&resp.response.client_data_json, "webauthn.get", challenge)?;
+        let raw = b64(&resp.response.authenticator_data)?;
+        let auth_data = parse_auth_data(&raw)?;
+        self.check_auth_data(&auth_data)?;
+        let key = VerifyingKey::from_sec1_bytes(public_key).context("stored public key is not P-256")?;
+        verify_es256(&key, &[raw.as_slice(), client_data_hash.as_slice()].concat(), &b64(&resp.response.signature)?)
+            .context("assertion signature")?;
+        // 0/0 = authenticator without a counter (allowed); otherwise it must grow
+        if (auth_data.counter != 0 || stored_counter != 0) && auth_data.counter <= stored_counter {
+            bail!("signature counter did not increase ({} <= {}): possible cloned authenticator", auth_data.counter, stored_counter);
+        }
+        Ok(auth_data.counter)
+    }
+
+    fn user_verification(&self) -> &'static str {
+        if self.require_uv { "required" } else { "preferred" }
+    }
+
+    /// Type, challenge and origin; returns SHA-256(clientDataJSON).
+    fn check_client_data(&self, client_data_json: &str, kind: &str, challenge: &str) -> Result<[u8; 32]> {
+        let raw = b64(client_data_json)?;
+        let cd: ClientData = serde_json::from_slice(&raw).context("invalid clientDataJSON")?;
+        if cd.kind != kind {
+            bail!("clientData type {:?}, expected {:?}", cd.kind, kind);
+        }
+        if cd.challenge.trim_end_matches('=') != challenge {
+            bail!("challenge mismatch");
+        }
+        if cd.origin != self.origin || cd.cross_origin {
+            bail!("origin {:?} is not {:?}", cd.origin, self.origin);
+        }
+        Ok(Sha256::digest(&raw).into())
+    }
+
+    fn check_auth_data(&self, ad: &AuthData) -> Result<()> {
+        if ad.rp_id_hash[..] != Sha256::digest(self.id.as_bytes())[..] {
+            bail!("authenticator data is for another RP ID");
+        }
+        if ad.flags & FLAG_UP == 0 {
+            bail!("user presence flag not set");
+        }
+        if self.require_uv && ad.flags & FLAG_UV == 0 {
+            bail!("user verification required");
+        }
+        Ok(())
+    }
+}
+
+/// 32 random bytes, base64url
+pub fn new_challenge() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Challenge echoed in clientDataJSON: finds the pending ceremony before verifying.
+pub fn client_challenge(client_data_json: &str) -> Result<String> {
+    let cd: ClientData = serde_json::from_slice(&b64(client_data_json)?).context("invalid clientDataJSON")?;
+    Ok(cd.challenge.trim_end_matches('=').to_string())
+}
+
+/// base64url with or without padding (browsers and libraries differ)
+pub fn b64(s: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(s.trim_end_matches('=')).context("invalid base64url")
+}
+
+fn check_credential_ids(kind: &str, id: &str, raw_id: &str) -> Result<()> {
+    if kind != "public-key" {
+        bail!("credential type {:?} is not public-key", kind);
+    }
+    if id.trim_end_matches('=') != raw_id.trim_end_matches('=') {
+        bail!("id and rawId differ");
+    }
+    Ok(())
+}
+
+fn parse_auth_data(raw: &[u8]) -> Result<AuthData> {
+    if raw.len() < 37 {
+        bail!("authenticator data too short ({} bytes)", raw.len());
+    }
+    let flags = raw[32];
+    let counter = u32::from_be_bytes([raw[33], raw[34], raw[35], raw[36]]);
+    let credential = if flags & FLAG_AT != 0 {
+        // aaguid (16) | credentialIdLength (2) | credentialId | COSE_Key
+        let rest = &raw[37..];
+        let len = rest.get(16..18).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).context("truncated attested credential data")?;
+        let id = rest.get(18..18 + len).context("truncated credential id")?.to_vec();
+        let cose: Cbor = ciborium::from_reader(&rest[18 + len..]).context("invalid COSE key")?;
+        Some((id, cose_to_sec1(&cose)?))
+    } else {
+        None
+    };
+    Ok(AuthData { rp_id_hash: raw[..32].try_into()?, flags, counter, credential })
+}
+
+/// EC2 / ES256 / P-256 COSE_Key → SEC1 uncompressed point
+fn cose_to_sec1(key: &Cbor) -> Result<Vec<u8>> {
+    let m = key.as_map().context("COSE key is not a map")?;
+    let int = |k: i64| get(m, &Cbor::Integer(k.into())).and_then(cbor_int);
+    let bytes = |k: i64| get(m, &Cbor::Integer(k.into())).and_then(Cbor::as_bytes).filter(|b| b.len() == 32);
+    if int(1) != Some(2) || int(3) != Some(COSE_ES256 as i128) || int(-1) != Some(1) {
+        bail!("only EC2 P-256 ES256 credentials are supported");
+    }
+    let (x, y) = bytes(-2).zip(bytes(-3)).context("COSE key without 32-byte x/y")?;
+    let sec1 = [&[0x04][..], x, y].concat();
+    VerifyingKey::from_sec1_bytes(&sec1).context("COSE key is not a valid P-256 point")?;
+    Ok(sec1)
+}
+
+fn get<'a>(m: &'a [(Cbor, Cbor)], key: &Cbor) -> Option<&'a Cbor> {
+    m.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+fn cbor_int(v: &Cbor) -> Option<i128> {
+    v.as_integer().map(i128::from)
+}
+
+fn verify_es256(key: &VerifyingKey, msg: &[u8], der_sig: &[u8]) -> Result<()> {
+    let sig = Signature::from_der(der_sig).context("signature is not DER ECDSA")?;
+    key.verify(msg, &sig).map_err(|_| anyhow::anyhow!("signature mismatch"))
+}
+
+/// Software authenticator for tests: what a browser + platform authenticator send.
+#[cfg(test)]
+pub(crate) mod soft {
+    use super::*;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    pub struct SoftAuthenticator {
+        key: SigningKey,
+        pub credential_id: Vec<u8>,
+        pub counter: u32,
+        pub rp_id: String,
+        pub origin: String,
+        pub flags: u8,
+    }
+
+    impl SoftAuthenticator {
+        pub fn new(rp: &RelyingParty, seed: u8) -> Self {
+            Self {
+                key: SigningKey::from_slice(&[seed.max(1); 32]).unwrap(),
+                credential_id: vec![seed; 16],
+                counter: 0,
+                rp_id: rp.id.clone(),
+                origin: rp.origin.clone(),
+                flags: FLAG_UP | FLAG_UV,
+            }
+        }
+
+        fn cose_key(&self) -> Vec<u8> {
+            let point = self.key.verifying_key().to_encoded_point(false);
+            let int = |v: i64| Cbor::Integer(v.into());
+            let key = Cbor::Map(vec![
+                (int(1), int(2)),
+                (int(3), int(COSE_ES256)),
+                (int(-1), int(1)),
+                (int(-2), Cbor::Bytes(point.x().unwrap().to_vec())),
+                (int(-3), Cbor::Bytes(point.y().unwrap().to_vec())),
+            ]);
+            let mut out = Vec::new();
+            ciborium::into_writer(&key, &mut out).unwrap();
+            out
+        }
+
+        fn auth_data(&self, attested: bool) -> Vec<u8> {
+            let mut out = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+            out.push(self.flags | if attested { FLAG_AT } else { 0 });
+            out.extend(self.counter.to_be_bytes());
+            if attested {
+                out.extend([0u8; 16]);
+                out.extend((self.credential_id.len() as u16).to_be_bytes());
+                out.extend(&self.credential_id);
+                out.extend(self.cose_key());
+            }
+            out
+        }
+
+        fn client_data(&self, kind: &str, challenge: &str) -> Vec<u8> {
+            serde_json::to_vec(&json!({ "type": kind, "challenge": challenge, "origin": self.origin, "crossOrigin": false })).unwrap()
+        }
+
+        fn sign(&self, auth_data: &[u8], client_data: &[u8]) -> Vec<u8> {
+            let sig: Signature = self.key.sign(&[auth_data, &Sha256::digest(client_data)[..]].concat());
+            sig.to_der().as_bytes().to_vec()
+        }
+
+        /// `navigator.credentials.create()` → JSON; fmt `none` or `packed` (self attestation)
+        pub fn register(&self, challenge: &str, fmt: &str) -> serde_json::Value {
+            let auth_data = self.auth_data(true);
+            let client_data = self.client_data("webauthn.create", challenge);
+            let stmt = match fmt {
+                "packed" => vec![
+                    (Cbor::Text("alg".into()), Cbor::Integer(COSE_ES256.into())),
+                    (Cbor::Text("sig".into()), Cbor::Bytes(self.sign(&auth_data, &client_data))),
+                ],
+                _ => vec![],
+            };
+            let att = Cbor::Map(vec![
+                (Cbor::Text("fmt".into()), Cbor::Text(fmt.into())),
+                (Cbor::Text("attStmt".into()), Cbor::Map(stmt)),
+                (Cbor::Text("authData".into()), Cbor::Bytes(auth_data)),
+            ]);
+            let mut att_bytes = Vec::new();
+            ciborium::into_writer(&att, &mut att_bytes).unwrap();
+            let id = URL_SAFE_NO_PAD.encode(&self.credential_id);
+            json!({
+                "id": id, "rawId": id, "type": "public-key",
+                "response": {
+                    "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                    "attestationObject": URL_SAFE_NO_PAD.encode(att_bytes),
+                }
+            })
+        }
+
+        /// `navigator.credentials.get()` → JSON; bumps the counter like hardware does
+        pub fn login(&mut self, challenge: &str, user_handle: &[u8]) -> serde_json::Value {
+            self.counter += 1;
+            let auth_data = self.auth_data(false);
+            let client_data = self.client_data("webauthn.get", challenge);
+            let id = URL_SAFE_NO_PAD.encode(&self.credential_id);
+            json!({
+                "id": id, "rawId": id, "type": "public-key",
+                "response": {
+                    "clientDataJSON": URL_SAFE_NO_PAD.encode(&client_data),
+                    "authenticatorData": URL_SAFE_NO_PAD.encode(&auth_data),
+                    "signature": URL_SAFE_NO_PAD.encode(self.sign(&auth_data, &client_data)),
+                    "userHandle": URL_SAFE_NO_PAD.encode(user_handle),
+                }
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::soft::SoftAuthenticator;
+    use super::*;
+
+    fn rp() -> RelyingParty {
+        RelyingParty::new("app.ubl.agency", "UBL Agency")
+    }
+
+    #[test]
+    fn registers_with_none_and_packed_and_rejects_tampering() {
+        let rp = rp();
+        for fmt in ["none", "packed"] {
+            let auth = SoftAuthenticator::new(&rp, 3);
+            let challenge = new_challenge();
+            let resp: RegistrationResponse = serde_json::from_value(auth.register(&challenge, fmt)).unwrap();
+            assert_eq!(client_challenge(&resp.response.client_data_json).unwrap(), challenge);
+            let cred = rp.verify_registration(&resp, &challenge).unwrap();
+            assert_eq!((cred.credential_id, cred.fmt.as_str(), cred.public_key.len()), (vec![3; 16], fmt, 65));
+            assert!(rp.verify_registration(&resp, &new_challenge()).unwrap_err().to_string().contains("challenge"));
+        }
+
+        let mut other_origin = SoftAuthenticator::new(&rp, 3);
+        other_origin.origin = "https://evil.example".into();
+        let challenge = new_challenge();
+        let resp: RegistrationResponse = serde_json::from_value(other_origin.register(&challenge, "none")).unwrap();
+        assert!(rp.verify_registration(&resp, &challenge).unwrap_err().to_string().contains("origin"));
+
+        let mut no_uv = SoftAuthenticator::new(&rp, 3);
+        no_uv.flags = FLAG_UP;
+        let resp: RegistrationResponse = serde_json::from_value(no_uv.register(&challenge, "none")).unwrap();
+        assert!(rp.verify_registration(&resp, &challenge).unwrap_err().to_string().contains("user verification"));
+
+        // packed: clientDataJSON válido, mas não o que foi assinado
+        let mut v = SoftAuthenticator::new(&rp, 3).register(&challenge, "packed");
+        let other = json!({ "type": "webauthn.create", "challenge": challenge, "origin": rp.origin, "extra": 1 });
+        v["response"]["clientDataJSON"] = URL_SAFE_NO_PAD.encode(other.to_string()).into();
+        let resp: RegistrationResponse = serde_json::from_value(v).unwrap();
+        assert!(format!("{:#}", rp.verify_registration(&resp, &challenge).unwrap_err()).contains("packed attestation signature"));
+    }
+
+    #[test]
+    fn assertion_checks_signature_and_counter() {
+        let rp = rp();
+        let mut auth = SoftAuthenticator::new(&rp, 7);
+        let challenge = new_challenge();
+        let reg: RegistrationResponse = serde_json::from_value(auth.register(&challenge, "none")).unwrap();
+        let cred = rp.verify_registration(&reg, &challenge).unwrap();
+
+        let challenge = new_challenge();
+        let first: AuthenticationResponse = serde_json::from_value(auth.login(&challenge, b"user:1")).unwrap();
+        assert_eq!(rp.verify_authentication(&first, &challenge, &cred.public_key, cred.counter).unwrap(), 1);
+        // mesma asserção de novo (ou clone com contador parado): recusada
+        assert!(rp.verify_authentication(&first, &challenge, &cred.public_key, 1).unwrap_err().to_string().contains("counter"));
+
+        let challenge = new_challenge();
+        let second: AuthenticationResponse = serde_json::from_value(auth.login(&challenge, b"user:1")).unwrap();
+        assert_eq!(rp.verify_authentication(&second, &challenge, &cred.public_key, 1).unwrap(), 2);
+        let stranger = SoftAuthenticator::new(&rp, 8);
+        let other_key = rp.verify_registration(&serde_json::from_value(stranger.register(&challenge, "none")).unwrap(), &challenge).unwrap().public_key;
+        assert!(rp.verify_authentication(&second, &challenge, &other_key, 1).unwrap_err().to_string().contains("signature"));
+        assert!(rp.verify_authentication(&second, "other", &cred.public_key, 1).is_err());
+    }
+}
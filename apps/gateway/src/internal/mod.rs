@@ -1,3 +1,5 @@
 pub mod verify;
 
 pub use verify::*;
+/// Identidade assinada pelo policy-proxy (X-Ubl-Assertion)
+pub use policy_assertion::extract::PolicyAssertion;
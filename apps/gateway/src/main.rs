@@ -11,20 +11,42 @@ mod internal;
 async fn main() {
     tracing_subscriber::fmt().with_env_filter(EnvFilter::from_default_env()).init();
     
-    // Initialize identity store (memory for now)
-    let store = identity::storage::MemoryIdentityStore::new();
-    
+    // Identity store: IDENTITY_DATABASE_URL = sqlite://… | postgres://…; sem ela, memória (dev)
+    match std::env::var("IDENTITY_DATABASE_URL") {
+        Ok(url) if url.starts_with("sqlite:") => {
+            serve(identity::sql::SqliteIdentityStore::connect(&url).await.expect("sqlite identity store")).await
+        }
+        Ok(url) => serve(identity::sql::PgIdentityStore::connect(&url).await.expect("postgres identity store")).await,
+        Err(_) => {
+            tracing::warn!("IDENTITY_DATABASE_URL not set; identities and sessions are kept in memory");
+            serve(identity::storage::MemoryIdentityStore::new()).await
+        }
+    }
+}
+
+async fn serve<S: identity::IdentityStore + Clone + 'static>(store: S) {
+    // Sessões vencidas saem a cada SESSION_SWEEP_SEC (padrão 300)
+    let sweep_sec = std::env::var("SESSION_SWEEP_SEC").ok().and_then(|v| v.parse().ok()).unwrap_or(300);
+    identity::storage::spawn_session_sweeper(store.clone(), std::time::Duration::from_secs(sweep_sec));
+
     // Initialize token manager (generate key for demo)
-    use ed25519_dalek::SigningKey;
-    use rand::rngs::OsRng;
-    let mut rng = OsRng;
-    let signing_key = SigningKey::generate(&mut rng);
-    let token_mgr = identity::tokens::TokenManager::new(signing_key, "current".into());
+    let token_mgr = identity::tokens::TokenManager::generate("current".into()).expect("token key");
     
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/mcp", get(mcp::server::ws_upgrade))
-        .merge(http::routes_auth::routes(store.clone()))
+        .merge(http::routes_auth::routes(store.clone(), identity::webauthn::RelyingParty::from_env()))
         .merge(http::routes_tokens::routes(store.clone(), token_mgr));
+
+    // Verificador de X-Ubl-Assertion (policy-proxy) para internal::PolicyAssertion
+    match policy_assertion::AssertionVerifier::from_env().expect("invalid POLICY_ASSERTION_PUBKEY_PEM_B64") {
+        Some(verifier) => app = app.layer(axum::Extension(std::sync::Arc::new(verifier))),
+        None => tracing::warn!("POLICY_ASSERTION_PUBKEY_PEM_B64 not set; PolicyAssertion will reject requests"),
+    }
+    // Prova de step-up (cookie ubl_step_up) que o policy-proxy aceita nas saídas challenge
+    match policy_assertion::step_up::StepUpSigner::from_env().expect("invalid STEP_UP_KEY_PEM") {
+        Some(signer) => app = app.layer(axum::Extension(std::sync::Arc::new(signer))),
+        None => tracing::warn!("STEP_UP_KEY_PEM not set; passkey logins will not satisfy policy-proxy challenges"),
+    }
     
     let addr = SocketAddr::from(([127,0,0,1], 8080));
     tracing::info!("Gateway MCP + Identity listening on {}", addr);
@@ -0,0 +1,44 @@
+//! Extractor Axum: exige `X-Ubl-Assertion` válida.
+//! O verificador entra como `Extension(Arc<AssertionVerifier>)`, então serve
+//! para qualquer tipo de state (core-api, gateway).
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use std::sync::Arc;
+
+use crate::{AssertionClaims, AssertionVerifier, HEADER};
+
+/// Identidade decidida e assinada pelo policy-proxy
+pub struct PolicyAssertion(pub AssertionClaims);
+
+pub struct AssertionRejection(StatusCode, String);
+
+impl IntoResponse for AssertionRejection {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({"error": "policy_assertion_invalid", "detail": self.1}))).into_response()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for PolicyAssertion
+where
+    S: Send + Sync,
+{
+    type Rejection = AssertionRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let verifier = parts.extensions.get::<Arc<AssertionVerifier>>().ok_or_else(|| {
+            AssertionRejection(StatusCode::INTERNAL_SERVER_ERROR, "assertion verifier not configured".into())
+        })?;
+        let token = parts.headers.get(HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| AssertionRejection(StatusCode::UNAUTHORIZED, format!("{} required", HEADER)))?;
+        verifier.verify(token)
+            .map(PolicyAssertion)
+            .map_err(|e| AssertionRejection(StatusCode::UNAUTHORIZED, e.to_string()))
+    }
+}
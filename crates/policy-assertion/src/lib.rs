@@ -0,0 +1,225 @@
+//! Asserção de identidade assinada pelo policy-proxy (JWS compacto, EdDSA/Ed25519)
+//! O proxy decide, assina e encaminha em `X-Ubl-Assertion`; upstreams verificam
+//! antes de confiar em `X-Who`/`X-Auth-*`.
+
+#[cfg(feature = "axum")]
+pub mod extract;
+pub mod step_up;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header HTTP que carrega a asserção
+pub const HEADER: &str = "X-Ubl-Assertion";
+/// `typ` do header JWS
+pub const TYP: &str = "ubl-policy+jws";
+/// Emissor fixo
+pub const ISSUER: &str = "policy-proxy";
+/// Validade padrão (segundos): cobre só o salto proxy → upstream
+pub const DEFAULT_TTL_SEC: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AssertionClaims {
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+    /// id da request (CF-Ray ou gerado pelo proxy)
+    pub jti: String,
+    pub tenant: String,
+    pub decision: String,
+    pub trigger: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub who: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// BLAKE3 do chip que decidiu
+    pub chip: String,
+    /// método + path autorizados (ex.: "GET /core/x")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JwsHeader {
+    alg: String,
+    typ: String,
+    kid: String,
+}
+
+pub fn now_epoch() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// Lado do proxy
+pub struct AssertionSigner {
+    key: SigningKey,
+    kid: String,
+    ttl_sec: i64,
+}
+
+impl AssertionSigner {
+    pub fn new(key: SigningKey, kid: String) -> Self {
+        Self { key, kid, ttl_sec: DEFAULT_TTL_SEC }
+    }
+
+    /// PKCS#8 PEM (mesmo formato do policy-keygen)
+    pub fn from_pkcs8_pem(pem: &str, kid: String) -> anyhow::Result<Self> {
+        let key = SigningKey::from_pkcs8_pem(pem).map_err(|e| anyhow::anyhow!("invalid assertion key: {}", e))?;
+        Ok(Self::new(key, kid))
+    }
+
+    pub fn with_ttl(mut self, ttl_sec: i64) -> Self {
+        self.ttl_sec = ttl_sec;
+        self
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Preenche `iss`/`iat`/`exp` e devolve o JWS compacto.
+    pub fn sign(&self, mut claims: AssertionClaims) -> anyhow::Result<String> {
+        let now = now_epoch();
+        claims.iss = ISSUER.into();
+        claims.iat = now;
+        claims.exp = now + self.ttl_sec;
+        sign_jws(&self.key, &self.kid, TYP, &claims)
+    }
+}
+
+/// JWS compacto EdDSA com `typ` e `kid` no header.
+pub(crate) fn sign_jws<T: Serialize>(key: &SigningKey, kid: &str, typ: &str, claims: &T) -> anyhow::Result<String> {
+    let header = JwsHeader { alg: "EdDSA".into(), typ: typ.into(), kid: kid.into() };
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?),
+    );
+    let sig = key.sign(signing_input.as_bytes());
+    Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(sig.to_bytes())))
+}
+
+/// Confere formato, `alg`/`typ` e assinatura pelo `kid`; validade fica com quem chama.
+pub(crate) fn verify_jws<T: DeserializeOwned>(keys: &HashMap<String, VerifyingKey>, typ: &str, token: &str) -> anyhow::Result<T> {
+    let mut parts = token.split('.');
+    let (Some(h), Some(p), Some(s), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        anyhow::bail!("malformed assertion");
+    };
+    let header: JwsHeader = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(h)?)?;
+    if header.alg != "EdDSA" || header.typ != typ {
+        anyhow::bail!("unsupported assertion alg/typ: {}/{}", header.alg, header.typ);
+    }
+    let key = keys.get(&header.kid).ok_or_else(|| anyhow::anyhow!("unknown assertion kid: {}", header.kid))?;
+    let sig_bytes: [u8; 64] = URL_SAFE_NO_PAD.decode(s)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid signature length"))?;
+    key.verify(format!("{}.{}", h, p).as_bytes(), &Signature::from_bytes(&sig_bytes))
+        .map_err(|_| anyhow::anyhow!("assertion signature mismatch"))?;
+    Ok(serde_json::from_slice(&URL_SAFE_NO_PAD.decode(p)?)?)
+}
+
+pub(crate) fn public_key_from_pem(pem: &str) -> anyhow::Result<VerifyingKey> {
+    VerifyingKey::from_public_key_pem(pem).map_err(|e| anyhow::anyhow!("invalid assertion public key: {}", e))
+}
+
+/// PEM SPKI em base64 na variável `var`; `None` se ela não estiver definida.
+pub(crate) fn public_pem_from_env(var: &str) -> anyhow::Result<Option<String>> {
+    use base64::engine::general_purpose::STANDARD;
+    let Ok(b64) = std::env::var(var) else { return Ok(None) };
+    Ok(Some(String::from_utf8(STANDARD.decode(b64.trim())?)?))
+}
+
+/// Lado do upstream: chaves públicas do(s) proxy(s) por kid
+#[derive(Clone, Default)]
+pub struct AssertionVerifier {
+    keys: HashMap<String, VerifyingKey>,
+    leeway_sec: i64,
+}
+
+impl AssertionVerifier {
+    pub fn new() -> Self {
+        Self { keys: HashMap::new(), leeway_sec: 5 }
+    }
+
+    pub fn with_key(mut self, kid: impl Into<String>, key: VerifyingKey) -> Self {
+        self.keys.insert(kid.into(), key);
+        self
+    }
+
+    /// SPKI PEM (ex.: assertion_public.pem do policy-keygen)
+    pub fn with_public_pem(self, kid: impl Into<String>, pem: &str) -> anyhow::Result<Self> {
+        Ok(self.with_key(kid, public_key_from_pem(pem)?))
+    }
+
+    /// `POLICY_ASSERTION_PUBKEY_PEM_B64` + `POLICY_ASSERTION_KID` (padrão "proxy-v1");
+    /// `None` se a variável não estiver definida.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(pem) = public_pem_from_env("POLICY_ASSERTION_PUBKEY_PEM_B64")? else { return Ok(None) };
+        let kid = std::env::var("POLICY_ASSERTION_KID").unwrap_or_else(|_| "proxy-v1".into());
+        Ok(Some(Self::new().with_public_pem(kid, &pem)?))
+    }
+
+    pub fn verify(&self, token: &str) -> anyhow::Result<AssertionClaims> {
+        self.verify_at(token, now_epoch())
+    }
+
+    pub fn verify_at(&self, token: &str, now: i64) -> anyhow::Result<AssertionClaims> {
+        let claims: AssertionClaims = verify_jws(&self.keys, TYP, token)?;
+        if claims.iss != ISSUER {
+            anyhow::bail!("unexpected assertion issuer: {}", claims.iss);
+        }
+        if now > claims.exp + self.leeway_sec || claims.iat > now + self.leeway_sec {
+            anyhow::bail!("assertion expired or not yet valid");
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn claims() -> AssertionClaims {
+        AssertionClaims {
+            iss: String::new(), iat: 0, exp: 0,
+            jti: "8a1f-GRU".into(),
+            tenant: "ubl".into(),
+            decision: "allow_standard_access".into(),
+            trigger: "W_ZeroTrust_Standard".into(),
+            who: Some("dan@ubl.agency".into()),
+            groups: vec!["ubl-ops".into()],
+            chip: "c77f".into(),
+            did: Some("GET /core/x".into()),
+        }
+    }
+
+    #[test]
+    fn roundtrip_and_tamper() {
+        let signer = AssertionSigner::new(SigningKey::from_bytes(&[7u8; 32]), "proxy-v1".into());
+        let verifier = AssertionVerifier::new().with_key("proxy-v1", signer.verifying_key());
+        let token = signer.sign(claims()).unwrap();
+        let got = verifier.verify(&token).unwrap();
+        assert_eq!(got.who.as_deref(), Some("dan@ubl.agency"));
+        assert_eq!(got.exp - got.iat, DEFAULT_TTL_SEC);
+
+        // payload trocado por outro quem
+        let mut forged = claims();
+        forged.who = Some("attacker@evil".into());
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        assert!(verifier.verify(&format!("{}.{}.{}", parts[0], forged_payload, parts[2])).is_err());
+
+        // expirada
+        assert!(verifier.verify_at(&token, got.exp + 60).is_err());
+        // kid desconhecido
+        assert!(AssertionVerifier::new().verify(&token).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Prova de step-up: o gateway assina depois de uma asserção WebAuthn bem
+//! sucedida e entrega em cookie (`ubl_step_up`) ou header (`X-Ubl-Step-Up`);
+//! o policy-proxy verifica e reavalia o chip com `auth.method = webauthn`.
+//! Mesmo formato da asserção (JWS EdDSA), com outro `typ` e emissor — e
+//! chaves no sentido inverso (gateway assina, proxy verifica).
+
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{now_epoch, public_key_from_pem, public_pem_from_env, sign_jws, verify_jws};
+
+pub const COOKIE: &str = "ubl_step_up";
+pub const HEADER: &str = "X-Ubl-Step-Up";
+pub const TYP: &str = "ubl-step-up+jws";
+pub const ISSUER: &str = "gateway";
+/// Validade padrão (segundos): MFA sob demanda, não sessão longa
+pub const DEFAULT_TTL_SEC: i64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepUpClaims {
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+    /// Quem passou pela passkey (o mesmo `who` que o proxy vê)
+    pub sub: String,
+    pub rp_id: String,
+    /// credential id (base64url) usado na asserção
+    pub cred: String,
+}
+
+/// Lado do gateway
+pub struct StepUpSigner {
+    key: SigningKey,
+    kid: String,
+    ttl_sec: i64,
+}
+
+impl StepUpSigner {
+    pub fn new(key: SigningKey, kid: String) -> Self {
+        Self { key, kid, ttl_sec: DEFAULT_TTL_SEC }
+    }
+
+    pub fn from_pkcs8_pem(pem: &str, kid: String) -> anyhow::Result<Self> {
+        let key = SigningKey::from_pkcs8_pem(pem).map_err(|e| anyhow::anyhow!("invalid step-up key: {}", e))?;
+        Ok(Self::new(key, kid))
+    }
+
+    /// `STEP_UP_KEY_PEM` (caminho do PKCS#8 PEM) + `STEP_UP_KID` (padrão "gateway-v1");
+    /// `None` se a variável não estiver definida.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(path) = std::env::var("STEP_UP_KEY_PEM") else { return Ok(None) };
+        let pem = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        let kid = std::env::var("STEP_UP_KID").unwrap_or_else(|_| "gateway-v1".into());
+        Ok(Some(Self::from_pkcs8_pem(&pem, kid)?))
+    }
+
+    pub fn with_ttl(mut self, ttl_sec: i64) -> Self {
+        self.ttl_sec = ttl_sec;
+        self
+    }
+
+    pub fn ttl_sec(&self) -> i64 {
+        self.ttl_sec
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    pub fn sign(&self, sub: &str, rp_id: &str, cred: &str) -> anyhow::Result<String> {
+        let now = now_epoch();
+        let claims = StepUpClaims {
+            iss: ISSUER.into(),
+            iat: now,
+            exp: now + self.ttl_sec,
+            sub: sub.into(),
+            rp_id: rp_id.into(),
+            cred: cred.into(),
+        };
+        sign_jws(&self.key, &self.kid, TYP, &claims)
+    }
+}
+
+/// Lado do proxy
+#[derive(Clone, Default)]
+pub struct StepUpVerifier {
+    keys: HashMap<String, VerifyingKey>,
+    leeway_sec: i64,
+}
+
+impl StepUpVerifier {
+    pub fn new() -> Self {
+        Self { keys: HashMap::new(), leeway_sec: 5 }
+    }
+
+    pub fn with_key(mut self, kid: impl Into<String>, key: VerifyingKey) -> Self {
+        self.keys.insert(kid.into(), key);
+        self
+    }
+
+    /// `POLICY_STEP_UP_PUBKEY_PEM_B64` + `POLICY_STEP_UP_KID` (padrão "gateway-v1");
+    /// `None` se a variável não estiver definida.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(pem) = public_pem_from_env("POLICY_STEP_UP_PUBKEY_PEM_B64")? else { return Ok(None) };
+        let kid = std::env::var("POLICY_STEP_UP_KID").unwrap_or_else(|_| "gateway-v1".into());
+        Ok(Some(Self::new().with_key(kid, public_key_from_pem(&pem)?)))
+    }
+
+    /// Prova válida agora e emitida para `sub`.
+    pub fn verify(&self, token: &str, sub: &str) -> anyhow::Result<StepUpClaims> {
+        self.verify_at(token, sub, now_epoch())
+    }
+
+    pub fn verify_at(&self, token: &str, sub: &str, now: i64) -> anyhow::Result<StepUpClaims> {
+        let claims: StepUpClaims = verify_jws(&self.keys, TYP, token)?;
+        if claims.iss != ISSUER {
+            anyhow::bail!("unexpected step-up issuer: {}", claims.iss);
+        }
+        if now > claims.exp + self.leeway_sec || claims.iat > now + self.leeway_sec {
+            anyhow::bail!("step-up expired or not yet valid");
+        }
+        if claims.sub != sub {
+            anyhow::bail!("step-up issued for another subject");
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssertionSigner, AssertionVerifier};
+
+    #[test]
+    fn binds_subject_and_does_not_mix_with_assertions() {
+        let signer = StepUpSigner::new(SigningKey::from_bytes(&[9u8; 32]), "gateway-v1".into());
+        let verifier = StepUpVerifier::new().with_key("gateway-v1", signer.verifying_key());
+        let token = signer.sign("dan@ubl.agency", "app.ubl.agency", "Y3JlZA").unwrap();
+        let got = verifier.verify(&token, "dan@ubl.agency").unwrap();
+        assert_eq!((got.rp_id.as_str(), got.exp - got.iat), ("app.ubl.agency", DEFAULT_TTL_SEC));
+        assert!(verifier.verify(&token, "eve@ubl.agency").is_err());
+        assert!(verifier.verify_at(&token, "dan@ubl.agency", got.exp + 60).is_err());
+
+        // mesma chave, typ diferente: uma prova de step-up não vale como asserção (e vice-versa)
+        let as_assertion = AssertionVerifier::new().with_key("gateway-v1", signer.verifying_key());
+        assert!(as_assertion.verify(&token).is_err());
+        let proxy = AssertionSigner::new(SigningKey::from_bytes(&[9u8; 32]), "gateway-v1".into());
+        let assertion = proxy.sign(crate::tests::claims()).unwrap();
+        assert!(verifier.verify(&assertion, "dan@ubl.agency").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Análise exaustiva do chip: cada bit com avaliador vira uma variável
+//! booleana livre e a tabela-verdade inteira passa pela mesma fiação de
+//! `decide`. Relata saídas que nunca disparam, saídas sombreadas por
+//! anteriores e atribuições que violam os `invariants` do chip.
+//!
+//! Os bits são tratados como independentes; uma atribuição pode não ter
+//! contexto real correspondente (o resultado é conservador).
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{eval_wire_rec, lint, split_trigger, SemanticChip, WiringStructure, BUILTIN_BITS};
+
+/// 2^20 atribuições; acima disso a enumeração deixa de ser instantânea.
+const MAX_VARIABLES: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Analysis {
+    /// Bits tratados como variáveis (referenciados na fiação e com avaliador)
+    pub variables: Vec<String>,
+    /// Referenciados mas sem avaliador: sempre `false`
+    pub constant_false: Vec<String>,
+    pub assignments: usize,
+    pub outputs: Vec<OutputReach>,
+    /// Atribuições em que nenhuma saída dispara (default deny)
+    pub default_deny: usize,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputReach {
+    pub output: String,
+    pub reach: Reach,
+    /// Atribuições em que o trigger é verdadeiro
+    pub fires: usize,
+    /// Atribuições em que esta saída é a decisão
+    pub decides: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "status", content = "by")]
+pub enum Reach {
+    Reachable,
+    /// O trigger nunca é verdadeiro
+    Unreachable,
+    /// O trigger dispara, mas sempre depois de uma saída anterior (listadas)
+    Shadowed(Vec<String>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub invariant: String,
+    pub output: String,
+    /// Atribuições que violam (contra-exemplo em `example`)
+    pub count: usize,
+    pub example: BTreeMap<String, bool>,
+}
+
+pub fn analyze(chip: &SemanticChip) -> anyhow::Result<Analysis> {
+    // a fiação cíclica não termina no avaliador
+    if let Some(d) = lint(chip).into_iter().find(|d| d.code == "cycle") {
+        anyhow::bail!("{}", d);
+    }
+
+    let referenced: BTreeSet<&str> = chip.wiring.iter().flat_map(|w| match &w.structure {
+        WiringStructure::Sequence { sequence } => sequence.iter().filter(|x| !x.starts_with("W_")).collect::<Vec<_>>(),
+        WiringStructure::Parallel { parallel } => parallel.policies.iter().collect(),
+    }).map(String::as_str).filter(|x| chip.find_wire(x).is_none()).collect();
+    let (variables, constant_false): (Vec<&str>, Vec<&str>) = referenced.iter().copied().partition(|b| BUILTIN_BITS.contains(b));
+    if variables.len() > MAX_VARIABLES {
+        anyhow::bail!("{} bit variables; exhaustive analysis is limited to {}", variables.len(), MAX_VARIABLES);
+    }
+
+    let outputs: Vec<(&str, &str)> = chip.outputs.iter().flat_map(|o| o.0.iter()).map(|(k, a)| (k.as_str(), a.trigger.as_str())).collect();
+    for inv in &chip.invariants {
+        for pat in &inv.outputs {
+            if !outputs.iter().any(|(name, _)| matches(pat, name)) {
+                anyhow::bail!("invariant {}: {} matches no output", inv.id, pat);
+            }
+        }
+        for req in &inv.require {
+            let (_, bit) = split_trigger(req);
+            if !referenced.contains(bit) {
+                anyhow::bail!("invariant {}: {} is not a bit used by the wiring", inv.id, bit);
+            }
+        }
+    }
+
+    let mut fires = vec![0; outputs.len()];
+    let mut decides = vec![0; outputs.len()];
+    // saída sombreada → saídas anteriores que decidiram quando ela também disparava
+    let mut winners: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); outputs.len()];
+    let mut default_deny = 0;
+    let mut violations: BTreeMap<(usize, usize), Violation> = BTreeMap::new();
+
+    let assignments = 1usize << variables.len();
+    for n in 0..assignments {
+        let value = |b: &str| variables.iter().position(|v| *v == b).is_some_and(|i| (n >> i) & 1 == 1);
+        let fired: Vec<bool> = outputs.iter().map(|(_, trigger)| {
+            let (negated, wire) = split_trigger(trigger);
+            eval_wire_rec(chip, wire, &value, &mut vec![]) != negated
+        }).collect();
+        let Some(decided) = fired.iter().position(|f| *f) else {
+            default_deny += 1;
+            continue;
+        };
+        decides[decided] += 1;
+        for i in (0..fired.len()).filter(|&i| fired[i]) {
+            fires[i] += 1;
+            if i > decided {
+                winners[i].insert(decided);
+            }
+        }
+
+        let (name, _) = outputs[decided];
+        for (k, inv) in chip.invariants.iter().enumerate() {
+            if !inv.outputs.iter().any(|p| matches(p, name)) {
+                continue;
+            }
+            let holds = inv.require.iter().all(|r| {
+                let (negated, bit) = split_trigger(r);
+                value(bit) != negated
+            });
+            if !holds {
+                violations.entry((k, decided)).or_insert_with(|| Violation {
+                    invariant: inv.id.clone(),
+                    output: name.to_string(),
+                    count: 0,
+                    example: variables.iter().map(|v| (v.to_string(), value(v))).collect(),
+                }).count += 1;
+            }
+        }
+    }
+
+    let outputs = outputs.iter().enumerate().map(|(i, (name, _))| OutputReach {
+        output: name.to_string(),
+        reach: if fires[i] == 0 {
+            Reach::Unreachable
+        } else if decides[i] == 0 {
+            Reach::Shadowed(winners[i].iter().map(|w| outputs[*w].0.to_string()).collect())
+        } else {
+            Reach::Reachable
+        },
+        fires: fires[i],
+        decides: decides[i],
+    }).collect();
+
+    Ok(Analysis {
+        variables: variables.iter().map(|v| v.to_string()).collect(),
+        constant_false: constant_false.iter().map(|v| v.to_string()).collect(),
+        assignments,
+        outputs,
+        default_deny,
+        violations: violations.into_values().collect(),
+    })
+}
+
+/// Nome exato ou prefixo terminado em `*`.
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_chip_is_reachable_and_holds_invariants() {
+        let chip = SemanticChip::from_yaml(&crate::shipped_yaml(include_str!("../../../policies/ubl_core_v1.yaml"))).unwrap();
+        let a = analyze(&chip).unwrap();
+        assert!(a.outputs.iter().all(|o| o.reach == Reach::Reachable), "{:?}", a.outputs);
+        assert!(a.violations.is_empty(), "{:?}", a.violations);
+        assert_eq!(a.default_deny, 0);
+    }
+
+    #[test]
+    fn reports_shadowed_unreachable_and_violations() {
+        let yaml = r#"
+version: "tdln-chip/0.1"
+policies: []
+wiring:
+  - id: W_Tls
+    structure: { sequence: [P_Transport_Secure] }
+  - id: W_Admin
+    structure: { sequence: [W_Tls, P_Role_Admin] }
+  - id: W_Panic
+    structure: { parallel: { policies: [P_Circuit_Breaker, P_Role_Admin], aggregator: ANY } }
+outputs:
+  - allow_any: { trigger: W_Panic, action: "200" }
+  - allow_admin: { trigger: W_Admin, action: "200" }
+  - deny_rate: { trigger: P_Rate_Bucket_OK, action: "429" }
+  - deny: { trigger: NOT(W_Tls), action: "403" }
+invariants:
+  - id: tls
+    outputs: ["allow_*"]
+    require: [P_Transport_Secure]
+"#;
+        let a = analyze(&SemanticChip::from_yaml(yaml).unwrap()).unwrap();
+        let reach: BTreeMap<&str, &Reach> = a.outputs.iter().map(|o| (o.output.as_str(), &o.reach)).collect();
+        assert_eq!(reach["allow_any"], &Reach::Reachable);
+        assert_eq!(reach["allow_admin"], &Reach::Shadowed(vec!["allow_any".into()]));
+        assert_eq!(reach["deny_rate"], &Reach::Unreachable);
+        assert_eq!(a.violations.len(), 1);
+        let v = &a.violations[0];
+        assert_eq!((v.output.as_str(), v.count), ("allow_any", 3));
+        assert!(!v.example["P_Transport_Secure"]);
+
+        let bad = yaml.replace("require: [P_Transport_Secure]", "require: [P_Typo]");
+        assert!(analyze(&SemanticChip::from_yaml(&bad).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! Cache de decisões por chip. A decisão só depende do valor dos bits, então
+//! a chave é o valor de cada bit alcançável do chip (com os `params` dele),
+//! calculado pelo próprio `bit_eval`: requests que diferem em `who`, `req_id`
+//! ou num campo que nenhum bit lê caem na mesma entrada, e nenhuma diferença
+//! que o avaliador enxerga (ex.: `rate` ausente vs `rate.ok` ausente) se perde.
+//! Um cache vale para um chip — recarregou, cria outro.
+//!
+//! Chip que alcança bit dependente do relógio (`P_Legacy_JWT`) não é
+//! cacheado: todo `decide` passa direto (`Lookup::Bypass`).
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::explain::inputs;
+use crate::{bit_eval, decide, split_trigger, BitParams, Decision, RequestContext, SemanticChip, WiringStructure, BUILTIN_BITS};
+
+/// Bits cujo valor muda com o tempo para o mesmo contexto.
+pub const CLOCK_BITS: &[&str] = &["P_Legacy_JWT"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lookup {
+    Hit,
+    Miss,
+    /// Cache desligado (capacidade 0 ou bit de relógio)
+    Bypass,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub enabled: bool,
+    pub disabled_reason: Option<String>,
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Campos do contexto que entram na chave
+    pub fields: Vec<&'static str>,
+}
+
+pub struct DecisionCache {
+    bits: Vec<(&'static str, Option<BitParams>)>,
+    capacity: usize,
+    disabled: Option<String>,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// Segunda chance: a entrada usada desde a última passada volta para o fim
+/// da fila em vez de sair.
+#[derive(Default)]
+struct Entries {
+    map: HashMap<[u8; 32], (Decision, bool)>,
+    order: VecDeque<[u8; 32]>,
+}
+
+impl DecisionCache {
+    /// `capacity` entradas (0 desliga).
+    pub fn new(chip: &SemanticChip, capacity: usize) -> Self {
+        let bits: Vec<_> = read_bits(chip).into_iter().map(|b| (b, chip.bit_params(b).cloned())).collect();
+        let disabled = if let Some((b, _)) = bits.iter().find(|(b, _)| CLOCK_BITS.contains(b)) {
+            Some(format!("{} depends on the clock", b))
+        } else if capacity == 0 {
+            Some("capacity 0".into())
+        } else {
+            None
+        };
+        Self {
+            bits,
+            capacity,
+            disabled,
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.disabled.is_none()
+    }
+
+    /// Chave do contexto: valor de cada bit lido pelo chip, na ordem dos bits.
+    /// Bits de relógio desligam o cache, então `now` não entra.
+    pub fn fingerprint(&self, ctx: &RequestContext) -> [u8; 32] {
+        let mut h = blake3::Hasher::new();
+        for (b, params) in &self.bits {
+            h.update(b.as_bytes());
+            h.update(if bit_eval(b, params.as_ref(), ctx, 0) { b"=1\n" } else { b"=0\n" });
+        }
+        *h.finalize().as_bytes()
+    }
+
+    /// `decide` com cache; `chip` precisa ser o mesmo de `new`.
+    pub fn decide(&self, chip: &SemanticChip, ctx: &RequestContext) -> (Decision, Lookup) {
+        if !self.enabled() {
+            return (decide(chip, ctx), Lookup::Bypass);
+        }
+        let key = self.fingerprint(ctx);
+        if let Some((d, used)) = self.lock().map.get_mut(&key) {
+            *used = true;
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return (d.clone(), Lookup::Hit);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let d = decide(chip, ctx);
+        self.insert(key, d.clone());
+        (d, Lookup::Miss)
+    }
+
+    fn insert(&self, key: [u8; 32], d: Decision) {
+        let mut e = self.lock();
+        if e.map.contains_key(&key) {
+            return;
+        }
+        while e.map.len() >= self.capacity {
+            let Some(old) = e.order.pop_front() else { break };
+            match e.map.get_mut(&old) {
+                Some((_, used)) if *used => {
+                    *used = false;
+                    e.order.push_back(old);
+                }
+                _ => {
+                    e.map.remove(&old);
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        e.map.insert(key, (d, false));
+        e.order.push_back(key);
+    }
+
+    pub fn clear(&self) {
+        *self.lock() = Entries::default();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let defaults = RequestContext::default();
+        CacheStats {
+            enabled: self.enabled(),
+            disabled_reason: self.disabled.clone(),
+            capacity: self.capacity,
+            entries: self.lock().map.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            fields: self.bits.iter().flat_map(|(b, _)| inputs(b, &defaults)).map(|i| i.path).collect(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        // o mapa fica consistente mesmo se outro thread entrou em pânico
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Bits com avaliador alcançáveis a partir dos triggers, como `decide` os visita.
+fn read_bits(chip: &SemanticChip) -> Vec<&'static str> {
+    let mut bits = BTreeSet::new();
+    let mut seen = BTreeSet::new();
+    let mut stack: Vec<&str> = chip.outputs.iter().flat_map(|o| o.0.values()).map(|a| split_trigger(&a.trigger).1).collect();
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        match chip.find_wire(id).map(|w| &w.structure) {
+            Some(WiringStructure::Sequence { sequence }) => {
+                for x in sequence {
+                    if x.starts_with("W_") { stack.push(x) } else { bits.insert(x.as_str()); }
+                }
+            }
+            // paralelo avalia todos os membros como bits
+            Some(WiringStructure::Parallel { parallel }) => bits.extend(parallel.policies.iter().map(String::as_str)),
+            None => {}
+        }
+    }
+    BUILTIN_BITS.iter().copied().filter(|b| bits.contains(b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(groups: &[&str], path: &str) -> RequestContext {
+        let mut ctx = RequestContext::default();
+        ctx.transport.tls_version = 1.3;
+        ctx.mtls.verified = true;
+        ctx.mtls.issuer = "UBL Local CA".into();
+        ctx.auth.method = "webauthn".into();
+        ctx.auth.rp_id = "app.ubl.agency".into();
+        ctx.user.groups = groups.iter().map(|g| g.to_string()).collect();
+        ctx.req = Some(crate::ReqCtx { path: Some(path.into()), method: Some("GET".into()) });
+        ctx
+    }
+
+    #[test]
+    fn keys_on_fields_the_chip_reads() {
+        let chip = SemanticChip::from_yaml(&crate::shipped_yaml(include_str!("../../../policies/ubl_core_v1.yaml"))).unwrap();
+        let cache = DecisionCache::new(&chip, 2);
+        assert!(cache.stats().fields.contains(&"req.path") && !cache.stats().fields.contains(&"rate.ok"));
+
+        let a = ctx(&["ubl-ops"], "/admin/x");
+        let (d, lookup) = cache.decide(&chip, &a);
+        assert_eq!((d.decision.as_str(), lookup), ("allow_admin_write", Lookup::Miss));
+        // who, req_id, método e rate não são lidos pelo chip
+        let mut same = a.clone();
+        same.who = Some("other@ubl.agency".into());
+        same.req_id = Some("r-2".into());
+        same.rate = Some(crate::RateCtx { ok: Some(false) });
+        same.req.as_mut().unwrap().method = Some("POST".into());
+        assert_eq!(cache.decide(&chip, &same).1, Lookup::Hit);
+
+        let (d, lookup) = cache.decide(&chip, &ctx(&[], "/admin/x"));
+        assert_eq!((d.decision.as_str(), lookup), ("allow_standard_access", Lookup::Miss));
+        // cheio: a primeira entrada (usada) ganha segunda chance, sai a segunda
+        cache.decide(&chip, &ctx(&[], "/y"));
+        assert_eq!(cache.decide(&chip, &a).1, Lookup::Hit);
+        assert_eq!(cache.decide(&chip, &ctx(&[], "/admin/x")).1, Lookup::Miss);
+        let s = cache.stats();
+        assert_eq!((s.entries, s.hits, s.misses), (2, 2, 4));
+        assert!(s.evictions >= 1);
+    }
+
+    /// `rate` ausente libera (bit verdadeiro), `rate.ok` ausente bloqueia:
+    /// a chave não pode achatar os dois no mesmo `null`.
+    #[test]
+    fn absent_rate_and_absent_rate_ok_are_different_keys() {
+        let yaml = r#"
+version: "tdln-chip/0.4"
+policies: []
+wiring:
+  - id: W_Rate
+    structure: { sequence: [P_Rate_Bucket_OK] }
+outputs:
+  - allow: { trigger: W_Rate, action: "200" }
+  - deny_rate_limit: { trigger: NOT(W_Rate), action: "429" }
+"#;
+        let chip = SemanticChip::from_yaml(yaml).unwrap();
+        let cache = DecisionCache::new(&chip, 8);
+        let open = ctx(&[], "/");
+        let mut unknown = open.clone();
+        unknown.rate = Some(crate::RateCtx { ok: None });
+        for c in [&open, &unknown, &open, &unknown] {
+            assert_eq!(cache.decide(&chip, c).0.decision, decide(&chip, c).decision);
+        }
+        assert_eq!(cache.decide(&chip, &unknown).0.decision, "deny_rate_limit");
+    }
+
+    #[test]
+    fn bypasses_clock_bits_and_zero_capacity() {
+        let yaml = r#"
+version: "tdln-chip/0.4"
+policies: []
+wiring:
+  - id: W_Jwt
+    structure: { parallel: { policies: [P_Legacy_JWT, P_Role_Admin], aggregator: ANY } }
+outputs:
+  - allow: { trigger: W_Jwt, action: "200" }
+"#;
+        let chip = SemanticChip::from_yaml(yaml).unwrap();
+        let cache = DecisionCache::new(&chip, 100);
+        assert!(cache.stats().disabled_reason.unwrap().contains("P_Legacy_JWT"));
+        assert_eq!(cache.decide(&chip, &ctx(&["ubl-ops"], "/")).1, Lookup::Bypass);
+
+        let v1 = SemanticChip::from_yaml(&crate::shipped_yaml(include_str!("../../../policies/ubl_core_v1.yaml"))).unwrap();
+        assert_eq!(DecisionCache::new(&v1, 0).decide(&v1, &ctx(&[], "/")).1, Lookup::Bypass);
+    }
+}
@@ -0,0 +1,431 @@
+//! Explicação de uma decisão: a árvore avaliada por `decide` (saída por
+//! saída, fio por fio, bit por bit, com os valores do contexto lidos) e a
+//! menor troca de bits que teria mudado o resultado.
+//!
+//! A árvore espelha `eval_wire_rec` — inclusive o curto-circuito de
+//! `sequence` e os membros de `parallel` avaliados como bits.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::{bit_eval, decide_at, now_epoch, param_list, param_min_tls, param_prefix, BitParams, ADMIN_GROUPS, ISSUERS, PASSKEY_METHODS, RP_IDS, split_trigger, Decision, RequestContext, SemanticChip, WiringStructure, BUILTIN_BITS};
+
+/// Limite da busca por flips (combinações de até N bits).
+const MAX_FLIP_BITS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    pub decision: Decision,
+    /// Saídas tentadas, em ordem, até a primeira que disparou
+    pub steps: Vec<Step>,
+    /// Menores conjuntos de bits cuja troca muda a decisão (vazio se nenhum até `MAX_FLIP_BITS`)
+    pub flips: Vec<Flip>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    pub output: String,
+    pub action: String,
+    pub trigger: String,
+    pub negated: bool,
+    pub fired: bool,
+    /// Fio testado pelo trigger
+    pub wire: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Bit,
+    Sequence,
+    Parallel,
+    /// Fio referenciado que não existe no chip (avalia `false`)
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    pub id: String,
+    pub kind: NodeKind,
+    pub passed: bool,
+    /// `false` quando o `sequence` parou antes (curto-circuito)
+    pub evaluated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregator: Option<String>,
+    /// Valores do contexto lidos pelo bit
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<Input>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Input {
+    pub path: &'static str,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Flip {
+    pub changes: Vec<BitChange>,
+    /// Decisão que resultaria
+    pub decision: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BitChange {
+    pub bit: String,
+    pub to: bool,
+    /// Campos do contexto alterados para isso
+    pub set: Vec<Input>,
+}
+
+pub fn explain(chip: &SemanticChip, ctx: &RequestContext) -> Explanation {
+    explain_at(chip, ctx, now_epoch())
+}
+
+/// `explain` com relógio fixo (ver `decide_at`).
+pub fn explain_at(chip: &SemanticChip, ctx: &RequestContext, now: i64) -> Explanation {
+    let decision = decide_at(chip, ctx, now);
+    let mut steps = Vec::new();
+    for (name, act) in chip.outputs.iter().flat_map(|o| o.0.iter()) {
+        let (negated, wire) = split_trigger(&act.trigger);
+        let wire = trace_wire(chip, wire, ctx, now, 0);
+        let fired = wire.passed != negated;
+        steps.push(Step { output: name.clone(), action: act.action.clone(), trigger: act.trigger.clone(), negated, fired, wire });
+        if fired {
+            break;
+        }
+    }
+    let flips = flips(chip, ctx, &decision.decision, now);
+    Explanation { decision, steps, flips }
+}
+
+fn trace_bit(chip: &SemanticChip, id: &str, ctx: &RequestContext, now: i64) -> Node {
+    Node {
+        id: id.to_string(),
+        kind: NodeKind::Bit,
+        passed: bit_eval(id, chip.bit_params(id), ctx, now),
+        evaluated: true,
+        description: chip.policies.iter().find(|p| p.id == id).and_then(|p| p.description.clone()),
+        aggregator: None,
+        inputs: inputs(id, ctx),
+        members: vec![],
+    }
+}
+
+/// `depth` corta a fiação cíclica como em `eval_wire_depth`.
+fn trace_wire(chip: &SemanticChip, id: &str, ctx: &RequestContext, now: i64, depth: usize) -> Node {
+    let mut node = skipped(chip, id);
+    node.evaluated = true;
+    if depth > chip.wiring.len() {
+        return node;
+    }
+    match chip.find_wire(id).map(|w| &w.structure) {
+        None => {}
+        Some(WiringStructure::Sequence { sequence }) => {
+            let mut passed = true;
+            for x in sequence {
+                let member = if !passed {
+                    skipped(chip, x)
+                } else if x.starts_with("W_") {
+                    trace_wire(chip, x, ctx, now, depth + 1)
+                } else {
+                    trace_bit(chip, x, ctx, now)
+                };
+                passed = passed && member.passed;
+                node.members.push(member);
+            }
+            node.passed = passed;
+        }
+        Some(WiringStructure::Parallel { parallel }) => {
+            node.members = parallel.policies.iter().map(|p| trace_bit(chip, p, ctx, now)).collect();
+            node.passed = match parallel.aggregator.as_str() {
+                "ANY" => node.members.iter().any(|m| m.passed),
+                "ALL" => node.members.iter().all(|m| m.passed),
+                _ => false,
+            };
+        }
+    }
+    node
+}
+
+/// Nó não avaliado (curto-circuito) ou ponto de partida de `trace_wire`.
+fn skipped(chip: &SemanticChip, id: &str) -> Node {
+    let (kind, aggregator) = match chip.find_wire(id).map(|w| &w.structure) {
+        Some(WiringStructure::Sequence { .. }) => (NodeKind::Sequence, None),
+        Some(WiringStructure::Parallel { parallel }) => (NodeKind::Parallel, Some(parallel.aggregator.clone())),
+        None if id.starts_with("W_") => (NodeKind::Missing, None),
+        None => (NodeKind::Bit, None),
+    };
+    Node {
+        id: id.to_string(),
+        kind,
+        passed: false,
+        evaluated: false,
+        description: chip.policies.iter().find(|p| p.id == id).and_then(|p| p.description.clone()),
+        aggregator,
+        inputs: vec![],
+        members: vec![],
+    }
+}
+
+fn input(path: &'static str, value: Value) -> Input {
+    Input { path, value }
+}
+
+/// f32 → JSON sem o ruído da conversão para f64 (1.3, não 1.2999999523).
+fn tls(v: f32) -> Value {
+    json!(v.to_string().parse::<f64>().unwrap_or_default())
+}
+
+/// Campos lidos por cada bit em `bit_eval` (manter em sincronia: a chave do
+/// `DecisionCache` também sai daqui).
+pub(crate) fn inputs(id: &str, ctx: &RequestContext) -> Vec<Input> {
+    match id {
+        "P_Transport_Secure" => vec![input("transport.tls_version", tls(ctx.transport.tls_version))],
+        "P_Device_Identity" => vec![input("mtls.verified", json!(ctx.mtls.verified)), input("mtls.issuer", json!(ctx.mtls.issuer))],
+        "P_User_Passkey" => vec![input("auth.method", json!(ctx.auth.method)), input("auth.rp_id", json!(ctx.auth.rp_id))],
+        "P_Role_Admin" => vec![input("user.groups", json!(ctx.user.groups))],
+        "P_Circuit_Breaker" => vec![input("system.panic_mode", json!(ctx.system.panic_mode))],
+        "P_Is_Admin_Path" | "P_Is_Warmup_Path" => vec![input("req.path", json!(ctx.req.as_ref().and_then(|r| r.path.clone())))],
+        "P_Rate_Bucket_OK" => vec![input("rate.ok", json!(ctx.rate.as_ref().and_then(|r| r.ok)))],
+        "P_Webhook_Verified" => vec![input("webhook.verified", json!(ctx.webhook.as_ref().and_then(|w| w.verified)))],
+        "P_Legacy_JWT" => {
+            let jwt = ctx.legacy_jwt.as_ref();
+            vec![
+                input("legacy_jwt.valid", json!(jwt.and_then(|j| j.valid))),
+                input("legacy_jwt.expires_at", json!(jwt.and_then(|j| j.expires_at))),
+            ]
+        }
+        _ => vec![],
+    }
+}
+
+/// Altera o contexto para que o bit avalie `to` (com os `params` do chip);
+/// devolve os campos escritos.
+fn set_bit(id: &str, params: Option<&BitParams>, to: bool, ctx: &mut RequestContext, now: i64) -> Vec<Input> {
+    let first = |key, default: &[&str]| param_list(params, key, default).first().map(|s| s.to_string()).unwrap_or_default();
+    match (id, to) {
+        ("P_Transport_Secure", _) => {
+            let min = param_min_tls(params);
+            ctx.transport.tls_version = if to { min } else { ((min * 10.0).round() - 1.0) / 10.0 };
+        }
+        ("P_Device_Identity", true) => {
+            ctx.mtls.verified = true;
+            if !param_list(params, "issuers", ISSUERS).contains(&ctx.mtls.issuer.as_str()) {
+                ctx.mtls.issuer = first("issuers", ISSUERS);
+            }
+        }
+        ("P_Device_Identity", false) => ctx.mtls.verified = false,
+        ("P_User_Passkey", true) => {
+            if !param_list(params, "methods", PASSKEY_METHODS).contains(&ctx.auth.method.as_str()) {
+                ctx.auth.method = first("methods", PASSKEY_METHODS);
+            }
+            if !param_list(params, "rp_ids", RP_IDS).contains(&ctx.auth.rp_id.as_str()) {
+                ctx.auth.rp_id = first("rp_ids", RP_IDS);
+            }
+        }
+        ("P_User_Passkey", false) => ctx.auth.method = "password".into(),
+        ("P_Role_Admin", true) => ctx.user.groups.push(first("groups", ADMIN_GROUPS)),
+        ("P_Role_Admin", false) => {
+            let groups = param_list(params, "groups", ADMIN_GROUPS);
+            ctx.user.groups.retain(|g| !groups.contains(&g.as_str()));
+        }
+        ("P_Circuit_Breaker", _) => ctx.system.panic_mode = to,
+        ("P_Is_Admin_Path" | "P_Is_Warmup_Path", _) => {
+            let req = ctx.req.get_or_insert_with(Default::default);
+            let path = req.path.take().unwrap_or_default();
+            let prefix = param_prefix(id, params);
+            let rest = path.strip_prefix(prefix).unwrap_or(&path).trim_start_matches('/');
+            req.path = Some(if to { format!("{}{}", prefix, rest) } else { format!("/{}", rest) });
+        }
+        ("P_Rate_Bucket_OK", _) => ctx.rate.get_or_insert_with(Default::default).ok = Some(to),
+        ("P_Webhook_Verified", _) => ctx.webhook.get_or_insert_with(Default::default).verified = Some(to),
+        ("P_Legacy_JWT", _) => {
+            let jwt = ctx.legacy_jwt.get_or_insert_with(Default::default);
+            jwt.valid = Some(to);
+            if to {
+                jwt.expires_at = Some(now + 3600);
+            }
+        }
+        _ => return vec![],
+    }
+    inputs(id, ctx)
+}
+
+/// Bits com avaliador que o chip referencia (declarados ou usados na fiação).
+fn flippable(chip: &SemanticChip) -> Vec<&'static str> {
+    let referenced = |b: &str| {
+        chip.policies.iter().any(|p| p.id == b)
+            || chip.wiring.iter().any(|w| match &w.structure {
+                WiringStructure::Sequence { sequence } => sequence.iter().any(|x| x == b),
+                WiringStructure::Parallel { parallel } => parallel.policies.iter().any(|x| x == b),
+            })
+    };
+    BUILTIN_BITS.iter().copied().filter(|b| referenced(b)).collect()
+}
+
+/// Busca em largura: todas as combinações de 1 bit, depois 2, … até
+/// `MAX_FLIP_BITS`; para no primeiro tamanho que muda a decisão.
+fn flips(chip: &SemanticChip, ctx: &RequestContext, current: &str, now: i64) -> Vec<Flip> {
+    let bits = flippable(chip);
+    for size in 1..=MAX_FLIP_BITS.min(bits.len()) {
+        let mut found = Vec::new();
+        let mut idx: Vec<usize> = (0..size).collect();
+        loop {
+            let mut flipped = ctx.clone();
+            let mut changes = Vec::new();
+            for &i in &idx {
+                let (bit, params) = (bits[i], chip.bit_params(bits[i]));
+                let to = !bit_eval(bit, params, ctx, now);
+                let before = inputs(bit, &flipped);
+                let set = set_bit(bit, params, to, &mut flipped, now).into_iter().filter(|a| !before.contains(a)).collect();
+                changes.push(BitChange { bit: bit.to_string(), to, set });
+            }
+            let decision = decide_at(chip, &flipped, now).decision;
+            if decision != current {
+                found.push(Flip { changes, decision });
+            }
+            if !next_combination(&mut idx, bits.len()) {
+                break;
+            }
+        }
+        if !found.is_empty() {
+            return found;
+        }
+    }
+    vec![]
+}
+
+/// Próxima combinação (ordem lexicográfica) de `idx.len()` índices em `0..n`.
+fn next_combination(idx: &mut [usize], n: usize) -> bool {
+    let k = idx.len();
+    let Some(i) = (0..k).rev().find(|&i| idx[i] < n - k + i) else { return false };
+    idx[i] += 1;
+    for j in i + 1..k {
+        idx[j] = idx[j - 1] + 1;
+    }
+    true
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "decision: {} ({})", self.decision.decision, self.decision.why)?;
+        if self.steps.iter().all(|s| !s.fired) {
+            writeln!(f, "no output fired; default deny")?;
+        }
+        for (n, s) in self.steps.iter().enumerate() {
+            writeln!(f)?;
+            let verdict = if s.fired { "fired" } else { "not fired" };
+            let wire = if s.negated { format!("NOT {}", s.wire.id) } else { s.wire.id.clone() };
+            writeln!(f, "{}. {} — {}: {}", n + 1, s.output, wire, verdict)?;
+            write_node(f, &s.wire, 1)?;
+        }
+        writeln!(f)?;
+        if self.flips.is_empty() {
+            return writeln!(f, "no change of up to {} bit(s) flips this decision", MAX_FLIP_BITS);
+        }
+        writeln!(f, "minimal change to flip the decision:")?;
+        for flip in &self.flips {
+            let changes: Vec<String> = flip.changes.iter().map(|c| {
+                let set: Vec<String> = c.set.iter().map(|i| format!("{}={}", i.path, i.value)).collect();
+                format!("{} → {} ({})", c.bit, c.to, set.join(", "))
+            }).collect();
+            writeln!(f, "  - {} ⇒ {}", changes.join(" + "), flip.decision)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_node(f: &mut fmt::Formatter<'_>, node: &Node, depth: usize) -> fmt::Result {
+    let mark = match (node.evaluated, node.passed) {
+        (false, _) => "·",
+        (true, true) => "✓",
+        (true, false) => "✗",
+    };
+    let indent = "  ".repeat(depth);
+    let kind = match (node.kind, &node.aggregator) {
+        (NodeKind::Bit, _) => String::new(),
+        (NodeKind::Parallel, Some(agg)) => format!(" (parallel {})", agg),
+        (NodeKind::Parallel, None) => " (parallel)".into(),
+        (NodeKind::Sequence, _) => " (sequence)".into(),
+        (NodeKind::Missing, _) => " (missing wire)".into(),
+    };
+    write!(f, "{}{} {}{}", indent, mark, node.id, kind)?;
+    if let Some(d) = &node.description {
+        write!(f, " — {}", d)?;
+    }
+    if !node.evaluated {
+        write!(f, " [not evaluated]")?;
+    } else if !node.inputs.is_empty() {
+        let vals: Vec<String> = node.inputs.iter().map(|i| format!("{}={}", i.path, i.value)).collect();
+        write!(f, " [{}]", vals.join(", "))?;
+    }
+    writeln!(f)?;
+    for m in &node.members {
+        write_node(f, m, depth + 1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval_trigger;
+
+    fn chip() -> SemanticChip {
+        SemanticChip::from_yaml(&crate::shipped_yaml(include_str!("../../../policies/ubl_core_v1.yaml"))).unwrap()
+    }
+
+    fn zero_trust() -> RequestContext {
+        let mut ctx = RequestContext::default();
+        ctx.transport.tls_version = 1.3;
+        ctx.mtls.verified = true;
+        ctx.mtls.issuer = "UBL Local CA".into();
+        ctx.auth.method = "webauthn".into();
+        ctx.auth.rp_id = "app.ubl.agency".into();
+        ctx
+    }
+
+    #[test]
+    fn tree_matches_decide() {
+        let chip = chip();
+        let mut ctx = zero_trust();
+        ctx.mtls.verified = false;
+        let e = explain(&chip, &ctx);
+        assert_eq!(e.decision.decision, "deny_invalid_access");
+        assert_eq!(e.steps.len(), 3);
+        for s in &e.steps {
+            assert_eq!(s.fired, eval_trigger(&chip, &s.trigger, &ctx, now_epoch()).0, "{}", s.output);
+        }
+        let zt = &e.steps[1].wire;
+        let device = &zt.members[1];
+        assert_eq!((device.id.as_str(), device.passed), ("P_Device_Identity", false));
+        assert_eq!(device.inputs[0], Input { path: "mtls.verified", value: json!(false) });
+        assert!(!zt.members[2].evaluated);
+
+        assert_eq!(e.flips.len(), 1);
+        assert_eq!(e.flips[0].changes[0].bit, "P_Device_Identity");
+        assert_eq!(e.flips[0].decision, "allow_standard_access");
+        assert!(e.to_string().contains("P_Device_Identity → true (mtls.verified=true)"), "{}", e);
+    }
+
+    #[test]
+    fn flip_to_admin_write() {
+        let chip = chip();
+        let mut ctx = zero_trust();
+        ctx.req = Some(crate::ReqCtx { path: Some("/admin/deploy".into()), method: None });
+        let e = explain(&chip, &ctx);
+        assert_eq!(e.decision.decision, "allow_standard_access");
+        assert!(e.flips.iter().all(|f| f.changes.len() == 1));
+        let role = e.flips.iter().find(|f| f.changes[0].bit == "P_Role_Admin").unwrap();
+        assert_eq!(role.decision, "allow_admin_write");
+        assert_eq!(role.changes[0].set, vec![Input { path: "user.groups", value: json!(["ubl-ops"]) }]);
+        // caminho não-admin não muda nada sozinho
+        assert!(e.flips.iter().all(|f| f.changes[0].bit != "P_Is_Admin_Path"));
+    }
+}
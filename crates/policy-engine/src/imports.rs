@@ -0,0 +1,258 @@
+//! `imports:` — bits e fios compartilhados entre chips.
+//!
+//! Uma biblioteca (`version: tdln-lib/0.1`) tem só `policies` e `wiring`.
+//! O chip a importa por `path` (relativo ao chip) e/ou `blake3`; só com o
+//! hash, o arquivo é procurado no diretório do chip e em `lib/`. `with:`
+//! reconfigura bits da biblioteca: `P_User_Passkey(rp_ids=['a','b'])`.
+//!
+//! A resolução embute tudo num chip plano (sem comentários), fixa o blake3
+//! de cada biblioteca em `imported:` e devolve o YAML que o policy-signer
+//! assina — proxy e worker nunca leem bibliotecas.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+use crate::{check_keys, check_params, BitParams, SemanticChip};
+
+pub const LIB_VERSION: &str = "tdln-lib/0.1";
+const LIB_KEYS: &[&str] = &["version", "library", "description", "policies", "wiring"];
+const IMPORT_KEYS: &[&str] = &["path", "blake3", "with"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Import {
+    pub path: Option<String>,
+    /// Hash esperado do arquivo; sem `path`, é o que localiza a biblioteca
+    pub blake3: Option<String>,
+    /// `P_x(param=valor, ...)` sobre bits da biblioteca
+    #[serde(default)]
+    pub with: Vec<String>,
+}
+
+/// Biblioteca embutida no chip resolvido.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Pin {
+    pub library: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub blake3: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    /// O próprio YAML de entrada quando não há `imports`
+    pub yaml: String,
+    pub pins: Vec<Pin>,
+    /// Os `imports` como escritos no chip fonte
+    pub imports: Vec<Import>,
+}
+
+/// Resolve lendo as bibliotecas a partir de `base` (diretório do chip).
+pub fn resolve_imports(yaml: &str, base: &Path) -> Result<Resolved> {
+    resolve_imports_with(yaml, &|imp| load(imp, base))
+}
+
+/// `load` devolve o conteúdo da biblioteca; o pin `blake3` é conferido aqui.
+pub fn resolve_imports_with(yaml: &str, load: &dyn Fn(&Import) -> Result<String>) -> Result<Resolved> {
+    let mut doc: Value = serde_yaml::from_str(yaml)?;
+    let Some(imports) = doc.as_mapping_mut().and_then(|m| m.shift_remove("imports")) else {
+        return Ok(Resolved { yaml: yaml.to_string(), pins: vec![], imports: vec![] });
+    };
+    if doc.get("imported").is_some() {
+        anyhow::bail!("chip has both `imports` and `imported`; resolve from the source chip");
+    }
+    let entries = imports.as_sequence().ok_or_else(|| anyhow!("`imports` must be a list"))?;
+
+    let (mut policies, mut wiring, mut pins, mut parsed) = (vec![], vec![], vec![], vec![]);
+    for (i, entry) in entries.iter().enumerate() {
+        check_keys(entry, IMPORT_KEYS, "imports[].")?;
+        let imp: Import = serde_yaml::from_value(entry.clone()).map_err(|e| anyhow!("imports[{}]: {}", i, e))?;
+        let at = imp.path.clone().or_else(|| imp.blake3.clone())
+            .ok_or_else(|| anyhow!("imports[{}]: needs `path` or `blake3`", i))?;
+        let content = load(&imp).map_err(|e| anyhow!("imports {}: {}", at, e))?;
+        let digest = blake3::hash(content.as_bytes()).to_hex().to_string();
+        if let Some(pin) = &imp.blake3 {
+            if !pin.eq_ignore_ascii_case(&digest) {
+                anyhow::bail!("imports {}: blake3 is {}, but the chip pins {}", at, digest, pin);
+            }
+        }
+
+        let (library, mut bits, wires) = parse_library(&content).map_err(|e| anyhow!("imports {}: {}", at, e))?;
+        for call in &imp.with {
+            let (id, params) = parse_bit_call(call).map_err(|e| anyhow!("imports {}: with {}: {}", at, call, e))?;
+            let bit = bits.iter_mut().find(|b| b.get("id").and_then(Value::as_str) == Some(id.as_str()))
+                .ok_or_else(|| anyhow!("imports {}: with {}: library {} has no bit {}", at, call, library, id))?;
+            if bit.get("params").and_then(Value::as_mapping).is_none() {
+                bit["params"] = Value::Mapping(Mapping::new());
+            }
+            let merged = bit["params"].as_mapping_mut().expect("params is a mapping");
+            for (k, v) in params {
+                merged.insert(Value::String(k), v);
+            }
+        }
+        policies.extend(bits);
+        wiring.extend(wires);
+        pins.push(Pin { library, path: imp.path.clone(), blake3: digest });
+        parsed.push(imp);
+    }
+
+    // definições do chip depois das importadas; o mesmo id nos dois é erro
+    for (key, merged) in [("policies", &mut policies), ("wiring", &mut wiring)] {
+        let imported = merged.len();
+        merged.extend(doc.get(key).and_then(Value::as_sequence).cloned().unwrap_or_default());
+        for (i, item) in merged.iter().enumerate().skip(imported) {
+            let id = item.get("id").and_then(Value::as_str).unwrap_or("?");
+            if merged[..imported].iter().any(|m| m.get("id").and_then(Value::as_str) == Some(id)) {
+                anyhow::bail!("{}.{} (entry {}) is already defined by an import; change it with `with:` instead", key, id, i - imported);
+            }
+        }
+        doc[key] = Value::Sequence(std::mem::take(merged));
+    }
+    doc["imported"] = serde_yaml::to_value(&pins)?;
+
+    let yaml = serde_yaml::to_string(&doc)?;
+    SemanticChip::from_yaml(&yaml)?;
+    Ok(Resolved { yaml, pins, imports: parsed })
+}
+
+/// `(nome, bits, fios)` de uma biblioteca.
+fn parse_library(content: &str) -> Result<(String, Vec<Value>, Vec<Value>)> {
+    let doc: Value = serde_yaml::from_str(content)?;
+    check_keys(&doc, LIB_KEYS, "")?;
+    match doc.get("version").and_then(Value::as_str) {
+        Some(LIB_VERSION) => {}
+        v => anyhow::bail!("library version {:?} is not {}", v.unwrap_or("(none)"), LIB_VERSION),
+    }
+    let name = doc.get("library").and_then(Value::as_str).ok_or_else(|| anyhow!("library has no `library:` name"))?;
+    let list = |key| doc.get(key).and_then(Value::as_sequence).cloned().unwrap_or_default();
+    Ok((name.to_string(), list("policies"), list("wiring")))
+}
+
+/// `P_x(a=1.3, b=['x','y'])` → `("P_x", {a: 1.3, b: [x, y]})`; valores em YAML.
+pub fn parse_bit_call(call: &str) -> Result<(String, BitParams)> {
+    let call = call.trim();
+    let (id, args) = call.strip_suffix(')').and_then(|c| c.split_once('('))
+        .ok_or_else(|| anyhow!("expected P_x(param=value, ...)"))?;
+    let id = id.trim();
+    let mut params = BitParams::new();
+    for arg in split_args(args).into_iter().filter(|a| !a.trim().is_empty()) {
+        let (k, v) = arg.split_once('=').ok_or_else(|| anyhow!("argument {:?} is not param=value", arg.trim()))?;
+        let value: Value = serde_yaml::from_str(v.trim()).map_err(|e| anyhow!("{}: {}", k.trim(), e))?;
+        params.insert(k.trim().to_string(), value);
+    }
+    check_params(id, &params)?;
+    Ok((id.to_string(), params))
+}
+
+/// Vírgulas de topo (fora de `[]`, `{}` e aspas).
+fn split_args(args: &str) -> Vec<&str> {
+    let (mut out, mut depth, mut quote, mut start) = (vec![], 0i32, None, 0);
+    for (i, c) in args.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                out.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&args[start..]);
+    out
+}
+
+fn load(imp: &Import, base: &Path) -> Result<String> {
+    if let Some(path) = &imp.path {
+        return std::fs::read_to_string(base.join(path)).map_err(|e| anyhow!("{}: {}", base.join(path).display(), e));
+    }
+    let hash = imp.blake3.as_deref().unwrap_or_default();
+    let dirs = [base.to_path_buf(), base.join("lib")];
+    for dir in &dirs {
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if !matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")) {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)?;
+            if blake3::hash(content.as_bytes()).to_hex().eq_ignore_ascii_case(hash) {
+                return Ok(content);
+            }
+        }
+    }
+    anyhow::bail!("no library with blake3 {} in {} or {}", hash, dirs[0].display(), dirs[1].display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decide, RequestContext};
+
+    const CHIP: &str = r#"
+version: "tdln-chip/0.4"
+imports:
+  - path: lib/zero_trust.yaml
+    with: ["P_User_Passkey(rp_ids=['app.ubl.agency', 'voulezvous.tv'])"]
+policies: []
+wiring: []
+outputs:
+  - allow: { trigger: W_ZeroTrust_Standard, action: "200" }
+"#;
+
+    fn policies_dir() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../policies"))
+    }
+
+    fn vvz_ctx() -> RequestContext {
+        let mut ctx = RequestContext::default();
+        ctx.transport.tls_version = 1.3;
+        ctx.mtls.verified = true;
+        ctx.mtls.issuer = "Cloudflare Edge".into();
+        ctx.auth.method = "webauthn".into();
+        ctx.auth.rp_id = "voulezvous.tv".into();
+        ctx
+    }
+
+    #[test]
+    fn resolves_params_and_pins_library_hash() {
+        assert!(SemanticChip::from_yaml(CHIP).unwrap_err().to_string().contains("unresolved import"));
+
+        let r = resolve_imports(CHIP, policies_dir()).unwrap();
+        let lib = std::fs::read(policies_dir().join("lib/zero_trust.yaml")).unwrap();
+        assert_eq!(r.pins, vec![Pin { library: "zero_trust".into(), path: Some("lib/zero_trust.yaml".into()), blake3: blake3::hash(&lib).to_hex().to_string() }]);
+
+        let chip = SemanticChip::from_yaml_strict(&r.yaml).unwrap();
+        assert_eq!(chip.imported, r.pins);
+        assert_eq!(decide(&chip, &vvz_ctx()).decision, "allow");
+        // sem o `with`, vale o padrão da biblioteca (só app.ubl.agency)
+        let plain = resolve_imports(&CHIP.replace("    with:", "    #with:"), policies_dir()).unwrap();
+        assert_eq!(decide(&SemanticChip::from_yaml(&plain.yaml).unwrap(), &vvz_ctx()).decision, "deny_invalid_access");
+
+        // só pelo hash: encontrado em lib/; hash errado não passa
+        let by_hash = CHIP.replace("path: lib/zero_trust.yaml", &format!("blake3: {}", r.pins[0].blake3));
+        let h = resolve_imports(&by_hash, policies_dir()).unwrap();
+        assert_eq!((h.pins[0].path.as_deref(), h.pins[0].blake3.as_str()), (None, r.pins[0].blake3.as_str()));
+        let wrong = CHIP.replace("    with:", &format!("    blake3: {}\n    with:", "0".repeat(64)));
+        assert!(resolve_imports(&wrong, policies_dir()).unwrap_err().to_string().contains("pins"));
+
+        let dup = CHIP.replace("policies: []", "policies: [{ id: P_User_Passkey }]");
+        assert!(resolve_imports(&dup, policies_dir()).unwrap_err().to_string().contains("with:"));
+    }
+
+    #[test]
+    fn parses_bit_calls() {
+        let (id, params) = parse_bit_call("P_User_Passkey(rp_ids=['a, b', \"c\"], methods=webauthn)").unwrap();
+        assert_eq!(id, "P_User_Passkey");
+        assert_eq!(params["rp_ids"], serde_yaml::from_str::<Value>("['a, b', c]").unwrap());
+        assert_eq!(params["methods"], Value::String("webauthn".into()));
+        assert_eq!(parse_bit_call("P_Transport_Secure(min_tls=1.2)").unwrap().1["min_tls"], serde_yaml::from_str::<Value>("1.2").unwrap());
+
+        assert!(parse_bit_call("P_User_Passkey(rp_id=x)").unwrap_err().to_string().contains("rp_ids"));
+        assert!(parse_bit_call("P_Transport_Secure(min_tls=high)").is_err());
+        assert!(parse_bit_call("P_User_Passkey").is_err());
+    }
+}
@@ -1,40 +1,69 @@
 #[cfg(target_arch = "wasm32")]
-mod wasm;
+pub mod wasm;
+mod lint;
+mod explain;
+mod analyze;
+mod migrate;
+mod imports;
+mod cache;
+mod obligation;
+
+pub use lint::{lint, Diagnostic, Severity};
+pub use analyze::{analyze, Analysis, OutputReach, Reach, Violation};
+pub use migrate::{migrate, Migration, CHIP_VERSION, CHIP_VERSIONS};
+pub use explain::{explain, explain_at, BitChange, Explanation, Flip, Input, Node, NodeKind, Step};
+pub use cache::{CacheStats, DecisionCache, Lookup, CLOCK_BITS};
+pub use obligation::{Challenge, Obligation, LOG_SEVERITIES};
+pub use imports::{parse_bit_call, resolve_imports, resolve_imports_with, Import, Pin, Resolved, LIB_VERSION};
 
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 use std::collections::{BTreeMap};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct PolicyBitDefinition {
     pub id: String,
     pub description: Option<String>,
     pub logic: Option<String>,
+    /// Parâmetros do avaliador embutido (`BIT_PARAMS`); ausente = padrão
+    #[serde(default)]
+    pub params: BitParams,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+pub type BitParams = BTreeMap<String, serde_yaml::Value>;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum WiringStructure {
     Sequence { sequence: Vec<String> },
     Parallel { parallel: ParallelConfig },
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ParallelConfig {
     pub policies: Vec<String>,
     pub aggregator: String, // ANY | ALL
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct WiringDefinition {
     pub id: String,
     pub structure: WiringStructure,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct OutputAction {
     pub trigger: String,
     pub action: String,
+    /// Devolvidas na `Decision`; quem aplica precisa cumprir
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub obligations: Vec<Obligation>,
+    /// Devolvido na `Decision`; só informativo
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub advice: Vec<Obligation>,
+    /// Saída do tipo challenge: pede step-up em vez de decidir
+    #[serde(default)]
+    pub challenge: Option<Challenge>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -43,9 +72,44 @@ pub struct OutputDefinition(pub BTreeMap<String, OutputAction>);
 #[derive(Debug, Deserialize, Clone)]
 pub struct SemanticChip {
     pub version: String,
+    #[serde(default)]
+    pub meta: ChipMeta,
     pub policies: Vec<PolicyBitDefinition>,
     pub wiring: Vec<WiringDefinition>,
     pub outputs: Vec<OutputDefinition>,
+    /// Propriedades conferidas por `analyze`; não afetam `decide`
+    #[serde(default)]
+    pub invariants: Vec<Invariant>,
+    /// Bibliotecas a resolver (`resolve_imports`); o engine só avalia chips resolvidos
+    #[serde(default)]
+    pub imports: Vec<Import>,
+    /// Bibliotecas já embutidas pela resolução, com o blake3 fixado
+    #[serde(default)]
+    pub imported: Vec<Pin>,
+}
+
+/// Bloco `meta:` — intenção, donos e tenant; não afeta a decisão.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ChipMeta {
+    pub intent: Option<String>,
+    #[serde(default)]
+    pub owners: Vec<String>,
+    pub tenant: Option<String>,
+}
+
+/// Chaves aceitas por `from_yaml_strict` no topo e em `meta:`.
+pub const CHIP_KEYS: &[&str] = &["version", "meta", "policies", "wiring", "outputs", "invariants", "imports", "imported"];
+pub const META_KEYS: &[&str] = &["intent", "owners", "tenant"];
+
+/// "Nenhuma saída em `outputs` decide sem todos os `require`".
+/// `outputs`: nomes exatos ou prefixo com `*` (`allow_*`);
+/// `require`: bits, opcionalmente `NOT(P_x)`.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Invariant {
+    pub id: String,
+    pub description: Option<String>,
+    pub outputs: Vec<String>,
+    pub require: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -101,18 +165,77 @@ pub struct LegacyJwtCtx {
     pub expires_at: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Decision {
     pub decision: String,
     pub why: String,
     pub trigger: String,
     pub chain: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub obligations: Vec<Obligation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub advice: Vec<Obligation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<Challenge>,
 }
 
 impl SemanticChip {
     pub fn from_yaml(y: &str) -> Result<Self> {
         let c: SemanticChip = serde_yaml::from_str(y)?;
-        Ok(c)
+        c.validate()
+    }
+
+    /// Como `from_yaml`, mas chave desconhecida no topo ou em `meta:` é erro
+    /// (no modo normal ela é ignorada em silêncio — um `owner:` digitado errado some).
+    pub fn from_yaml_strict(y: &str) -> Result<Self> {
+        let v: serde_yaml::Value = serde_yaml::from_str(y)?;
+        check_keys(&v, CHIP_KEYS, "")?;
+        if let Some(meta) = v.get("meta") {
+            check_keys(meta, META_KEYS, "meta.")?;
+        }
+        serde_yaml::from_value::<Self>(v)?.validate()
+    }
+
+    /// Versão suportada; em `CHIP_VERSION` os triggers precisam apontar para fios.
+    fn validate(self) -> Result<Self> {
+        if !CHIP_VERSIONS.contains(&self.version.as_str()) {
+            anyhow::bail!("unsupported chip version {:?}: this engine supports {}", self.version, CHIP_VERSIONS.join(", "));
+        }
+        if !self.imports.is_empty() {
+            anyhow::bail!("chip has {} unresolved import(s); resolve them first (policy-signer signs the resolved chip)", self.imports.len());
+        }
+        for p in &self.policies {
+            check_params(&p.id, &p.params).map_err(|e| anyhow::anyhow!("policies.{}: {}", p.id, e))?;
+        }
+        for (name, act) in self.outputs.iter().flat_map(|o| o.0.iter()) {
+            for o in act.obligations.iter().chain(&act.advice) {
+                o.check().map_err(|e| anyhow::anyhow!("outputs.{}: {}: {}", name, o.kind(), e))?;
+            }
+            if let Some(c) = &act.challenge {
+                c.check().map_err(|e| anyhow::anyhow!("outputs.{}: {}", name, e))?;
+            }
+        }
+        if self.version == CHIP_VERSION {
+            for (name, act) in self.outputs.iter().flat_map(|o| o.0.iter()) {
+                let (_, wire) = split_trigger(&act.trigger);
+                if self.find_wire(wire).is_none() {
+                    anyhow::bail!("outputs.{}: trigger {} must be W_x or NOT(W_x) with a declared wire ({})", name, act.trigger, CHIP_VERSION);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn owners(&self) -> &[String] {
+        &self.meta.owners
+    }
+
+    pub fn tenant(&self) -> Option<&str> {
+        self.meta.tenant.as_deref()
+    }
+
+    pub fn bit_params(&self, id: &str) -> Option<&BitParams> {
+        self.policies.iter().find(|p| p.id == id).map(|p| &p.params)
     }
 
     fn find_wire(&self, id: &str) -> Option<&WiringDefinition> {
@@ -120,17 +243,91 @@ impl SemanticChip {
     }
 }
 
-fn bit_eval(id: &str, ctx: &RequestContext) -> bool {
+pub(crate) fn check_keys(v: &serde_yaml::Value, allowed: &[&str], prefix: &str) -> Result<()> {
+    for k in v.as_mapping().into_iter().flat_map(|m| m.keys()) {
+        let k = k.as_str().unwrap_or("?");
+        if !allowed.contains(&k) {
+            anyhow::bail!("unknown chip key `{}{}` (expected one of: {})", prefix, k, allowed.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Bits com avaliador embutido; qualquer outro id avalia `false`.
+pub const BUILTIN_BITS: &[&str] = &[
+    "P_Transport_Secure", "P_Device_Identity", "P_User_Passkey", "P_Role_Admin", "P_Circuit_Breaker",
+    "P_Is_Admin_Path", "P_Is_Warmup_Path", "P_Rate_Bucket_OK", "P_Webhook_Verified", "P_Legacy_JWT",
+];
+
+/// Parâmetros aceitos por bit embutido; os padrões são os valores históricos.
+pub const BIT_PARAMS: &[(&str, &str)] = &[
+    ("P_Transport_Secure", "min_tls"), ("P_Device_Identity", "issuers"), ("P_User_Passkey", "methods"),
+    ("P_User_Passkey", "rp_ids"), ("P_Role_Admin", "groups"), ("P_Is_Admin_Path", "prefix"),
+    ("P_Is_Warmup_Path", "prefix"),
+];
+
+pub(crate) const MIN_TLS: f32 = 1.3;
+pub(crate) const ISSUERS: &[&str] = &["Cloudflare Edge", "UBL Local CA"];
+pub(crate) const PASSKEY_METHODS: &[&str] = &["access-passkey", "webauthn"];
+pub(crate) const RP_IDS: &[&str] = &["app.ubl.agency"];
+pub(crate) const ADMIN_GROUPS: &[&str] = &["ubl-ops"];
+pub(crate) const ADMIN_PREFIX: &str = "/admin/";
+pub(crate) const WARMUP_PREFIX: &str = "/warmup";
+
+pub(crate) fn check_params(id: &str, params: &BitParams) -> Result<()> {
+    use serde_yaml::Value;
+    for (k, v) in params {
+        if !BIT_PARAMS.contains(&(id, k.as_str())) {
+            let known: Vec<&str> = BIT_PARAMS.iter().filter(|(b, _)| *b == id).map(|(_, p)| *p).collect();
+            anyhow::bail!("unknown param `{}` (accepted: {})", k, if known.is_empty() { "none".into() } else { known.join(", ") });
+        }
+        let ok = match k.as_str() {
+            "min_tls" => v.is_number(),
+            "prefix" => v.is_string(),
+            _ => v.is_string() || v.as_sequence().is_some_and(|s| !s.is_empty() && s.iter().all(Value::is_string)),
+        };
+        if !ok {
+            anyhow::bail!("param `{}` has the wrong type ({})", k, serde_yaml::to_string(v)?.trim());
+        }
+    }
+    Ok(())
+}
+
+/// Lista de strings do parâmetro (escalar vale como lista de um) ou o padrão.
+pub(crate) fn param_list<'a>(params: Option<&'a BitParams>, key: &str, default: &'a [&'a str]) -> Vec<&'a str> {
+    match params.and_then(|p| p.get(key)) {
+        Some(serde_yaml::Value::String(s)) => vec![s.as_str()],
+        Some(serde_yaml::Value::Sequence(s)) => s.iter().filter_map(serde_yaml::Value::as_str).collect(),
+        _ => default.to_vec(),
+    }
+}
+
+pub(crate) fn param_min_tls(params: Option<&BitParams>) -> f32 {
+    params.and_then(|p| p.get("min_tls")).and_then(serde_yaml::Value::as_f64).map_or(MIN_TLS, |v| v as f32)
+}
+
+/// Prefixo dos bits de rota (`P_Is_Admin_Path`, `P_Is_Warmup_Path`).
+pub(crate) fn param_prefix<'a>(id: &str, params: Option<&'a BitParams>) -> &'a str {
+    let default = if id == "P_Is_Warmup_Path" { WARMUP_PREFIX } else { ADMIN_PREFIX };
+    params.and_then(|p| p.get("prefix")).and_then(serde_yaml::Value::as_str).unwrap_or(default)
+}
+
+/// `now`: epoch em segundos (só `P_Legacy_JWT` lê o relógio).
+fn bit_eval(id: &str, params: Option<&BitParams>, ctx: &RequestContext, now: i64) -> bool {
     match id {
-        "P_Transport_Secure" => ctx.transport.tls_version >= 1.3,
-        "P_Device_Identity"  => ctx.mtls.verified && (ctx.mtls.issuer == "Cloudflare Edge" || ctx.mtls.issuer == "UBL Local CA"),
-        "P_User_Passkey"     => (ctx.auth.method == "access-passkey" || ctx.auth.method == "webauthn") && ctx.auth.rp_id == "app.ubl.agency",
-        "P_Role_Admin"       => ctx.user.groups.iter().any(|g| g == "ubl-ops"),
+        "P_Transport_Secure" => ctx.transport.tls_version >= param_min_tls(params),
+        "P_Device_Identity"  => ctx.mtls.verified && param_list(params, "issuers", ISSUERS).contains(&ctx.mtls.issuer.as_str()),
+        "P_User_Passkey"     => param_list(params, "methods", PASSKEY_METHODS).contains(&ctx.auth.method.as_str())
+            && param_list(params, "rp_ids", RP_IDS).contains(&ctx.auth.rp_id.as_str()),
+        "P_Role_Admin"       => {
+            let groups = param_list(params, "groups", ADMIN_GROUPS);
+            ctx.user.groups.iter().any(|g| groups.contains(&g.as_str()))
+        },
         "P_Circuit_Breaker"  => ctx.system.panic_mode,
-        "P_Is_Admin_Path"    => {
+        "P_Is_Admin_Path" | "P_Is_Warmup_Path" => {
             if let Some(ref req) = ctx.req {
                 if let Some(ref path) = req.path {
-                    return path.starts_with("/admin/");
+                    return path.starts_with(param_prefix(id, params));
                 }
             }
             false
@@ -153,10 +350,6 @@ fn bit_eval(id: &str, ctx: &RequestContext) -> bool {
                     return false;
                 }
                 if let Some(expires) = jwt.expires_at {
-                    let now = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs() as i64;
                     return now < expires;
                 }
                 return false;
@@ -167,22 +360,33 @@ fn bit_eval(id: &str, ctx: &RequestContext) -> bool {
     }
 }
 
-fn eval_wire_rec(chip: &SemanticChip, id: &str, ctx: &RequestContext, chain: &mut Vec<String>) -> bool {
+/// `bit` dá o valor de cada bit: `bit_eval` sobre o contexto na decisão,
+/// uma atribuição livre na análise (`analyze`).
+fn eval_wire_rec(chip: &SemanticChip, id: &str, bit: &dyn Fn(&str) -> bool, chain: &mut Vec<String>) -> bool {
+    eval_wire_depth(chip, id, bit, chain, 0)
+}
+
+/// Fiação cíclica (lint `cycle`): um caminho com mais fios do que o chip
+/// declara repete algum, e o fio avalia `false` em vez de recursar sem fim.
+fn eval_wire_depth(chip: &SemanticChip, id: &str, bit: &dyn Fn(&str) -> bool, chain: &mut Vec<String>, depth: usize) -> bool {
     chain.push(id.to_string());
+    if depth > chip.wiring.len() {
+        return false;
+    }
     let w = match chip.find_wire(id) { Some(w) => w, None => return false };
     match &w.structure {
         WiringStructure::Sequence { sequence } => {
             for x in sequence {
                 if x.starts_with("W_") {
-                    if !eval_wire_rec(chip, x, ctx, chain) { return false; }
-                } else if !bit_eval(x, ctx) {
+                    if !eval_wire_depth(chip, x, bit, chain, depth + 1) { return false; }
+                } else if !bit(x) {
                     return false;
                 }
             }
             true
         }
         WiringStructure::Parallel { parallel } => {
-            let vals: Vec<bool> = parallel.policies.iter().map(|p| bit_eval(p, ctx)).collect();
+            let vals: Vec<bool> = parallel.policies.iter().map(|p| bit(p)).collect();
             match parallel.aggregator.as_str() {
                 "ANY" => vals.iter().any(|v| *v),
                 "ALL" => vals.iter().all(|v| *v),
@@ -192,20 +396,70 @@ fn eval_wire_rec(chip: &SemanticChip, id: &str, ctx: &RequestContext, chain: &mu
     }
 }
 
+/// Avalia um trigger (`W_x` ou `NOT(W_x)`) no instante `now`; devolve se
+/// disparou e os fios visitados.
+pub fn eval_trigger(chip: &SemanticChip, trigger: &str, ctx: &RequestContext, now: i64) -> (bool, Vec<String>) {
+    let mut chain = vec![];
+    let (negated, wire) = split_trigger(trigger);
+    let fired = eval_wire_rec(chip, wire, &|b| bit_eval(b, chip.bit_params(b), ctx, now), &mut chain) != negated;
+    (fired, chain)
+}
+
+/// `NOT(W_x)` → `(true, "W_x")`; `W_x` → `(false, "W_x")`.
+fn split_trigger(trigger: &str) -> (bool, &str) {
+    if trigger.starts_with("NOT(") {
+        (true, trigger.trim_start_matches("NOT(").trim_end_matches(")"))
+    } else {
+        (false, trigger)
+    }
+}
+
+/// Epoch atual em segundos (relógio de `decide` e `explain`).
+pub fn now_epoch() -> i64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// Decisão no relógio do sistema; em wasm32 (sem `SystemTime`) use `decide_at`.
 pub fn decide(chip: &SemanticChip, ctx: &RequestContext) -> Decision {
+    decide_at(chip, ctx, now_epoch())
+}
+
+/// Decisão com relógio fixo: mesmo chip, contexto e `now` ⇒ mesma decisão.
+pub fn decide_at(chip: &SemanticChip, ctx: &RequestContext, now: i64) -> Decision {
     for out in &chip.outputs {
         for (name, act) in &out.0 {
-            let mut chain = vec![];
-            let fired = if act.trigger.starts_with("NOT(") {
-                let inner = act.trigger.trim_start_matches("NOT(").trim_end_matches(")");
-                !eval_wire_rec(chip, inner, ctx, &mut chain)
-            } else {
-                eval_wire_rec(chip, &act.trigger, ctx, &mut chain)
-            };
+            let (fired, chain) = eval_trigger(chip, &act.trigger, ctx, now);
             if fired {
-                return Decision{ decision: name.clone(), why: act.action.clone(), trigger: act.trigger.clone(), chain };
+                return Decision{
+                    decision: name.clone(), why: act.action.clone(), trigger: act.trigger.clone(), chain,
+                    obligations: act.obligations.clone(), advice: act.advice.clone(), challenge: act.challenge.clone(),
+                };
             }
         }
     }
-    Decision{ decision:"deny_invalid_access".into(), why:"default_deny".into(), trigger:"none".into(), chain: vec![] }
+    Decision{ decision:"deny_invalid_access".into(), why:"default_deny".into(), trigger:"none".into(), chain: vec![], obligations: vec![], advice: vec![], challenge: None }
+}
+
+/// Chip de `policies/` com os imports resolvidos (biblioteca embutida no teste).
+#[cfg(test)]
+pub(crate) fn shipped_yaml(yaml: &str) -> String {
+    resolve_imports_with(yaml, &|_| Ok(include_str!("../../../policies/lib/zero_trust.yaml").to_string())).unwrap().yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta_and_strict_keys() {
+        let chip = SemanticChip::from_yaml_strict(&shipped_yaml(include_str!("../../../policies/vvz_core_v1.yaml"))).unwrap();
+        assert_eq!(chip.owners(), ["voulezvous-ops"]);
+        assert_eq!(chip.tenant(), Some("voulezvous"));
+
+        let typo = "version: tdln-chip/0.3\nmeta: { owner: [x] }\npolicies: []\nwiring: []\noutputs: []\n";
+        assert!(SemanticChip::from_yaml(typo).unwrap().owners().is_empty());
+        let err = SemanticChip::from_yaml_strict(typo).unwrap_err().to_string();
+        assert!(err.contains("meta.owner"), "{}", err);
+        assert!(SemanticChip::from_yaml_strict(&typo.replace("meta: { owner: [x] }", "outputz: []")).is_err());
+    }
 }
@@ -0,0 +1,182 @@
+//! Checagens estáticas do chip: referências quebradas, ciclos e construções
+//! que o avaliador aceita mas que sempre dão `false` (bit sem avaliador,
+//! fio dentro de `parallel`, trigger apontando para bit).
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{SemanticChip, WiringStructure, BUILTIN_BITS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    /// Onde no chip (ex.: `wiring.W_Admin_Access`, `outputs.deny_rate_limit`)
+    pub at: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}[{}] {}: {}", level, self.code, self.at, self.message)
+    }
+}
+
+pub fn lint(chip: &SemanticChip) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    let mut push = |severity, code, at: String, message: String| out.push(Diagnostic { severity, code, at, message });
+
+    let bits: BTreeSet<&str> = chip.policies.iter().map(|p| p.id.as_str()).collect();
+    let wires: HashMap<&str, &WiringStructure> = chip.wiring.iter().map(|w| (w.id.as_str(), &w.structure)).collect();
+    let mut used: BTreeSet<&str> = BTreeSet::new();
+
+    for (i, p) in chip.policies.iter().enumerate() {
+        let at = format!("policies.{}", p.id);
+        if chip.policies[..i].iter().any(|o| o.id == p.id) {
+            push(Severity::Error, "duplicate_bit", at.clone(), "bit declared twice".into());
+        }
+        if !BUILTIN_BITS.contains(&p.id.as_str()) {
+            push(Severity::Warning, "no_evaluator", at, "engine has no evaluator for this bit; it is always false".into());
+        }
+    }
+
+    for (i, w) in chip.wiring.iter().enumerate() {
+        let at = format!("wiring.{}", w.id);
+        if chip.wiring[..i].iter().any(|o| o.id == w.id) {
+            push(Severity::Error, "duplicate_wire", at.clone(), "wire declared twice; the first one is used".into());
+        }
+        if !w.id.starts_with("W_") {
+            push(Severity::Error, "wire_prefix", at.clone(), "wire ids must start with W_ (other ids are evaluated as bits)".into());
+        }
+        match &w.structure {
+            WiringStructure::Sequence { sequence } => {
+                for x in sequence {
+                    used.insert(x);
+                    if !bits.contains(x.as_str()) && !wires.contains_key(x.as_str()) {
+                        push(Severity::Error, "unknown_ref", at.clone(), format!("{} is neither a declared bit nor a wire", x));
+                    }
+                }
+            }
+            WiringStructure::Parallel { parallel } => {
+                if parallel.aggregator != "ANY" && parallel.aggregator != "ALL" {
+                    push(Severity::Error, "bad_aggregator", at.clone(), format!("aggregator {:?} is not ANY or ALL; wire is always false", parallel.aggregator));
+                }
+                for x in &parallel.policies {
+                    used.insert(x);
+                    if wires.contains_key(x.as_str()) {
+                        push(Severity::Error, "wire_in_parallel", at.clone(), format!("{} is a wire; parallel members are evaluated as bits, so it is always false", x));
+                    } else if !bits.contains(x.as_str()) {
+                        push(Severity::Error, "unknown_ref", at.clone(), format!("{} is not a declared bit", x));
+                    }
+                }
+            }
+        }
+    }
+
+    // Ciclo = recursão infinita no avaliador
+    for w in &chip.wiring {
+        if reaches(&wires, &w.id, &w.id, &mut BTreeSet::new()) {
+            push(Severity::Error, "cycle", format!("wiring.{}", w.id), "wire references itself (directly or through other wires)".into());
+        }
+    }
+
+    let mut outputs: BTreeSet<&str> = BTreeSet::new();
+    if chip.outputs.is_empty() {
+        push(Severity::Warning, "no_outputs", "outputs".into(), "no outputs; every request gets default_deny".into());
+    }
+    for (name, act) in chip.outputs.iter().flat_map(|o| o.0.iter()) {
+        let at = format!("outputs.{}", name);
+        if !outputs.insert(name) {
+            push(Severity::Warning, "duplicate_output", at.clone(), "output declared twice; only the first can fire".into());
+        }
+        if name.starts_with("deny") && !act.obligations.is_empty() {
+            push(Severity::Warning, "obligations_on_deny", at.clone(), "deny outputs are refused as-is; their obligations are never applied".into());
+        }
+        let target = act.trigger.strip_prefix("NOT(").and_then(|t| t.strip_suffix(')')).unwrap_or(&act.trigger);
+        used.insert(target);
+        if wires.contains_key(target) {
+            continue;
+        }
+        let what = if bits.contains(target) { "a bit" } else { "not a declared wire" };
+        let outcome = if target.len() == act.trigger.len() { "never fires" } else { "always fires" };
+        push(Severity::Error, "trigger_not_wire", at, format!("trigger {} is {}; only wires are evaluated, so this output {}", target, what, outcome));
+    }
+
+    for p in &chip.policies {
+        if !used.contains(p.id.as_str()) {
+            push(Severity::Warning, "unused_bit", format!("policies.{}", p.id), "bit is not referenced by any wire".into());
+        }
+    }
+    for w in &chip.wiring {
+        if !used.contains(w.id.as_str()) {
+            push(Severity::Warning, "unused_wire", format!("wiring.{}", w.id), "wire is not referenced by any wire or output".into());
+        }
+    }
+
+    out.sort_by_key(|d| std::cmp::Reverse(d.severity));
+    out
+}
+
+fn reaches<'a>(wires: &HashMap<&'a str, &'a WiringStructure>, from: &'a str, target: &str, seen: &mut BTreeSet<&'a str>) -> bool {
+    let Some(WiringStructure::Sequence { sequence }) = wires.get(from) else { return false };
+    for x in sequence.iter().filter(|x| x.starts_with("W_")) {
+        if x == target {
+            return true;
+        }
+        if seen.insert(x) && reaches(wires, x, target, seen) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(yaml: &str) -> Vec<&'static str> {
+        lint(&SemanticChip::from_yaml(yaml).unwrap()).iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn clean_chip_has_no_errors() {
+        let yaml = crate::shipped_yaml(include_str!("../../../policies/ubl_core_v1.yaml"));
+        let diags = lint(&SemanticChip::from_yaml(&yaml).unwrap());
+        assert!(diags.iter().all(|d| d.severity == Severity::Warning || d.code == "wire_in_parallel"), "{:?}", diags);
+    }
+
+    #[test]
+    fn flags_broken_references_and_cycles() {
+        let yaml = r#"
+version: "tdln-chip/0.3"
+policies:
+  - id: P_Transport_Secure
+  - id: P_Custom
+wiring:
+  - id: W_A
+    structure: { sequence: [P_Transport_Secure, W_B, P_Missing] }
+  - id: W_B
+    structure: { sequence: [W_A] }
+  - id: W_P
+    structure: { parallel: { policies: [W_A, P_Custom], aggregator: SOME } }
+outputs:
+  - deny: { trigger: "NOT(P_Transport_Secure)", action: "403", obligations: [{ max_body_bytes: 1 }] }
+  - allow: { trigger: W_A, action: "200" }
+"#;
+        let c = codes(yaml);
+        for code in ["unknown_ref", "cycle", "wire_in_parallel", "bad_aggregator", "trigger_not_wire", "no_evaluator", "unused_wire", "obligations_on_deny"] {
+            assert!(c.contains(&code), "missing {} in {:?}", code, c);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Versões do formato do chip e migração para a atual.
+//!
+//! `tdln-chip/0.4` só muda a gramática dos triggers: `W_x` ou `NOT(W_x)`
+//! com `W_x` declarado em `wiring`. Nas versões anteriores um trigger que
+//! aponta para um bit (`NOT(P_Rate_Bucket_OK)`) é aceito, mas avaliado como
+//! fio inexistente — `false`, então o `NOT(..)` sempre dispara. A migração
+//! embrulha o bit num fio `W_Bit_<bit>`; a decisão dessas saídas muda (passa
+//! a fazer o que o autor escreveu), por isso cada uma vira uma nota.
+//!
+//! A reescrita é textual para preservar comentários; se o resultado não for
+//! idêntico à migração estrutural, o YAML é regravado sem comentários.
+
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+
+use crate::split_trigger;
+
+/// Versões que o engine avalia; a última é a atual.
+pub const CHIP_VERSIONS: &[&str] = &["tdln-chip/0.1", "tdln-chip/0.3", CHIP_VERSION];
+pub const CHIP_VERSION: &str = "tdln-chip/0.4";
+
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub from: String,
+    pub yaml: String,
+    /// O que mudou e por quê (vazio se já estava na versão atual)
+    pub notes: Vec<String>,
+    /// `false` quando foi preciso regravar o documento inteiro
+    pub comments_preserved: bool,
+}
+
+impl Migration {
+    pub fn changed(&self) -> bool {
+        self.from != CHIP_VERSION
+    }
+}
+
+pub fn migrate(yaml: &str) -> Result<Migration> {
+    let mut doc: Value = serde_yaml::from_str(yaml)?;
+    let from = doc.get("version").and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("chip has no `version`"))?
+        .to_string();
+    if !CHIP_VERSIONS.contains(&from.as_str()) {
+        anyhow::bail!("unsupported chip version {:?} (supported: {})", from, CHIP_VERSIONS.join(", "));
+    }
+    if from == CHIP_VERSION {
+        return Ok(Migration { from, yaml: yaml.to_string(), notes: vec![], comments_preserved: true });
+    }
+
+    let mut wires: Vec<String> = doc.get("wiring").and_then(Value::as_sequence).into_iter().flatten()
+        .filter_map(|w| w.get("id").and_then(Value::as_str).map(String::from))
+        .collect();
+    // fios de bibliotecas (`imports:`) só aparecem na resolução, que confere a referência
+    let has_imports = doc.get("imports").is_some();
+    let mut notes = vec![format!("version: {} → {}", from, CHIP_VERSION)];
+    // (trigger antigo, novo) e fios a criar
+    let mut rewrites: Vec<(String, String)> = vec![];
+    let mut new_wires: Vec<(String, String)> = vec![];
+
+    for out in doc.get_mut("outputs").and_then(Value::as_sequence_mut).into_iter().flatten() {
+        for (name, act) in out.as_mapping_mut().into_iter().flat_map(|m| m.iter_mut()) {
+            let name = name.as_str().unwrap_or("?").to_string();
+            let Some(trigger) = act.get("trigger").and_then(Value::as_str).map(String::from) else { continue };
+            let (negated, target) = split_trigger(&trigger);
+            if wires.iter().any(|w| w == target) || (has_imports && target.starts_with("W_")) {
+                continue;
+            }
+            if target.starts_with("W_") {
+                anyhow::bail!("outputs.{}: trigger references undeclared wire {}; fix it by hand", name, target);
+            }
+            let wire = format!("W_Bit_{}", target);
+            let new = if negated { format!("NOT({})", wire) } else { wire.clone() };
+            let outcome = if negated { "always fired" } else { "never fired" };
+            notes.push(format!("outputs.{}: trigger {} referenced bit {} directly and {}; now {} — its decisions change",
+                name, trigger, target, outcome, new));
+            act["trigger"] = Value::String(new.clone());
+            if !wires.contains(&wire) {
+                wires.push(wire.clone());
+                new_wires.push((wire, target.to_string()));
+            }
+            rewrites.push((trigger, new));
+        }
+    }
+
+    doc["version"] = Value::String(CHIP_VERSION.into());
+    if !new_wires.is_empty() {
+        if doc.get("wiring").and_then(Value::as_sequence).is_none() {
+            doc["wiring"] = Value::Sequence(vec![]);
+        }
+        let seq = doc["wiring"].as_sequence_mut().expect("wiring is a sequence");
+        for (wire, bit) in &new_wires {
+            let mut structure = Mapping::new();
+            structure.insert("sequence".into(), Value::Sequence(vec![Value::String(bit.clone())]));
+            let mut entry = Mapping::new();
+            entry.insert("id".into(), Value::String(wire.clone()));
+            entry.insert("structure".into(), Value::Mapping(structure));
+            seq.push(Value::Mapping(entry));
+        }
+    }
+
+    let text = rewrite_text(yaml, &rewrites, &new_wires);
+    let (yaml, comments_preserved) = match text.as_deref().map(serde_yaml::from_str::<Value>) {
+        Some(Ok(v)) if v == doc => (text.unwrap(), true),
+        _ => (serde_yaml::to_string(&doc)?, false),
+    };
+    Ok(Migration { from, yaml, notes, comments_preserved })
+}
+
+/// Reescrita linha a linha: `version:`, os triggers e os fios novos ao fim
+/// da seção `wiring:`. `None` se o layout não for o esperado.
+fn rewrite_text(yaml: &str, rewrites: &[(String, String)], new_wires: &[(String, String)]) -> Option<String> {
+    let mut lines: Vec<String> = yaml.lines().map(String::from).collect();
+    let version = lines.iter().position(|l| l.starts_with("version:"))?;
+    lines[version] = format!("version: \"{}\"", CHIP_VERSION);
+
+    for line in lines.iter_mut().filter(|l| l.contains("trigger:")) {
+        for (old, new) in rewrites {
+            let at = line.find("trigger:")? + "trigger:".len();
+            let value = line[at..].trim_start().trim_start_matches(['"', '\'']);
+            if value.starts_with(old.as_str()) && !value[old.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                let start = line.len() - value.len();
+                line.replace_range(start..start + old.len(), new);
+                break;
+            }
+        }
+    }
+
+    if !new_wires.is_empty() {
+        let wiring = lines.iter().position(|l| l.starts_with("wiring:"))?;
+        let indent = lines[wiring + 1..].iter().find(|l| l.trim_start().starts_with("- "))
+            .map(|l| l.len() - l.trim_start().len())
+            .unwrap_or(2);
+        // fim da seção: próxima chave de topo, recuando comentários e linhas em branco
+        let next = lines[wiring + 1..].iter().position(|l| !l.is_empty() && !l.starts_with([' ', '#']))
+            .map(|i| i + wiring + 1)
+            .unwrap_or(lines.len());
+        let mut end = next;
+        while end > wiring + 1 && (lines[end - 1].trim().is_empty() || lines[end - 1].starts_with('#')) {
+            end -= 1;
+        }
+        let pad = " ".repeat(indent);
+        let mut block = vec![String::new(), format!("{}# tdln-chip/0.4: triggers só referenciam fios", pad)];
+        for (wire, bit) in new_wires {
+            block.push(format!("{}- id: {}", pad, wire));
+            block.push(format!("{}  structure:", pad));
+            block.push(format!("{}    sequence: [{}]", pad, bit));
+        }
+        lines.splice(end..end, block);
+    }
+
+    let mut out = lines.join("\n");
+    if yaml.ends_with('\n') {
+        out.push('\n');
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decide, RequestContext, SemanticChip};
+
+    /// Como ubl_core_v3 antes da migração: 0.3, com import e `NOT(P_Rate_Bucket_OK)`.
+    const V03: &str = r#"# Conforme CONSTITUTION.md (2026-01-03)
+version: "tdln-chip/0.3"
+
+imports:
+  - path: lib/zero_trust.yaml
+
+policies:
+  - id: P_Rate_Bucket_OK
+    logic: "context.rate.ok == true"
+
+wiring:
+  - id: W_Webhook_Trusted
+    structure:
+      sequence: [P_Webhook_Verified]
+
+outputs:
+  - deny_rate_limit:
+      trigger: NOT(P_Rate_Bucket_OK)
+      action: "HTTP 429"
+
+  - allow_standard_access:
+      trigger: W_ZeroTrust_Standard
+      action: "HTTP 200"
+"#;
+
+    #[test]
+    fn wraps_bare_bit_triggers_and_keeps_comments() {
+        let m = migrate(V03).unwrap();
+        assert!(m.changed() && m.comments_preserved, "{}", m.yaml);
+        assert!(m.yaml.contains("# Conforme CONSTITUTION.md"));
+        assert!(m.yaml.contains("trigger: NOT(W_Bit_P_Rate_Bucket_OK)"));
+        assert!(m.notes.iter().any(|n| n.contains("deny_rate_limit") && n.contains("always fired")));
+
+        let old = SemanticChip::from_yaml(&crate::shipped_yaml(V03)).unwrap();
+        let new = SemanticChip::from_yaml_strict(&crate::shipped_yaml(&m.yaml)).unwrap();
+        assert_eq!(new.version, CHIP_VERSION);
+        // sem rate no contexto o bit é true: o 429 deixa de ser incondicional
+        let ctx = RequestContext::default();
+        assert_eq!(decide(&old, &ctx).decision, "deny_rate_limit");
+        assert_ne!(decide(&new, &ctx).decision, "deny_rate_limit");
+
+        let again = migrate(&m.yaml).unwrap();
+        assert!(!again.changed());
+        assert_eq!(again.yaml, m.yaml);
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_bare_triggers_in_current() {
+        let chip = |v: &str, trigger: &str| format!("version: {}\npolicies: []\nwiring: []\noutputs:\n  - a: {{ trigger: \"{}\", action: x }}\n", v, trigger);
+        let err = SemanticChip::from_yaml(&chip("tdln-chip/9.0", "W_A")).unwrap_err().to_string();
+        assert!(err.contains("tdln-chip/0.4"), "{}", err);
+        assert!(SemanticChip::from_yaml(&chip("tdln-chip/0.3", "NOT(P_X)")).is_ok());
+        assert!(SemanticChip::from_yaml(&chip(CHIP_VERSION, "NOT(P_X)")).is_err());
+
+        let m = migrate(&chip("tdln-chip/0.1", "P_X")).unwrap();
+        assert!(m.yaml.contains("W_Bit_P_X"));
+        assert!(SemanticChip::from_yaml(&m.yaml).is_ok());
+    }
+}
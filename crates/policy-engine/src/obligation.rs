@@ -0,0 +1,200 @@
+//! Obrigações e conselhos (à la XACML) presos a uma saída do chip.
+//! `obligations` precisam ser cumpridas por quem aplica a decisão — o
+//! policy-proxy recusa a request se não conseguir; `advice` é só repassado
+//! (ledger, upstream) e pode ser ignorado. `challenge` troca a saída por um
+//! pedido de step-up: o proxy responde 401 apontando para a passkey do gateway
+//! e reavalia quando a sessão volta elevada.
+//!
+//! ```yaml
+//! - allow_read_only:
+//!     trigger: W_ZeroTrust_Standard
+//!     action: "200"
+//!     obligations:
+//!       - methods: [GET, HEAD]
+//!       - redact_headers: [Cookie]
+//!       - log: { severity: high }
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Obligation {
+    /// Cabeçalhos adicionados à request encaminhada
+    SetHeaders(BTreeMap<String, String>),
+    /// Cabeçalhos removidos antes de encaminhar
+    RedactHeaders(Vec<String>),
+    /// Métodos HTTP aceitos (ex.: só leitura)
+    Methods(Vec<String>),
+    MaxBodyBytes(u64),
+    /// Sessão precisa passar por passkey em `url` antes de seguir
+    StepUp { url: String },
+    /// Severidade do registro no ledger
+    Log { severity: String },
+}
+
+/// Saída do tipo challenge (ex.: admin sem passkey recente)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    /// Cerimônia WebAuthn no gateway (ex.: `/auth/passkey/login`)
+    pub url: String,
+}
+
+impl Challenge {
+    pub(crate) fn check(&self) -> Result<()> {
+        check_url("challenge", &self.url)
+    }
+}
+
+fn check_url(what: &str, url: &str) -> Result<()> {
+    if !url.starts_with('/') && !url.starts_with("https://") {
+        anyhow::bail!("{} url must be a path or https:// URL, got {:?}", what, url);
+    }
+    Ok(())
+}
+
+pub const LOG_SEVERITIES: &[&str] = &["low", "medium", "high", "critical"];
+
+impl Obligation {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::SetHeaders(_) => "set_headers",
+            Self::RedactHeaders(_) => "redact_headers",
+            Self::Methods(_) => "methods",
+            Self::MaxBodyBytes(_) => "max_body_bytes",
+            Self::StepUp { .. } => "step_up",
+            Self::Log { .. } => "log",
+        }
+    }
+
+    /// Valores que o proxy conseguiria aplicar; o resto é erro ao carregar o chip.
+    pub(crate) fn check(&self) -> Result<()> {
+        let header = |h: &str| !h.is_empty() && h.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_".contains(&b));
+        match self {
+            Self::SetHeaders(h) => {
+                if let Some((k, v)) = h.iter().find(|(k, v)| !header(k) || v.bytes().any(|b| b.is_ascii_control())) {
+                    anyhow::bail!("invalid header {}: {:?}", k, v);
+                }
+            }
+            Self::RedactHeaders(h) => {
+                if let Some(k) = h.iter().find(|k| !header(k)) {
+                    anyhow::bail!("invalid header name {:?}", k);
+                }
+            }
+            Self::Methods(m) => {
+                if m.is_empty() || m.iter().any(|m| m.is_empty() || !m.bytes().all(|b| b.is_ascii_uppercase())) {
+                    anyhow::bail!("methods must be a non-empty list of uppercase HTTP methods");
+                }
+            }
+            Self::MaxBodyBytes(0) => anyhow::bail!("max_body_bytes must be > 0"),
+            Self::MaxBodyBytes(_) => {}
+            Self::StepUp { url } => check_url("step_up", url)?,
+            Self::Log { severity } => {
+                if !LOG_SEVERITIES.contains(&severity.as_str()) {
+                    anyhow::bail!("log severity {:?} is not one of {}", severity, LOG_SEVERITIES.join(", "));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SemanticChip;
+
+    const CHIP: &str = r#"
+version: "tdln-chip/0.4"
+policies: [{ id: P_Transport_Secure }]
+wiring:
+  - id: W_Tls
+    structure: { sequence: [P_Transport_Secure] }
+outputs:
+  - allow_read_only:
+      trigger: W_Tls
+      action: "200"
+      obligations:
+        - methods: [GET, HEAD]
+        - set_headers: { X-Ubl-Mode: read-only }
+        - redact_headers: [Cookie]
+        - max_body_bytes: 1024
+        - step_up: { url: /auth/passkey/register }
+        - log: { severity: high }
+      advice:
+        - log: { severity: low }
+"#;
+
+    #[test]
+    fn decision_carries_obligations_and_advice() {
+        let chip = SemanticChip::from_yaml(CHIP).unwrap();
+        let mut ctx = crate::RequestContext::default();
+        ctx.transport.tls_version = 1.3;
+        let d = crate::decide(&chip, &ctx);
+        let kinds: Vec<_> = d.obligations.iter().map(|o| o.kind()).collect();
+        assert_eq!(kinds, ["methods", "set_headers", "redact_headers", "max_body_bytes", "step_up", "log"]);
+        assert_eq!(d.advice.len(), 1);
+        let json = serde_json::to_value(&d).unwrap();
+        assert_eq!(json["obligations"][0], serde_json::json!({ "methods": ["GET", "HEAD"] }));
+
+        // default deny não tem obrigações (e não as serializa)
+        let deny = crate::decide(&chip, &crate::RequestContext::default());
+        assert!(serde_json::to_value(&deny).unwrap().get("obligations").is_none());
+    }
+
+    #[test]
+    fn rejects_obligations_the_proxy_cannot_apply() {
+        for (bad, expect) in [
+            ("- methods: [get]", "uppercase"),
+            ("- max_body_bytes: 0", "> 0"),
+            ("- step_up: { url: \"http://x\" }", "step_up url"),
+            ("- log: { severity: loud }", "severity"),
+            ("- redact_headers: [\"bad header\"]", "header name"),
+            ("- rate_limit: 10", "unknown variant"),
+        ] {
+            let yaml = CHIP.replace("- methods: [GET, HEAD]", bad);
+            let err = format!("{:#}", SemanticChip::from_yaml(&yaml).unwrap_err());
+            assert!(err.contains(expect), "{}: {}", bad, err);
+        }
+    }
+
+    /// MFA sob demanda: admin em /admin/ sem passkey do gateway recebe challenge;
+    /// com a sessão elevada (`webauthn`) o mesmo chip libera.
+    #[test]
+    fn challenge_until_the_session_is_stepped_up() {
+        let yaml = r#"
+version: "tdln-chip/0.4"
+policies:
+  - id: P_Transport_Secure
+  - id: P_Role_Admin
+  - id: P_Is_Admin_Path
+  - id: P_User_Passkey
+    params: { methods: [webauthn] }
+wiring:
+  - id: W_Admin
+    structure: { sequence: [P_Transport_Secure, P_Role_Admin, P_Is_Admin_Path] }
+  - id: W_Admin_Write
+    structure: { sequence: [W_Admin, P_User_Passkey] }
+outputs:
+  - allow_admin_write: { trigger: W_Admin_Write, action: "200" }
+  - challenge_admin_step_up: { trigger: W_Admin, action: "401", challenge: { url: /auth/passkey/login } }
+"#;
+        let chip = SemanticChip::from_yaml(yaml).unwrap();
+        let mut ctx = crate::RequestContext::default();
+        ctx.transport.tls_version = 1.3;
+        ctx.user.groups = vec!["ubl-ops".into()];
+        ctx.req = Some(crate::ReqCtx { path: Some("/admin/deploy".into()), method: None });
+        ctx.auth = crate::AuthCtx { method: "access-passkey".into(), rp_id: "app.ubl.agency".into() };
+        let d = crate::decide(&chip, &ctx);
+        assert_eq!(d.decision, "challenge_admin_step_up");
+        assert_eq!(d.challenge.unwrap().url, "/auth/passkey/login");
+
+        ctx.auth.method = "webauthn".into();
+        let d = crate::decide(&chip, &ctx);
+        assert_eq!((d.decision.as_str(), d.challenge), ("allow_admin_write", None));
+
+        let bad = yaml.replace("url: /auth/passkey/login", "url: \"javascript:x\"");
+        assert!(format!("{:#}", SemanticChip::from_yaml(&bad).unwrap_err()).contains("challenge url"));
+    }
+}
@@ -1,32 +1,142 @@
 //! WASM bindings para policy-engine
+//!
+//! Entradas aceitam objeto JS ou string JSON; saídas são objetos JS.
+//! `WasmPolicyEngine.fromPack` só carrega o chip se o pack estiver assinado
+//! por chaves confiáveis e o BLAKE3 do YAML bater com o assinado.
 
-#[cfg(target_arch = "wasm32")]
+use policy_pack::{AnyPack, TrustSet, TrustedKey, VerifiedPack};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
-use crate::{SemanticChip, RequestContext, decide};
 
-#[cfg(target_arch = "wasm32")]
+use crate::{decide_at, explain_at, lint, ChipMeta, RequestContext, SemanticChip};
+
 #[wasm_bindgen]
 pub struct WasmPolicyEngine {
     chip: SemanticChip,
+    pack: Option<VerifiedPack>,
+}
+
+#[derive(Serialize)]
+struct Metadata<'a> {
+    version: &'a str,
+    meta: &'a ChipMeta,
+    bits: Vec<&'a str>,
+    wires: Vec<&'a str>,
+    outputs: Vec<&'a str>,
+    /// `null` quando carregado sem pack (`new`)
+    pack: Option<&'a VerifiedPack>,
 }
 
-#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 impl WasmPolicyEngine {
+    /// Chip sem verificação — só para desenvolvimento e testes.
     #[wasm_bindgen(constructor)]
     pub fn new(yaml_content: &str) -> Result<WasmPolicyEngine, JsValue> {
         let chip = SemanticChip::from_yaml(yaml_content)
             .map_err(|e| JsValue::from_str(&format!("Failed to parse YAML: {}", e)))?;
-        Ok(Self { chip })
+        Ok(Self { chip, pack: None })
+    }
+
+    /// `trusted`: chave pública (PEM ou base64 de PEM, como `POLICY_PUBKEY_B64`)
+    /// ou array delas; `threshold` padrão 1.
+    #[wasm_bindgen(js_name = fromPack)]
+    pub fn from_pack(yaml_content: &str, pack: JsValue, trusted: JsValue, threshold: Option<usize>) -> Result<WasmPolicyEngine, JsValue> {
+        let verified = verify(yaml_content, &pack, &trusted, threshold)?;
+        let mut engine = Self::new(yaml_content)?;
+        verified.check_chip_version(&engine.chip.version).map_err(err)?;
+        engine.pack = Some(verified);
+        Ok(engine)
+    }
+
+    #[wasm_bindgen]
+    pub fn decide(&self, ctx: JsValue) -> Result<JsValue, JsValue> {
+        let ctx: RequestContext = from_js(&ctx, "context")?;
+        to_js(&decide_at(&self.chip, &ctx, now()))
+    }
+
+    /// Árvore da decisão (saídas, fios, bits, valores do contexto) e o menor flip.
+    #[wasm_bindgen]
+    pub fn explain(&self, ctx: JsValue) -> Result<JsValue, JsValue> {
+        let ctx: RequestContext = from_js(&ctx, "context")?;
+        to_js(&explain_at(&self.chip, &ctx, now()))
+    }
+
+    /// Mesma explicação, renderizada como texto.
+    #[wasm_bindgen(js_name = explainText)]
+    pub fn explain_text(&self, ctx: JsValue) -> Result<String, JsValue> {
+        let ctx: RequestContext = from_js(&ctx, "context")?;
+        Ok(explain_at(&self.chip, &ctx, now()).to_string())
+    }
+
+    #[wasm_bindgen]
+    pub fn metadata(&self) -> Result<JsValue, JsValue> {
+        to_js(&Metadata {
+            version: &self.chip.version,
+            meta: &self.chip.meta,
+            bits: self.chip.policies.iter().map(|p| p.id.as_str()).collect(),
+            wires: self.chip.wiring.iter().map(|w| w.id.as_str()).collect(),
+            outputs: self.chip.outputs.iter().flat_map(|o| o.0.keys().map(String::as_str)).collect(),
+            pack: self.pack.as_ref(),
+        })
     }
 
+    /// Diagnósticos do lint (`severity`, `code`, `at`, `message`).
     #[wasm_bindgen]
-    pub fn decide(&self, ctx_json: &str) -> Result<String, JsValue> {
-        let ctx: RequestContext = serde_json::from_str(ctx_json)
-            .map_err(|e| JsValue::from_str(&format!("Failed to parse context: {}", e)))?;
-        
-        let decision = decide(&self.chip, &ctx);
-        serde_json::to_string(&decision)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize decision: {}", e)))
+    pub fn diagnostics(&self) -> Result<JsValue, JsValue> {
+        to_js(&lint(&self.chip))
     }
 }
+
+/// Verifica pack + YAML sem montar o engine; devolve o pack verificado.
+#[wasm_bindgen(js_name = verifyPack)]
+pub fn verify_pack(yaml_content: &str, pack: JsValue, trusted: JsValue, threshold: Option<usize>) -> Result<JsValue, JsValue> {
+    to_js(&verify(yaml_content, &pack, &trusted, threshold)?)
+}
+
+fn verify(yaml: &str, pack: &JsValue, trusted: &JsValue, threshold: Option<usize>) -> Result<VerifiedPack, JsValue> {
+    let pack = AnyPack::from_json(&json_string(pack)?).map_err(err)?;
+    let keys: Vec<String> = if trusted.is_string() {
+        vec![trusted.as_string().unwrap_or_default()]
+    } else {
+        from_js(trusted, "trusted keys")?
+    };
+    let keys = keys.iter().map(|k| {
+        let key = if k.trim_start().starts_with("-----BEGIN") {
+            policy_pack::public_key_from_pem(k)
+        } else {
+            policy_pack::public_key_from_pem_b64(k)
+        };
+        key.map(TrustedKey::new).map_err(err)
+    }).collect::<Result<Vec<_>, _>>()?;
+    let trust = TrustSet::new(keys, threshold.unwrap_or(1)).map_err(err)?;
+    let verified = pack.verify(&trust, now() as u64).map_err(err)?;
+    verified.check_chip(yaml.as_bytes()).map_err(err)?;
+    Ok(verified)
+}
+
+/// SystemTime não existe em wasm32-unknown-unknown: relógio do JS.
+fn now() -> i64 {
+    (js_sys::Date::now() / 1000.0) as i64
+}
+
+fn err(e: anyhow::Error) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+/// Objeto JS → JSON; string passa direto.
+fn json_string(v: &JsValue) -> Result<String, JsValue> {
+    if let Some(s) = v.as_string() {
+        return Ok(s);
+    }
+    js_sys::JSON::stringify(v)?.as_string().ok_or_else(|| JsValue::from_str("value is not JSON-serializable"))
+}
+
+fn from_js<T: DeserializeOwned>(v: &JsValue, what: &str) -> Result<T, JsValue> {
+    serde_json::from_str(&json_string(v)?).map_err(|e| JsValue::from_str(&format!("Failed to parse {}: {}", what, e)))
+}
+
+fn to_js<T: Serialize>(v: &T) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(v).map_err(|e| JsValue::from_str(&format!("Failed to serialize: {}", e)))?;
+    js_sys::JSON::parse(&json)
+}
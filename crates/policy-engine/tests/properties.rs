@@ -0,0 +1,206 @@
+//! Propriedades de `decide` sobre contextos e chips arbitrários (proptest):
+//! não entra em pânico, termina mesmo com fiação cíclica, é determinística
+//! com relógio fixo, só devolve saídas declaradas ou o default deny, e o
+//! `DecisionCache` devolve exatamente o que `decide` devolveria.
+#![cfg(not(target_arch = "wasm32"))]
+
+use policy_engine::*;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+use serde_json::{json, Map, Value};
+
+const NOW: i64 = 1_700_000_000;
+
+/// Bits embutidos + um sem avaliador
+fn bit() -> impl Strategy<Value = String> {
+    select([BUILTIN_BITS, &["P_Unknown"]].concat()).prop_map(String::from)
+}
+
+/// Bit ou fio `W_0..=W_n` (`W_n` não declarado; qualquer fio pode fechar ciclo)
+fn member(wires: usize) -> impl Strategy<Value = String> {
+    prop_oneof![bit(), (0..=wires).prop_map(|i| format!("W_{}", i))]
+}
+
+/// Valores realistas (os que os bits aceitam) misturados com lixo.
+fn text(pool: &'static [&'static str]) -> impl Strategy<Value = String> {
+    prop_oneof![select(pool).prop_map(String::from), "[a-z./-]{0,12}"]
+}
+
+fn chip() -> impl Strategy<Value = SemanticChip> {
+    (1usize..7).prop_flat_map(|n| {
+        let structure = prop_oneof![
+            vec(member(n), 0..4).prop_map(|s| json!({ "sequence": s })),
+            (vec(member(n), 0..4), select(vec!["ANY", "ALL", "XOR"]))
+                .prop_map(|(p, a)| json!({ "parallel": { "policies": p, "aggregator": a } })),
+        ];
+        let trigger = (any::<bool>(), member(n)).prop_map(|(neg, t)| if neg { format!("NOT({})", t) } else { t });
+        let rp_ids = option::of(vec(text(&["app.ubl.agency", "voulezvous.tv"]), 1..3));
+        (vec(structure, n), vec(trigger, 1..6), rp_ids)
+    }).prop_map(|(structures, triggers, rp_ids)| {
+        let wiring: Vec<Value> = structures.into_iter().enumerate()
+            .map(|(i, s)| json!({ "id": format!("W_{}", i), "structure": s }))
+            .collect();
+        let outputs: Vec<Value> = triggers.into_iter().enumerate().map(|(i, t)| {
+            let mut out = Map::new();
+            out.insert(format!("out_{}", i), json!({ "trigger": t, "action": format!("action {}", i) }));
+            Value::Object(out)
+        }).collect();
+        let policies: Vec<Value> = rp_ids.into_iter().map(|r| json!({ "id": "P_User_Passkey", "params": { "rp_ids": r } })).collect();
+        // 0.3 aceita triggers em bits e fios não declarados: o pior caso
+        let doc = json!({ "version": "tdln-chip/0.3", "policies": policies, "wiring": wiring, "outputs": outputs });
+        SemanticChip::from_yaml(&doc.to_string()).expect("generated chip parses")
+    })
+}
+
+fn context() -> impl Strategy<Value = RequestContext> {
+    let tls = prop_oneof![select(vec![0.0f32, 1.0, 1.2, 1.3, 1.4]), any::<f32>()];
+    let flag = || option::of(option::of(any::<bool>()));
+    (
+        (tls, any::<bool>(), text(&["Cloudflare Edge", "UBL Local CA"])),
+        (text(&["access-passkey", "webauthn", "password"]), text(&["app.ubl.agency", "voulezvous.tv"])),
+        vec(text(&["ubl-ops", "voulezvous-ops"]), 0..3),
+        any::<bool>(),
+        option::of(option::of(text(&["/admin/deploy", "/mcp", "/", "/admin", "/warmup"]))),
+        flag(),
+        flag(),
+        option::of((option::of(any::<bool>()), option::of(NOW - 5..NOW + 5))),
+    ).prop_map(|((tls, verified, issuer), (method, rp_id), groups, panic, path, rate, webhook, jwt)| {
+        let mut ctx = RequestContext::default();
+        ctx.transport.tls_version = tls;
+        ctx.mtls = MtlsCtx { verified, issuer };
+        ctx.auth = AuthCtx { method, rp_id };
+        ctx.user.groups = groups;
+        ctx.system.panic_mode = panic;
+        ctx.req = path.map(|path| ReqCtx { path, method: None });
+        ctx.rate = rate.map(|ok| RateCtx { ok });
+        ctx.webhook = webhook.map(|verified| WebhookCtx { verified });
+        ctx.legacy_jwt = jwt.map(|(valid, expires_at)| LegacyJwtCtx { valid, expires_at });
+        ctx
+    })
+}
+
+fn shipped() -> Vec<(&'static str, SemanticChip)> {
+    [
+        ("ubl_core_v1", include_str!("../../../policies/ubl_core_v1.yaml")),
+        ("ubl_core_v3", include_str!("../../../policies/ubl_core_v3.yaml")),
+        ("vvz_core_v1", include_str!("../../../policies/vvz_core_v1.yaml")),
+    ].into_iter().map(|(name, yaml)| (name, SemanticChip::from_yaml(&resolved(yaml)).unwrap())).collect()
+}
+
+/// Os chips importam `policies/lib/zero_trust.yaml`.
+fn resolved(yaml: &str) -> String {
+    resolve_imports_with(yaml, &|_| Ok(include_str!("../../../policies/lib/zero_trust.yaml").to_string())).unwrap().yaml
+}
+
+/// Saída declarada (com o trigger e a ação dela) ou o default deny.
+fn check_declared(chip: &SemanticChip, d: &Decision) -> Result<(), TestCaseError> {
+    if d.why == "default_deny" {
+        prop_assert_eq!((d.decision.as_str(), d.trigger.as_str()), ("deny_invalid_access", "none"));
+        prop_assert!(d.chain.is_empty());
+        return Ok(());
+    }
+    let declared = chip.outputs.iter().flat_map(|o| o.0.iter())
+        .any(|(name, act)| *name == d.decision && act.trigger == d.trigger && act.action == d.why);
+    prop_assert!(declared, "undeclared decision {:?}", d);
+    Ok(())
+}
+
+fn check_decide(chip: &SemanticChip, ctx: &RequestContext) -> Result<(), TestCaseError> {
+    let d = decide_at(chip, ctx, NOW);
+    let again = decide_at(chip, ctx, NOW);
+    prop_assert_eq!((&d.decision, &d.why, &d.trigger, &d.chain), (&again.decision, &again.why, &again.trigger, &again.chain));
+    check_declared(chip, &d)?;
+    // explain refaz o caminho por conta própria e precisa concordar
+    let e = explain_at(chip, ctx, NOW);
+    prop_assert_eq!(&e.decision.decision, &d.decision);
+    if d.why != "default_deny" {
+        prop_assert!(e.steps.last().is_some_and(|s| s.fired && s.output == d.decision));
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn decide_is_total_deterministic_and_declared(chip in chip(), ctx in context()) {
+        check_decide(&chip, &ctx)?;
+    }
+
+    #[test]
+    fn shipped_chips_only_decide_declared_outputs(ctx in context()) {
+        for (_, chip) in shipped() {
+            check_decide(&chip, &ctx)?;
+        }
+    }
+
+    /// Os `invariants` de ubl_core_v1 valem sobre contextos reais, não só
+    /// sobre atribuições de bits (`analyze`).
+    #[test]
+    fn v1_allows_only_with_zero_trust(ctx in context()) {
+        let (_, chip) = &shipped()[0];
+        let d = decide_at(chip, &ctx, NOW);
+        if d.decision.starts_with("allow_") {
+            prop_assert!(ctx.transport.tls_version >= 1.3);
+            prop_assert!(ctx.mtls.verified && ["Cloudflare Edge", "UBL Local CA"].contains(&ctx.mtls.issuer.as_str()));
+            prop_assert!(["access-passkey", "webauthn"].contains(&ctx.auth.method.as_str()) && ctx.auth.rp_id == "app.ubl.agency");
+        }
+        if d.decision == "allow_admin_write" {
+            prop_assert!(ctx.user.groups.iter().any(|g| g == "ubl-ops"));
+            prop_assert!(ctx.req.as_ref().and_then(|r| r.path.as_deref()).is_some_and(|p| p.starts_with("/admin/")));
+        }
+    }
+
+    /// Cache nunca muda a decisão: uma sequência de contextos (repetidos, para
+    /// ter acertos) passa pelo mesmo cache pequeno e sai igual ao `decide`.
+    #[test]
+    fn cached_decisions_equal_uncached(chip in chip(), ctxs in vec(context(), 1..8)) {
+        let cache = DecisionCache::new(&chip, 4);
+        for ctx in ctxs.iter().chain(ctxs.iter()) {
+            let mut ctx = ctx.clone();
+            ctx.legacy_jwt = None;
+            prop_assert_eq!(cache.decide(&chip, &ctx).0, decide(&chip, &ctx));
+        }
+    }
+
+    #[test]
+    fn shipped_chips_cache_agrees_with_decide(ctxs in vec(context(), 1..8)) {
+        for (_, chip) in shipped() {
+            let cache = DecisionCache::new(&chip, 4);
+            for ctx in ctxs.iter().chain(ctxs.iter()) {
+                prop_assert_eq!(cache.decide(&chip, ctx).0, decide(&chip, ctx));
+            }
+        }
+    }
+
+    /// Só `P_Legacy_JWT` lê o relógio: fora dele, `now` não muda nada.
+    #[test]
+    fn clock_only_matters_for_legacy_jwt(chip in chip(), mut ctx in context(), now in any::<i64>()) {
+        ctx.legacy_jwt = None;
+        prop_assert_eq!(decide_at(&chip, &ctx, now).decision, decide_at(&chip, &ctx, NOW).decision);
+    }
+}
+
+#[test]
+fn cyclic_wiring_terminates_as_false() {
+    let yaml = r#"
+version: "tdln-chip/0.4"
+policies: []
+wiring:
+  - id: W_A
+    structure: { sequence: [P_Transport_Secure, W_B] }
+  - id: W_B
+    structure: { sequence: [W_A] }
+  - id: W_Self
+    structure: { sequence: [W_Self, W_Self] }
+outputs:
+  - loop: { trigger: W_A, action: "200" }
+  - self: { trigger: W_Self, action: "200" }
+  - not_loop: { trigger: NOT(W_B), action: "403" }
+"#;
+    let chip = SemanticChip::from_yaml(yaml).unwrap();
+    let mut ctx = RequestContext::default();
+    ctx.transport.tls_version = 1.3;
+    assert_eq!(decide_at(&chip, &ctx, NOW).decision, "not_loop");
+    assert_eq!(explain_at(&chip, &ctx, NOW).decision.decision, "not_loop");
+}
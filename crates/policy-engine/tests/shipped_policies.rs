@@ -0,0 +1,100 @@
+//! Regressão: decisões dos chips em `policies/` fixadas caso a caso.
+//! Mudou uma linha aqui? A mudança de comportamento precisa ser intencional
+//! (e o chip, re-assinado).
+#![cfg(not(target_arch = "wasm32"))]
+
+use policy_engine::{decide_at, resolve_imports, RequestContext, ReqCtx, RateCtx, WebhookCtx, LegacyJwtCtx, SemanticChip};
+
+const NOW: i64 = 1_700_000_000;
+
+/// Resolve os `imports:` a partir de `policies/`, como o policy-signer.
+fn chip(yaml: &str) -> SemanticChip {
+    let base = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../../policies"));
+    SemanticChip::from_yaml(&resolve_imports(yaml, base).unwrap().yaml).unwrap()
+}
+
+/// TLS 1.3 + mTLS da CA interna + passkey em app.ubl.agency
+fn zero_trust() -> RequestContext {
+    let mut ctx = RequestContext::default();
+    ctx.transport.tls_version = 1.3;
+    ctx.mtls.verified = true;
+    ctx.mtls.issuer = "UBL Local CA".into();
+    ctx.auth.method = "webauthn".into();
+    ctx.auth.rp_id = "app.ubl.agency".into();
+    ctx
+}
+
+fn with(mut ctx: RequestContext, f: impl FnOnce(&mut RequestContext)) -> RequestContext {
+    f(&mut ctx);
+    ctx
+}
+
+fn path(p: &str) -> Option<ReqCtx> {
+    Some(ReqCtx { path: Some(p.into()), method: None })
+}
+
+fn assert_decisions(chip: &SemanticChip, cases: &[(&str, RequestContext, &str)]) {
+    for (name, ctx, expected) in cases {
+        assert_eq!(decide_at(chip, ctx, NOW).decision, *expected, "case {}", name);
+    }
+}
+
+#[test]
+fn ubl_core_v1() {
+    let chip = chip(include_str!("../../../policies/ubl_core_v1.yaml"));
+    assert_decisions(&chip, &[
+        ("zero trust", zero_trust(), "allow_standard_access"),
+        ("edge issuer", with(zero_trust(), |c| c.mtls.issuer = "Cloudflare Edge".into()), "allow_standard_access"),
+        ("access passkey", with(zero_trust(), |c| c.auth.method = "access-passkey".into()), "allow_standard_access"),
+        ("ops on admin path", with(zero_trust(), |c| { c.user.groups = vec!["ubl-ops".into()]; c.req = path("/admin/deploy") }), "allow_admin_write"),
+        ("ops off admin path", with(zero_trust(), |c| { c.user.groups = vec!["ubl-ops".into()]; c.req = path("/deploy") }), "allow_standard_access"),
+        ("admin path without ops", with(zero_trust(), |c| c.req = path("/admin/deploy")), "allow_standard_access"),
+        ("/admin without slash", with(zero_trust(), |c| { c.user.groups = vec!["ubl-ops".into()]; c.req = path("/admin") }), "allow_standard_access"),
+        ("tls 1.2", with(zero_trust(), |c| c.transport.tls_version = 1.2), "deny_invalid_access"),
+        ("mtls unverified", with(zero_trust(), |c| c.mtls.verified = false), "deny_invalid_access"),
+        ("foreign issuer", with(zero_trust(), |c| c.mtls.issuer = "Let's Encrypt".into()), "deny_invalid_access"),
+        ("password", with(zero_trust(), |c| c.auth.method = "password".into()), "deny_invalid_access"),
+        ("other rp_id", with(zero_trust(), |c| c.auth.rp_id = "voulezvous.tv".into()), "deny_invalid_access"),
+        // W_Emergency_Override não é gatilho de nenhuma saída: panic_mode não libera
+        ("panic mode alone", with(RequestContext::default(), |c| c.system.panic_mode = true), "deny_invalid_access"),
+        ("empty context", RequestContext::default(), "deny_invalid_access"),
+    ]);
+}
+
+#[test]
+fn ubl_core_v3() {
+    let chip = chip(include_str!("../../../policies/ubl_core_v3.yaml"));
+    let rate_ok = |c: &mut RequestContext| c.rate = Some(RateCtx { ok: Some(true) });
+    assert_decisions(&chip, &[
+        ("zero trust", with(zero_trust(), rate_ok), "allow_standard_access"),
+        ("no rate info", zero_trust(), "allow_standard_access"),
+        ("ops on admin path", with(zero_trust(), |c| { rate_ok(c); c.user.groups = vec!["ubl-ops".into()]; c.req = path("/admin/x") }), "allow_admin_write"),
+        ("webhook", with(RequestContext::default(), |c| c.webhook = Some(WebhookCtx { verified: Some(true) })), "allow_webhook"),
+        ("over the limit", with(zero_trust(), |c| c.rate = Some(RateCtx { ok: Some(false) })), "deny_rate_limit"),
+        ("warmup", with(RequestContext::default(), |c| { c.transport.tls_version = 1.3; c.req = path("/warmup") }), "allow_public_warmup"),
+        ("warmup over tls 1.2", with(RequestContext::default(), |c| { c.transport.tls_version = 1.2; c.req = path("/warmup") }), "deny_invalid_access"),
+        ("tls 1.2", with(zero_trust(), |c| c.transport.tls_version = 1.2), "deny_invalid_access"),
+        ("unauthenticated", with(RequestContext::default(), |c| { c.transport.tls_version = 1.3; c.req = path("/core/x") }), "deny_invalid_access"),
+        ("legacy jwt is not wired", with(RequestContext::default(), |c| c.legacy_jwt = Some(LegacyJwtCtx { valid: Some(true), expires_at: Some(NOW + 60) })), "deny_invalid_access"),
+        ("empty context", RequestContext::default(), "deny_invalid_access"),
+    ]);
+}
+
+#[test]
+fn vvz_core_v1() {
+    let chip = chip(include_str!("../../../policies/vvz_core_v1.yaml"));
+    assert_decisions(&chip, &[
+        ("zero trust", zero_trust(), "allow_standard_access"),
+        // P_Is_MCP e P_Is_App_Origin não têm avaliador (sempre false):
+        // allow_mcp e allow_app_access são inalcançáveis
+        ("mcp", with(zero_trust(), |c| c.req = path("/mcp")), "allow_standard_access"),
+        ("admin path", with(zero_trust(), |c| c.req = path("/admin/x")), "allow_admin_access"),
+        ("over the limit", with(zero_trust(), |c| c.rate = Some(RateCtx { ok: Some(false) })), "deny_rate_limit"),
+        // rp_ids do `with:` no import de lib/zero_trust.yaml
+        ("vvz rp_id", with(zero_trust(), |c| c.auth.rp_id = "voulezvous.tv".into()), "allow_standard_access"),
+        ("other rp_id", with(zero_trust(), |c| c.auth.rp_id = "evil.example".into()), "deny_invalid_access"),
+        ("warmup", with(RequestContext::default(), |c| { c.transport.tls_version = 1.3; c.req = path("/warmup") }), "allow_public_warmup"),
+        ("tls 1.2", with(zero_trust(), |c| c.transport.tls_version = 1.2), "deny_invalid_access"),
+        ("empty context", RequestContext::default(), "deny_invalid_access"),
+    ]);
+}
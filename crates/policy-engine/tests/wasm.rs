@@ -0,0 +1,77 @@
+//! Bindings WASM sob Node:
+//! `CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner cargo test --target wasm32-unknown-unknown -p policy-engine`
+#![cfg(target_arch = "wasm32")]
+
+use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, EncodePublicKey};
+use ed25519_dalek::SigningKey;
+use policy_engine::wasm::{verify_pack, WasmPolicyEngine};
+use policy_pack::{PackEnvelope, FORMAT_V2, ALG_ED25519};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// ubl_core_v1 com `lib/zero_trust.yaml` embutida, como o policy-signer assina.
+fn chip() -> String {
+    let yaml = include_str!("../../../policies/ubl_core_v1.yaml");
+    policy_engine::resolve_imports_with(yaml, &|_| Ok(include_str!("../../../policies/lib/zero_trust.yaml").to_string())).unwrap().yaml
+}
+
+fn signed(yaml: &str, key: &SigningKey) -> String {
+    let envelope = PackEnvelope {
+        format: FORMAT_V2.into(),
+        alg: ALG_ED25519.into(),
+        kid: policy_pack::kid_for(&key.verifying_key()),
+        id: "ubl_access_chip_v1".into(),
+        version: "1".into(),
+        blake3: policy_pack::blake3_hex(yaml.as_bytes()),
+        created_at: 1_700_000_000,
+        not_before: None,
+        not_after: None,
+        chip_version: "tdln-chip/0.1".into(),
+    };
+    serde_json::to_string(&envelope.sign(key).unwrap()).unwrap()
+}
+
+fn pem(key: &SigningKey) -> JsValue {
+    JsValue::from_str(&key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap())
+}
+
+fn json(v: &JsValue) -> serde_json::Value {
+    serde_json::from_str(&js_sys::JSON::stringify(v).unwrap().as_string().unwrap()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn signed_pack_loads_and_decides_from_objects() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let pack = js_sys::JSON::parse(&signed(&chip(), &key)).unwrap();
+    let engine = WasmPolicyEngine::from_pack(&chip(), pack, pem(&key), None).unwrap();
+
+    let ctx = js_sys::JSON::parse(r#"{"transport":{"tls_version":1.3},"mtls":{"verified":true,"issuer":"UBL Local CA"},
+        "auth":{"method":"webauthn","rp_id":"app.ubl.agency"},"user":{"groups":[]},"system":{"panic_mode":false}}"#).unwrap();
+    assert_eq!(json(&engine.decide(ctx.clone()).unwrap())["decision"], "allow_standard_access");
+
+    let meta = json(&engine.metadata().unwrap());
+    assert_eq!(meta["version"], "tdln-chip/0.1");
+    assert_eq!(meta["outputs"][0], "allow_admin_write");
+    assert_eq!(meta["pack"]["signed_by"][0], policy_pack::kid_for(&key.verifying_key()));
+
+    let explained = json(&engine.explain(ctx.clone()).unwrap());
+    assert_eq!(explained["steps"][1]["fired"], true);
+    assert!(engine.explain_text(ctx).unwrap().contains("P_Role_Admin"));
+    assert!(json(&engine.diagnostics().unwrap()).as_array().unwrap().iter().any(|d| d["code"] == "wire_in_parallel"));
+}
+
+#[wasm_bindgen_test]
+fn rejects_tampered_yaml_and_untrusted_key() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let pack = JsValue::from_str(&signed(&chip(), &key));
+    assert!(verify_pack(&chip(), pack.clone(), pem(&key), None).is_ok());
+
+    let tampered = chip().replace("'ubl-ops'", "'everyone'");
+    assert!(WasmPolicyEngine::from_pack(&tampered, pack.clone(), pem(&key), None).is_err());
+
+    let other = SigningKey::from_bytes(&[8; 32]);
+    assert!(WasmPolicyEngine::from_pack(&chip(), pack.clone(), pem(&other), None).is_err());
+    // duas chaves, threshold 2: só uma assinou
+    let both = js_sys::Array::of2(&pem(&key), &pem(&other));
+    assert!(verify_pack(&chip(), pack, both.into(), Some(2)).is_err());
+}
@@ -0,0 +1,163 @@
+//! Leitura de arquivos de chave (privada ou pública, PEM ou DER) e as
+//! representações da chave pública: SPKI PEM, PEM em base64 (forma de
+//! `POLICY_PUBKEY_PEM_B64`), hex/base64 crus e JWK.
+//!
+//! kid: Ed25519 usa o mesmo de `policy_pack::kid_for`; ES256 segue o padrão
+//! (`es256:` + 16 hex do BLAKE3 do ponto SEC1 não comprimido).
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePublicKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyKind {
+    Private,
+    Public,
+}
+
+#[derive(Debug, Clone)]
+pub enum PublicKey {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    Es256(p256::PublicKey),
+}
+
+impl PublicKey {
+    pub fn alg(&self) -> &'static str {
+        match self {
+            Self::Ed25519(_) => "EdDSA",
+            Self::Es256(_) => "ES256",
+        }
+    }
+
+    /// Ed25519: 32 bytes; P-256: ponto SEC1 não comprimido (65 bytes)
+    pub fn raw(&self) -> Vec<u8> {
+        match self {
+            Self::Ed25519(k) => k.to_bytes().to_vec(),
+            Self::Es256(k) => k.to_encoded_point(false).as_bytes().to_vec(),
+        }
+    }
+
+    pub fn kid(&self) -> String {
+        match self {
+            Self::Ed25519(k) => policy_pack::kid_for(k),
+            Self::Es256(_) => format!("es256:{}", &policy_pack::blake3_hex(&self.raw())[..16]),
+        }
+    }
+
+    pub fn to_pem(&self) -> Result<String> {
+        let pem = match self {
+            Self::Ed25519(k) => k.to_public_key_pem(Default::default()),
+            Self::Es256(k) => k.to_public_key_pem(Default::default()),
+        };
+        pem.map_err(|e| anyhow::anyhow!("encode public key PEM: {}", e))
+    }
+
+    /// Mesma forma de POLICY_PUBKEY_PEM_B64
+    pub fn to_pem_b64(&self) -> Result<String> {
+        Ok(general_purpose::STANDARD.encode(self.to_pem()?))
+    }
+
+    /// Só os membros obrigatórios (base do thumbprint RFC 7638)
+    fn jwk_members(&self) -> Value {
+        let b64url = |b: &[u8]| general_purpose::URL_SAFE_NO_PAD.encode(b);
+        match self {
+            Self::Ed25519(k) => json!({ "kty": "OKP", "crv": "Ed25519", "x": b64url(k.as_bytes()) }),
+            Self::Es256(_) => {
+                let raw = self.raw();
+                json!({ "kty": "EC", "crv": "P-256", "x": b64url(&raw[1..33]), "y": b64url(&raw[33..65]) })
+            }
+        }
+    }
+
+    /// JWK público com alg/use/kid (mesmo formato do JWKS do core-api)
+    pub fn jwk(&self, kid: &str) -> Value {
+        let mut jwk = self.jwk_members();
+        let obj = jwk.as_object_mut().unwrap();
+        obj.insert("alg".into(), self.alg().into());
+        obj.insert("use".into(), "sig".into());
+        obj.insert("kid".into(), kid.into());
+        jwk
+    }
+
+    /// RFC 7638: SHA-256 do JWK canônico, base64url
+    pub fn jwk_thumbprint(&self) -> String {
+        let digest = Sha256::digest(policy_pack::canonical_json(&self.jwk_members()));
+        general_purpose::URL_SAFE_NO_PAD.encode(digest)
+    }
+}
+
+/// Aceita PKCS#8 (privada) ou SPKI (pública), PEM ou DER, Ed25519 ou P-256.
+pub fn load(path: &Path) -> Result<(KeyKind, PublicKey)> {
+    let bytes = std::fs::read(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    let pem = std::str::from_utf8(&bytes).ok().filter(|s| s.contains("-----BEGIN"));
+    macro_rules! try_decode {
+        ($decode_pem:expr, $decode_der:expr) => {
+            match pem {
+                Some(p) => $decode_pem(p).ok(),
+                None => $decode_der(&bytes).ok(),
+            }
+        };
+    }
+    if let Some(k) = try_decode!(ed25519_dalek::SigningKey::from_pkcs8_pem, ed25519_dalek::SigningKey::from_pkcs8_der) {
+        return Ok((KeyKind::Private, PublicKey::Ed25519(k.verifying_key())));
+    }
+    if let Some(k) = try_decode!(ed25519_dalek::VerifyingKey::from_public_key_pem, ed25519_dalek::VerifyingKey::from_public_key_der) {
+        return Ok((KeyKind::Public, PublicKey::Ed25519(k)));
+    }
+    if let Some(k) = try_decode!(p256::SecretKey::from_pkcs8_pem, p256::SecretKey::from_pkcs8_der) {
+        return Ok((KeyKind::Private, PublicKey::Es256(k.public_key())));
+    }
+    if let Some(k) = try_decode!(p256::PublicKey::from_public_key_pem, p256::PublicKey::from_public_key_der) {
+        return Ok((KeyKind::Public, PublicKey::Es256(k)));
+    }
+    if pem.is_some_and(|p| p.contains("ENCRYPTED PRIVATE KEY")) {
+        anyhow::bail!("{}: encrypted key; inspect its public key file instead", path.display());
+    }
+    anyhow::bail!("{}: not an Ed25519 or P-256 key (PKCS#8/SPKI, PEM or DER)", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+
+    #[test]
+    fn loads_both_algorithms_private_and_public() {
+        let dir = std::env::temp_dir().join(format!("policy-keygen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let ed = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        std::fs::write(dir.join("ed.der"), ed.to_pkcs8_der().unwrap().as_bytes()).unwrap();
+        std::fs::write(dir.join("ed.pub.pem"), ed.verifying_key().to_public_key_pem(Default::default()).unwrap()).unwrap();
+        let (kind, key) = load(&dir.join("ed.der")).unwrap();
+        assert_eq!(kind, KeyKind::Private);
+        assert_eq!(key.kid(), policy_pack::kid_for(&ed.verifying_key()));
+        let (kind, public) = load(&dir.join("ed.pub.pem")).unwrap();
+        assert_eq!(kind, KeyKind::Public);
+        assert_eq!(public.raw(), key.raw());
+        assert_eq!(key.jwk(&key.kid())["x"], general_purpose::URL_SAFE_NO_PAD.encode(ed.verifying_key().as_bytes()));
+
+        // RFC 8037, A.3
+        let x = general_purpose::URL_SAFE_NO_PAD.decode("11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo").unwrap();
+        let rfc = PublicKey::Ed25519(ed25519_dalek::VerifyingKey::from_bytes(&x.try_into().unwrap()).unwrap());
+        assert_eq!(rfc.jwk_thumbprint(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+
+        let ec = p256::SecretKey::from_slice(&[2u8; 32]).unwrap();
+        std::fs::write(dir.join("ec.pem"), ec.to_pkcs8_pem(Default::default()).unwrap().as_bytes()).unwrap();
+        let (kind, key) = load(&dir.join("ec.pem")).unwrap();
+        assert_eq!(kind, KeyKind::Private);
+        assert_eq!(key.alg(), "ES256");
+        assert!(key.kid().starts_with("es256:"));
+        let jwk = key.jwk("k");
+        assert_eq!((jwk["kty"].as_str(), jwk["crv"].as_str()), (Some("EC"), Some("P-256")));
+        assert_eq!(key.jwk_thumbprint().len(), 43);
+
+        std::fs::write(dir.join("junk"), b"not a key").unwrap();
+        assert!(load(&dir.join("junk")).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,49 @@
+//! Bundle de arquivo único: pack v2 assinado + YAML do chip embutido.
+//! Um só artefato para `rename` atômico no disco e um só valor na KV do Worker.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{AnyPack, PolicyPack, TrustSet, VerifiedPack};
+
+pub const BUNDLE_FORMAT: &str = "ubl-policy-bundle/1";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub pack: PolicyPack,
+    /// YAML do chip, byte a byte o que o `pack.blake3` cobre
+    pub chip: String,
+    /// Informativo (arquivo de origem, ferramenta); fora da assinatura
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+}
+
+impl Bundle {
+    pub fn new(pack: PolicyPack, chip: String) -> Self {
+        Self { format: BUNDLE_FORMAT.into(), pack, chip, meta: BTreeMap::new() }
+    }
+
+    pub fn from_json(raw: &str) -> anyhow::Result<Self> {
+        let bundle: Self = serde_json::from_str(raw)?;
+        if bundle.format != BUNDLE_FORMAT {
+            anyhow::bail!("unsupported bundle format: {}", bundle.format);
+        }
+        Ok(bundle)
+    }
+
+    /// O JSON é um bundle (e não um pack.json)?
+    pub fn is_bundle(raw: &str) -> bool {
+        serde_json::from_str::<serde_json::Value>(raw)
+            .ok()
+            .and_then(|v| v.get("format").and_then(|f| f.as_str()).map(|f| f == BUNDLE_FORMAT))
+            .unwrap_or(false)
+    }
+
+    /// Assinaturas + janela do pack e blake3 do chip embutido.
+    pub fn verify(&self, trust: &TrustSet, now: u64) -> anyhow::Result<VerifiedPack> {
+        let verified = AnyPack::V2(self.pack.clone()).verify(trust, now)?;
+        verified.check_chip(self.chip.as_bytes())?;
+        Ok(verified)
+    }
+}
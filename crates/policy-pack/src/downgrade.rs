@@ -0,0 +1,79 @@
+//! Autorização assinada para instalar um pack mais antigo que o já aceito
+//! (anti-rollback). Vale para exatamente um (id, version, blake3) e expira.
+
+use serde::{Deserialize, Serialize};
+
+use crate::signer::sign_b64;
+use crate::{canonical_json, Cosignature, Signer, TrustSet, ALG_ED25519};
+
+pub const DOWNGRADE_FORMAT: &str = "ubl-policy-downgrade/1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DowngradeEnvelope {
+    pub format: String,
+    pub id: String,
+    pub version: String,
+    pub blake3: String,
+    pub created_at: u64,
+    pub not_after: u64,
+}
+
+/// `pack.downgrade.json`: envelope + uma assinatura por aprovador
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DowngradeAuth {
+    #[serde(flatten)]
+    pub envelope: DowngradeEnvelope,
+    pub signatures: Vec<Cosignature>,
+}
+
+impl DowngradeAuth {
+    pub fn new(id: String, version: String, blake3: String, created_at: u64, not_after: u64) -> Self {
+        Self {
+            envelope: DowngradeEnvelope { format: DOWNGRADE_FORMAT.into(), id, version, blake3, created_at, not_after },
+            signatures: vec![],
+        }
+    }
+
+    pub fn from_json(raw: &str) -> anyhow::Result<Self> {
+        let auth: Self = serde_json::from_str(raw)?;
+        if auth.envelope.format != DOWNGRADE_FORMAT {
+            anyhow::bail!("unsupported downgrade format: {}", auth.envelope.format);
+        }
+        Ok(auth)
+    }
+
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        canonical_json(&serde_json::to_value(&self.envelope).expect("envelope serializes"))
+    }
+
+    pub fn sign(&mut self, key: &dyn Signer, kid: String) -> anyhow::Result<()> {
+        if self.signatures.iter().any(|s| s.kid == kid) {
+            anyhow::bail!("downgrade already signed by {}", kid);
+        }
+        let signature = sign_b64(key, &self.signed_bytes())?;
+        self.signatures.push(Cosignature { kid, alg: ALG_ED25519.into(), signature });
+        Ok(())
+    }
+
+    /// Confere se autoriza exatamente este pack, dentro do prazo, com o
+    /// threshold do trust set (janela das chaves em `now`, não no
+    /// `created_at`). Retorna os kids que aprovaram.
+    pub fn authorizes(&self, trust: &TrustSet, id: &str, version: &str, blake3: &str, now: u64) -> anyhow::Result<Vec<String>> {
+        let e = &self.envelope;
+        if e.id != id || e.version != version || e.blake3 != blake3 {
+            anyhow::bail!("downgrade authorization is for {}@{} ({}), not {}@{}", e.id, e.version, e.blake3, id, version);
+        }
+        if now > e.not_after {
+            anyhow::bail!("downgrade authorization expired at {}", e.not_after);
+        }
+        if e.created_at > now {
+            anyhow::bail!("downgrade authorization created_at {} is in the future", e.created_at);
+        }
+        let msg = self.signed_bytes();
+        let checks: Vec<_> = self.signatures.iter()
+            .filter(|s| s.alg == ALG_ED25519)
+            .map(|s| trust.check(Some(&s.kid), &msg, &s.signature, now))
+            .collect();
+        trust.approvals(&checks).map_err(|err| anyhow::anyhow!("downgrade authorization: {}", err))
+    }
+}
@@ -0,0 +1,524 @@
+//! Policy pack — envelope assinado (Ed25519 + BLAKE3) compartilhado por
+//! policy-signer, policy-proxy e Worker.
+//!
+//! v2 (`ubl-policy-pack/2`): a assinatura cobre o JSON canônico (chaves
+//! ordenadas, sem espaços) de todos os campos do envelope — formato, alg, kid,
+//! id, version, blake3, created_at, not_before, not_after e chip_version.
+//! v1 (`id=..\nversion=..\nblake3=..\n`) continua aceito só para verificação.
+//!
+//! Coassinaturas (`cosignatures`) assinam os mesmos bytes canônicos; a
+//! verificação conta chaves distintas de um [`TrustSet`] até o threshold.
+//! [`Bundle`] leva o pack e o YAML do chip num único JSON.
+
+mod bundle;
+mod downgrade;
+mod signer;
+mod trust;
+
+pub use bundle::{Bundle, BUNDLE_FORMAT};
+pub use downgrade::{DowngradeAuth, DowngradeEnvelope, DOWNGRADE_FORMAT};
+pub use signer::Signer;
+pub use trust::{KeyEntry, RotationBundle, SigCheck, SigStatus, TrustSet, TrustStore, TrustedKey, ROTATION_FORMAT};
+
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::pkcs8::DecodePublicKey;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+use signer::sign_b64;
+use serde::{Deserialize, Serialize};
+
+pub const FORMAT_V2: &str = "ubl-policy-pack/2";
+pub const ALG_ED25519: &str = "Ed25519";
+
+/// Campos cobertos pela assinatura v2
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PackEnvelope {
+    pub format: String,
+    pub alg: String,
+    pub kid: String,
+    pub id: String,
+    pub version: String,
+    /// BLAKE3 (hex) do YAML do chip
+    pub blake3: String,
+    pub created_at: u64,
+    pub not_before: Option<u64>,
+    pub not_after: Option<u64>,
+    /// `version:` declarado no chip (ex.: "tdln-chip/0.3")
+    pub chip_version: String,
+}
+
+/// pack.json v2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyPack {
+    #[serde(flatten)]
+    pub envelope: PackEnvelope,
+    /// Ed25519 (base64) sobre `envelope.canonical_bytes()`
+    pub signature: String,
+    /// Aprovações adicionais (M-of-N) sobre os mesmos bytes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cosignatures: Vec<Cosignature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cosignature {
+    pub kid: String,
+    pub alg: String,
+    pub signature: String,
+}
+
+/// pack.json legado — somente verificação
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyPackV1 {
+    pub id: String,
+    pub version: String,
+    pub blake3: String,
+    pub signature: String,
+    #[serde(default)]
+    pub created_at: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AnyPack {
+    V1(PolicyPackV1),
+    V2(PolicyPack),
+}
+
+/// Resultado de uma verificação bem-sucedida
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedPack {
+    pub format: u8,
+    pub id: String,
+    pub version: String,
+    pub blake3: String,
+    pub kid: Option<String>,
+    pub chip_version: Option<String>,
+    pub created_at: Option<u64>,
+    /// kids do trust set cujas assinaturas conferem
+    pub signed_by: Vec<String>,
+}
+
+impl PackEnvelope {
+    /// Envelope v2 para o YAML dado; `created_at` = agora.
+    pub fn for_chip(id: String, version: String, yaml: &[u8], chip_version: String, kid: String) -> Self {
+        Self {
+            format: FORMAT_V2.into(),
+            alg: ALG_ED25519.into(),
+            kid,
+            id,
+            version,
+            blake3: blake3_hex(yaml),
+            created_at: now_epoch(),
+            not_before: None,
+            not_after: None,
+            chip_version,
+        }
+    }
+
+    /// JSON canônico: objeto com chaves em ordem lexicográfica, sem espaços.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        canonical_json(&serde_json::to_value(self).expect("envelope serializes"))
+    }
+
+    pub fn sign(self, key: &dyn Signer) -> anyhow::Result<PolicyPack> {
+        let signature = sign_b64(key, &self.canonical_bytes())?;
+        Ok(PolicyPack { envelope: self, signature, cosignatures: vec![] })
+    }
+}
+
+impl PolicyPack {
+    /// Acrescenta uma assinatura; o mesmo kid não assina duas vezes.
+    pub fn cosign(&mut self, key: &dyn Signer, kid: String) -> anyhow::Result<()> {
+        if self.signatures().any(|(k, _)| k == kid) {
+            anyhow::bail!("pack already signed by {}", kid);
+        }
+        let signature = sign_b64(key, &self.envelope.canonical_bytes())?;
+        self.cosignatures.push(Cosignature { kid, alg: ALG_ED25519.into(), signature });
+        Ok(())
+    }
+
+    /// (kid, assinatura) — a principal primeiro, depois as coassinaturas Ed25519.
+    pub fn signatures(&self) -> impl Iterator<Item = (&str, &str)> {
+        std::iter::once((self.envelope.kid.as_str(), self.signature.as_str())).chain(
+            self.cosignatures.iter()
+                .filter(|c| c.alg == ALG_ED25519)
+                .map(|c| (c.kid.as_str(), c.signature.as_str())),
+        )
+    }
+}
+
+impl PolicyPackV1 {
+    pub fn message(&self) -> String {
+        format!("id={}\nversion={}\nblake3={}\n", self.id, self.version, self.blake3)
+    }
+}
+
+impl AnyPack {
+    /// `format` presente → v2; ausente → v1.
+    pub fn from_json(raw: &str) -> anyhow::Result<Self> {
+        let v: serde_json::Value = serde_json::from_str(raw)?;
+        match v.get("format").and_then(|f| f.as_str()) {
+            Some(FORMAT_V2) => Ok(Self::V2(serde_json::from_value(v)?)),
+            Some(other) => anyhow::bail!("unsupported pack format: {}", other),
+            None => Ok(Self::V1(serde_json::from_value(v)?)),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            Self::V1(p) => &p.id,
+            Self::V2(p) => &p.envelope.id,
+        }
+    }
+
+    /// Estado de cada assinatura do pack frente ao trust set. A validade das
+    /// chaves é conferida em `now`, também no v2 (`created_at` não conta).
+    pub fn check_signatures(&self, trust: &TrustSet, now: u64) -> Vec<SigCheck> {
+        match self {
+            Self::V1(p) => vec![trust.check(None, p.message().as_bytes(), &p.signature, now)],
+            Self::V2(p) => {
+                let msg = p.envelope.canonical_bytes();
+                p.signatures().map(|(kid, sig)| trust.check(Some(kid), &msg, sig, now)).collect()
+            }
+        }
+    }
+
+    /// Confere assinaturas (threshold do trust set) e janela de validade (`now` em segundos Unix).
+    pub fn verify(&self, trust: &TrustSet, now: u64) -> anyhow::Result<VerifiedPack> {
+        if let Self::V2(p) = self {
+            if p.envelope.alg != ALG_ED25519 {
+                anyhow::bail!("unsupported pack alg: {}", p.envelope.alg);
+            }
+            if p.envelope.created_at > now {
+                anyhow::bail!("pack created_at {} is in the future", p.envelope.created_at);
+            }
+        }
+        let signed_by = trust.approvals(&self.check_signatures(trust, now))?;
+        match self {
+            Self::V1(p) => Ok(VerifiedPack {
+                format: 1,
+                id: p.id.clone(),
+                version: p.version.clone(),
+                blake3: p.blake3.clone(),
+                kid: None,
+                chip_version: None,
+                created_at: p.created_at,
+                signed_by,
+            }),
+            Self::V2(p) => {
+                let e = &p.envelope;
+                if let Some(nbf) = e.not_before {
+                    if now < nbf {
+                        anyhow::bail!("pack not valid before {}", nbf);
+                    }
+                }
+                if let Some(naf) = e.not_after {
+                    if now > naf {
+                        anyhow::bail!("pack expired at {}", naf);
+                    }
+                }
+                Ok(VerifiedPack {
+                    format: 2,
+                    id: e.id.clone(),
+                    version: e.version.clone(),
+                    blake3: e.blake3.clone(),
+                    kid: Some(e.kid.clone()),
+                    chip_version: Some(e.chip_version.clone()),
+                    created_at: Some(e.created_at),
+                    signed_by,
+                })
+            }
+        }
+    }
+}
+
+impl VerifiedPack {
+    /// O YAML entregue é o que foi assinado?
+    pub fn check_chip(&self, yaml: &[u8]) -> anyhow::Result<()> {
+        if blake3_hex(yaml) != self.blake3 {
+            anyhow::bail!("policy YAML does not match pack blake3");
+        }
+        Ok(())
+    }
+
+    /// O `version:` do chip bate com o assinado (v2)?
+    pub fn check_chip_version(&self, chip_version: &str) -> anyhow::Result<()> {
+        match &self.chip_version {
+            Some(v) if v != chip_version => anyhow::bail!("chip version {} does not match pack chip_version {}", chip_version, v),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn verify_sig(vk: &VerifyingKey, msg: &[u8], sig_b64: &str) -> bool {
+    let Ok(sig_bytes) = general_purpose::STANDARD.decode(sig_b64) else { return false };
+    let Ok(sig_array) = <[u8; 64]>::try_from(sig_bytes) else { return false };
+    vk.verify(msg, &Signature::from_bytes(&sig_array)).is_ok()
+}
+
+/// JSON canônico: chaves de objetos em ordem lexicográfica (recursivo), sem espaços.
+pub fn canonical_json(v: &serde_json::Value) -> Vec<u8> {
+    fn write(v: &serde_json::Value, out: &mut Vec<u8>) {
+        match v {
+            serde_json::Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                out.push(b'{');
+                for (i, k) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(b',');
+                    }
+                    out.extend(serde_json::to_vec(k).expect("string serializes"));
+                    out.push(b':');
+                    write(&map[k], out);
+                }
+                out.push(b'}');
+            }
+            serde_json::Value::Array(items) => {
+                out.push(b'[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(b',');
+                    }
+                    write(item, out);
+                }
+                out.push(b']');
+            }
+            other => out.extend(serde_json::to_vec(other).expect("scalar serializes")),
+        }
+    }
+    let mut out = Vec::new();
+    write(v, &mut out);
+    out
+}
+
+/// Ordem de versões de pack: segmentos separados por `.`, numéricos comparados
+/// como números (`3.10` > `3.9`), os demais como texto; numérico < texto.
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    let mut xs = a.split('.');
+    let mut ys = b.split('.');
+    loop {
+        let ord = match (xs.next(), ys.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+pub fn blake3_hex(bytes: &[u8]) -> String {
+    hex::encode(blake3::hash(bytes).as_bytes())
+}
+
+/// kid estável da chave pública: `ed25519:` + 16 hex do BLAKE3 da chave crua.
+pub fn kid_for(vk: &VerifyingKey) -> String {
+    format!("ed25519:{}", &blake3_hex(vk.as_bytes())[..16])
+}
+
+/// Chave pública Ed25519 em SPKI PEM.
+pub fn public_key_from_pem(pem: &str) -> anyhow::Result<VerifyingKey> {
+    VerifyingKey::from_public_key_pem(pem.trim()).map_err(|e| anyhow::anyhow!("invalid public key PEM: {}", e))
+}
+
+/// `POLICY_PUBKEY_PEM_B64`: base64 de um SPKI PEM.
+pub fn public_key_from_pem_b64(pem_b64: &str) -> anyhow::Result<VerifyingKey> {
+    let pem = String::from_utf8(general_purpose::STANDARD.decode(pem_b64.trim())?)
+        .map_err(|e| anyhow::anyhow!("invalid PEM base64: {}", e))?;
+    public_key_from_pem(&pem)
+}
+
+pub fn now_epoch() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer as _, SigningKey};
+
+    const YAML: &[u8] = b"version: \"tdln-chip/0.3\"\npolicies: []\nwiring: []\noutputs: []\n";
+
+    fn key() -> SigningKey {
+        SigningKey::from_bytes(&[9u8; 32])
+    }
+
+    #[test]
+    fn v2_roundtrip_covers_every_field() {
+        let sk = key();
+        let vk = sk.verifying_key();
+        let mut env = PackEnvelope::for_chip("ubl_core_v3".into(), "3.0.0".into(), YAML, "tdln-chip/0.3".into(), kid_for(&vk));
+        env.not_after = Some(env.created_at + 3600);
+        let pack = env.sign(&sk).unwrap();
+        let raw = serde_json::to_string_pretty(&pack).unwrap();
+
+        let trust = TrustSet::single(vk);
+        let parsed = AnyPack::from_json(&raw).unwrap();
+        let ok = parsed.verify(&trust, pack.envelope.created_at).unwrap();
+        assert_eq!(ok.format, 2);
+        ok.check_chip(YAML).unwrap();
+        ok.check_chip_version("tdln-chip/0.3").unwrap();
+        assert!(ok.check_chip_version("tdln-chip/0.1").is_err());
+        assert!(parsed.verify(&trust, pack.envelope.created_at + 7200).is_err());
+
+        // created_at agora é coberto
+        let mut tampered: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        tampered["created_at"] = serde_json::json!(1);
+        assert!(AnyPack::from_json(&tampered.to_string()).unwrap().verify(&trust, 2).is_err());
+    }
+
+    #[test]
+    fn v1_still_verifies() {
+        let sk = key();
+        let msg = format!("id=ubl\nversion=1\nblake3={}\n", blake3_hex(YAML));
+        let sig = general_purpose::STANDARD.encode(sk.sign(msg.as_bytes()).to_bytes());
+        let raw = serde_json::json!({"id": "ubl", "version": "1", "blake3": blake3_hex(YAML), "signature": sig, "created_at": 1}).to_string();
+        let ok = AnyPack::from_json(&raw).unwrap().verify(&TrustSet::single(sk.verifying_key()), 0).unwrap();
+        assert_eq!(ok.format, 1);
+        ok.check_chip(YAML).unwrap();
+    }
+
+    #[test]
+    fn threshold_counts_distinct_trusted_keys() {
+        let keys: Vec<SigningKey> = (1..=3u8).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+        let outsider = SigningKey::from_bytes(&[7u8; 32]);
+        let trust = TrustSet::new(keys.iter().map(|k| TrustedKey::new(k.verifying_key())).collect(), 2).unwrap();
+
+        let env = PackEnvelope::for_chip("ubl".into(), "1".into(), YAML, "tdln-chip/0.3".into(), kid_for(&keys[0].verifying_key()));
+        let mut pack = env.sign(&keys[0]).unwrap();
+        let one_of_three = AnyPack::V2(pack.clone()).verify(&trust, pack.envelope.created_at);
+        assert!(one_of_three.unwrap_err().to_string().contains("1 of 2"));
+
+        pack.cosign(&outsider, kid_for(&outsider.verifying_key())).unwrap();
+        assert!(pack.cosign(&keys[0], kid_for(&keys[0].verifying_key())).is_err());
+        assert!(AnyPack::V2(pack.clone()).verify(&trust, pack.envelope.created_at).is_err());
+
+        pack.cosign(&keys[2], kid_for(&keys[2].verifying_key())).unwrap();
+        let raw = serde_json::to_string(&pack).unwrap();
+        let parsed = AnyPack::from_json(&raw).unwrap();
+        let ok = parsed.verify(&trust, pack.envelope.created_at).unwrap();
+        assert_eq!(ok.signed_by, vec![kid_for(&keys[0].verifying_key()), kid_for(&keys[2].verifying_key())]);
+        let statuses: Vec<_> = parsed.check_signatures(&trust, 0).into_iter().map(|c| c.status).collect();
+        assert!(matches!(statuses[1], SigStatus::Untrusted));
+    }
+
+    #[test]
+    fn bundle_embeds_the_signed_chip() {
+        let sk = key();
+        let trust = TrustSet::single(sk.verifying_key());
+        let env = PackEnvelope::for_chip("ubl".into(), "1".into(), YAML, "tdln-chip/0.3".into(), kid_for(&sk.verifying_key()));
+        let mut bundle = Bundle::new(env.sign(&sk).unwrap(), String::from_utf8(YAML.to_vec()).unwrap());
+        bundle.meta.insert("source".into(), "ubl.yaml".into());
+        let raw = serde_json::to_string_pretty(&bundle).unwrap();
+        assert!(Bundle::is_bundle(&raw));
+        assert!(!Bundle::is_bundle(&serde_json::to_string(&bundle.pack).unwrap()));
+
+        let parsed = Bundle::from_json(&raw).unwrap();
+        assert_eq!(parsed.verify(&trust, bundle.pack.envelope.created_at).unwrap().id, "ubl");
+        let mut tampered = parsed.clone();
+        tampered.chip.push_str("# extra\n");
+        assert!(tampered.verify(&trust, bundle.pack.envelope.created_at).is_err());
+    }
+
+    #[test]
+    fn version_ordering_and_downgrade_authorization() {
+        use std::cmp::Ordering::*;
+        assert_eq!(compare_versions("3.10.0", "3.9.1"), Greater);
+        assert_eq!(compare_versions("3.0", "3.0.0"), Less);
+        assert_eq!(compare_versions("2", "2"), Equal);
+
+        let keys: Vec<SigningKey> = (1..=2u8).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+        let trust = TrustSet::new(keys.iter().map(|k| TrustedKey::new(k.verifying_key())).collect(), 2).unwrap();
+        let mut auth = DowngradeAuth::new("ubl".into(), "2.0.0".into(), "abc".into(), 100, 200);
+        auth.sign(&keys[0], kid_for(&keys[0].verifying_key())).unwrap();
+        assert!(auth.authorizes(&trust, "ubl", "2.0.0", "abc", 150).is_err());
+        auth.sign(&keys[1], kid_for(&keys[1].verifying_key())).unwrap();
+        let auth = DowngradeAuth::from_json(&serde_json::to_string(&auth).unwrap()).unwrap();
+        assert_eq!(auth.authorizes(&trust, "ubl", "2.0.0", "abc", 150).unwrap().len(), 2);
+        assert!(auth.authorizes(&trust, "ubl", "1.0.0", "abc", 150).is_err());
+        assert!(auth.authorizes(&trust, "ubl", "2.0.0", "abc", 201).is_err());
+        assert!(auth.authorizes(&trust, "ubl", "2.0.0", "abc", 99).unwrap_err().to_string().contains("in the future"));
+
+        // chave aposentada em 120 não aprova depois disso, mesmo com created_at 100
+        let retiring = TrustSet::new(keys.iter().map(|k| TrustedKey { not_after: Some(120), ..TrustedKey::new(k.verifying_key()) }).collect(), 2).unwrap();
+        assert!(auth.authorizes(&retiring, "ubl", "2.0.0", "abc", 110).is_ok());
+        assert!(auth.authorizes(&retiring, "ubl", "2.0.0", "abc", 150).unwrap_err().to_string().contains("outside its validity window"));
+    }
+
+    #[test]
+    fn trust_store_rotation_and_revocation() {
+        let old = SigningKey::from_bytes(&[1u8; 32]);
+        let new = SigningKey::from_bytes(&[2u8; 32]);
+        let mut store = TrustStore::default();
+        store.add(KeyEntry::for_key(&old.verifying_key(), None, None).unwrap()).unwrap();
+
+        let bundle = RotationBundle::sign(&old, KeyEntry::for_key(&new.verifying_key(), Some(100), None).unwrap()).unwrap();
+        let mut forged = bundle.clone();
+        forged.new_key.not_before = Some(0);
+        assert!(store.clone().apply_rotation(&forged, 200).is_err());
+        store.apply_rotation(&bundle, 200).unwrap();
+
+        let mut env = PackEnvelope::for_chip("ubl".into(), "1".into(), YAML, "tdln-chip/0.3".into(), kid_for(&new.verifying_key()));
+        env.created_at = 50;
+        let pack = AnyPack::V2(env.clone().sign(&new).unwrap());
+        // antes da janela da chave nova
+        assert!(pack.verify(&store.trust_set(None).unwrap(), 50).unwrap_err().to_string().contains("outside its validity window"));
+        assert_eq!(pack.verify(&store.trust_set(None).unwrap(), 300).unwrap().signed_by, vec![kid_for(&new.verifying_key())]);
+        env.created_at = 400;
+        let future = AnyPack::V2(env.sign(&new).unwrap());
+        assert!(future.verify(&store.trust_set(None).unwrap(), 300).unwrap_err().to_string().contains("in the future"));
+
+        store.revoke(&kid_for(&new.verifying_key())).unwrap();
+        assert!(pack.verify(&store.trust_set(None).unwrap(), 300).unwrap_err().to_string().contains("revoked"));
+    }
+
+    /// A mesma chave sob dois kids não vale como duas aprovações.
+    #[test]
+    fn one_key_under_two_kids_does_not_meet_threshold() {
+        let sk = key();
+        let vk = sk.verifying_key();
+        let alias = TrustedKey { kid: "ops-2".into(), ..TrustedKey::new(vk) };
+        let other = TrustedKey::new(SigningKey::from_bytes(&[3u8; 32]).verifying_key());
+        assert!(TrustSet::new(vec![TrustedKey::new(vk), alias, other.clone()], 2).unwrap_err().to_string().contains("already in trust set"));
+
+        let entry = KeyEntry::for_key(&vk, None, None).unwrap();
+        let aliased = KeyEntry { kid: "ops-2".into(), ..entry.clone() };
+        let mut store = TrustStore { threshold: Some(2), keys: vec![entry.clone(), aliased.clone()] };
+        assert!(store.trust_set(None).unwrap_err().to_string().contains("does not match its key"));
+        store.keys.pop();
+        assert!(store.add(aliased.clone()).is_err());
+        let rotation = RotationBundle::sign(&sk, aliased).unwrap();
+        assert!(store.apply_rotation(&rotation, 0).unwrap_err().to_string().contains("does not match its key"));
+
+        // coassinatura da mesma chave com outro kid conta uma vez só
+        let trust = TrustSet::new(vec![TrustedKey::new(vk), other], 2).unwrap();
+        let env = PackEnvelope::for_chip("ubl".into(), "1".into(), YAML, "tdln-chip/0.3".into(), kid_for(&vk));
+        let mut pack = env.sign(&sk).unwrap();
+        pack.cosign(&sk, "ops-2".into()).unwrap();
+        let err = AnyPack::V2(pack.clone()).verify(&trust, pack.envelope.created_at).unwrap_err();
+        assert!(err.to_string().contains("1 of 2"), "{}", err);
+    }
+
+    /// Chave aposentada não assina pack novo só porque o `created_at` foi retroagido.
+    #[test]
+    fn backdated_pack_from_retired_key_is_rejected() {
+        let sk = key();
+        let trust = TrustSet::new(vec![TrustedKey { not_after: Some(200), ..TrustedKey::new(sk.verifying_key()) }], 1).unwrap();
+        let mut env = PackEnvelope::for_chip("ubl".into(), "1".into(), YAML, "tdln-chip/0.3".into(), kid_for(&sk.verifying_key()));
+        env.created_at = 150;
+        let pack = AnyPack::V2(env.sign(&sk).unwrap());
+        assert!(pack.verify(&trust, 180).is_ok());
+        assert!(pack.verify(&trust, 300).unwrap_err().to_string().contains("outside its validity window"));
+    }
+}
@@ -0,0 +1,38 @@
+//! Abstração de quem assina: chave em memória, ssh-agent, comando externo…
+//! Os backends concretos ficam no `policy-signer`; aqui só o contrato.
+
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signature, SigningKey, Verifier, VerifyingKey};
+
+use crate::kid_for;
+
+/// Produz assinaturas Ed25519 sobre bytes arbitrários.
+pub trait Signer {
+    /// Chave pública correspondente (kid e checagem pós-assinatura)
+    fn public_key(&self) -> VerifyingKey;
+
+    fn sign_message(&self, msg: &[u8]) -> anyhow::Result<Signature>;
+
+    fn kid(&self) -> String {
+        kid_for(&self.public_key())
+    }
+}
+
+impl Signer for SigningKey {
+    fn public_key(&self) -> VerifyingKey {
+        self.verifying_key()
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> anyhow::Result<Signature> {
+        Ok(ed25519_dalek::Signer::sign(self, msg))
+    }
+}
+
+/// Assina e confere contra `public_key()` antes de devolver em base64 —
+/// um backend externo que assine com a chave errada falha aqui, não no verify.
+pub(crate) fn sign_b64(signer: &dyn Signer, msg: &[u8]) -> anyhow::Result<String> {
+    let sig = signer.sign_message(msg)?;
+    signer.public_key().verify(msg, &sig)
+        .map_err(|_| anyhow::anyhow!("signer returned a signature that does not match key {}", signer.kid()))?;
+    Ok(general_purpose::STANDARD.encode(sig.to_bytes()))
+}
@@ -0,0 +1,296 @@
+//! Conjunto de chaves confiáveis + threshold (aprovação M-of-N)
+//!
+//! O trust store (JSON) lista chaves por kid com janela de validade e flag de
+//! revogação; a validade é conferida no `now` de quem verifica, nunca no
+//! `created_at` (escolhido por quem assina: retroagir a data não reabre a
+//! janela). Pack assinado por chave aposentada precisa ser reassinado.
+//! Rotação: a chave antiga assina a entrada da nova.
+//! O kid é sempre o derivado da chave ([`kid_for`]) e cada chave entra uma vez:
+//! a mesma chave sob dois kids não conta duas vezes para o threshold.
+
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+use crate::signer::sign_b64;
+use crate::{canonical_json, kid_for, now_epoch, public_key_from_pem, verify_sig, Signer};
+
+pub const ROTATION_FORMAT: &str = "ubl-key-rotation/1";
+
+#[derive(Debug, Clone)]
+pub struct TrustedKey {
+    pub kid: String,
+    pub key: VerifyingKey,
+    pub not_before: Option<u64>,
+    pub not_after: Option<u64>,
+    pub revoked: bool,
+}
+
+impl TrustedKey {
+    /// kid derivado da própria chave (ver [`kid_for`]), sem janela.
+    pub fn new(key: VerifyingKey) -> Self {
+        Self { kid: kid_for(&key), key, not_before: None, not_after: None, revoked: false }
+    }
+
+    pub fn valid_at(&self, at: u64) -> bool {
+        self.not_before.is_none_or(|nbf| at >= nbf) && self.not_after.is_none_or(|naf| at <= naf)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrustSet {
+    keys: Vec<TrustedKey>,
+    threshold: usize,
+}
+
+/// Resultado de uma assinatura do pack
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SigStatus {
+    /// Confere com a chave confiável `kid`
+    Trusted { kid: String },
+    /// kid conhecido, mas a assinatura não confere
+    Invalid,
+    /// Confere, mas a chave foi revogada
+    Revoked { kid: String },
+    /// Confere, mas a chave está fora da janela de validade em `now`
+    Expired { kid: String },
+    /// Nenhuma chave do trust set assina estes bytes
+    Untrusted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SigCheck {
+    /// kid declarado no pack (v1 não tem)
+    pub kid: Option<String>,
+    #[serde(flatten)]
+    pub status: SigStatus,
+}
+
+impl TrustSet {
+    pub fn new(keys: Vec<TrustedKey>, threshold: usize) -> anyhow::Result<Self> {
+        if keys.is_empty() {
+            anyhow::bail!("trust set has no keys");
+        }
+        if threshold == 0 || threshold > keys.len() {
+            anyhow::bail!("threshold {} out of range 1..={}", threshold, keys.len());
+        }
+        for (i, k) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|o| o.kid == k.kid) {
+                anyhow::bail!("duplicate kid in trust set: {}", k.kid);
+            }
+            if let Some(o) = keys[..i].iter().find(|o| o.key == k.key) {
+                anyhow::bail!("key {} already in trust set as {}", k.kid, o.kid);
+            }
+        }
+        Ok(Self { keys, threshold })
+    }
+
+    /// Uma chave, threshold 1 (modo anterior a M-of-N).
+    pub fn single(key: VerifyingKey) -> Self {
+        Self { keys: vec![TrustedKey::new(key)], threshold: 1 }
+    }
+
+    pub fn keys(&self) -> &[TrustedKey] {
+        &self.keys
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Procura pelo kid; sem chave com esse kid, tenta as chaves utilizáveis em `now`.
+    pub(crate) fn check(&self, kid: Option<&str>, msg: &[u8], sig_b64: &str, now: u64) -> SigCheck {
+        let status = match self.keys.iter().find(|k| Some(k.kid.as_str()) == kid) {
+            Some(k) if !verify_sig(&k.key, msg, sig_b64) => SigStatus::Invalid,
+            Some(k) if k.revoked => SigStatus::Revoked { kid: k.kid.clone() },
+            Some(k) if !k.valid_at(now) => SigStatus::Expired { kid: k.kid.clone() },
+            Some(k) => SigStatus::Trusted { kid: k.kid.clone() },
+            None => match self.keys.iter()
+                .filter(|k| !k.revoked && k.valid_at(now))
+                .find(|k| verify_sig(&k.key, msg, sig_b64))
+            {
+                Some(k) => SigStatus::Trusted { kid: k.kid.clone() },
+                None => SigStatus::Untrusted,
+            },
+        };
+        SigCheck { kid: kid.map(str::to_string), status }
+    }
+
+    /// Chaves confiáveis distintas (por kid e por bytes); erro se não atingirem o threshold.
+    pub(crate) fn approvals(&self, checks: &[SigCheck]) -> anyhow::Result<Vec<String>> {
+        let mut signed_by: Vec<String> = Vec::new();
+        let mut seen: Vec<&VerifyingKey> = Vec::new();
+        for c in checks {
+            if let SigStatus::Trusted { kid } = &c.status {
+                let Some(k) = self.keys.iter().find(|k| &k.kid == kid) else { continue };
+                if !signed_by.contains(kid) && !seen.contains(&&k.key) {
+                    signed_by.push(kid.clone());
+                    seen.push(&k.key);
+                }
+            }
+        }
+        if signed_by.is_empty() {
+            if let Some(c) = checks.iter().find(|c| matches!(c.status, SigStatus::Revoked { .. } | SigStatus::Expired { .. })) {
+                match &c.status {
+                    SigStatus::Revoked { kid } => anyhow::bail!("pack signed by revoked key {}", kid),
+                    SigStatus::Expired { kid } => anyhow::bail!("pack signed by key {} outside its validity window", kid),
+                    _ => unreachable!(),
+                }
+            }
+            anyhow::bail!("pack signature mismatch");
+        }
+        if signed_by.len() < self.threshold {
+            anyhow::bail!("pack has {} of {} required trusted signatures ({})", signed_by.len(), self.threshold, signed_by.join(", "));
+        }
+        Ok(signed_by)
+    }
+}
+
+/// Entrada do trust store; `policy-keygen` grava uma igual em `<name>.kid.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyEntry {
+    pub kid: String,
+    /// SPKI PEM
+    pub public_key_pem: String,
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    #[serde(default)]
+    pub not_before: Option<u64>,
+    #[serde(default)]
+    pub not_after: Option<u64>,
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+impl KeyEntry {
+    pub fn for_key(key: &VerifyingKey, not_before: Option<u64>, not_after: Option<u64>) -> anyhow::Result<Self> {
+        let pem = key.to_public_key_pem(Default::default())
+            .map_err(|e| anyhow::anyhow!("encode public key PEM: {}", e))?;
+        Ok(Self {
+            kid: kid_for(key),
+            public_key_pem: pem,
+            created_at: Some(now_epoch()),
+            not_before,
+            not_after,
+            revoked: false,
+        })
+    }
+
+    /// Recusa entrada cujo kid não é o derivado da chave.
+    pub fn trusted_key(&self) -> anyhow::Result<TrustedKey> {
+        let key = public_key_from_pem(&self.public_key_pem).map_err(|e| anyhow::anyhow!("kid {}: {}", self.kid, e))?;
+        if self.kid != kid_for(&key) {
+            anyhow::bail!("kid {} does not match its key ({})", self.kid, kid_for(&key));
+        }
+        Ok(TrustedKey { kid: self.kid.clone(), key, not_before: self.not_before, not_after: self.not_after, revoked: self.revoked })
+    }
+}
+
+/// Arquivo do trust store (POLICY_TRUST_STORE)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    /// Assinaturas distintas exigidas (padrão 1)
+    #[serde(default)]
+    pub threshold: Option<usize>,
+    pub keys: Vec<KeyEntry>,
+}
+
+impl TrustStore {
+    pub fn from_file(path: &str) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)?;
+        serde_json::from_str(&raw).map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    pub fn get(&self, kid: &str) -> Option<&KeyEntry> {
+        self.keys.iter().find(|k| k.kid == kid)
+    }
+
+    pub fn add(&mut self, entry: KeyEntry) -> anyhow::Result<()> {
+        if self.get(&entry.kid).is_some() {
+            anyhow::bail!("kid {} already in trust store", entry.kid);
+        }
+        let key = entry.trusted_key()?.key;
+        for other in &self.keys {
+            if other.trusted_key().is_ok_and(|o| o.key == key) {
+                anyhow::bail!("key {} already in trust store as {}", entry.kid, other.kid);
+            }
+        }
+        self.keys.push(entry);
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, kid: &str) -> anyhow::Result<()> {
+        let entry = self.keys.iter_mut().find(|k| k.kid == kid)
+            .ok_or_else(|| anyhow::anyhow!("kid {} not in trust store", kid))?;
+        entry.revoked = true;
+        Ok(())
+    }
+
+    /// Aceita a nova chave se o bundle foi assinado por uma chave do store
+    /// ainda utilizável (não revogada e dentro da janela em `now`).
+    pub fn apply_rotation(&mut self, bundle: &RotationBundle, now: u64) -> anyhow::Result<()> {
+        let old = self.get(&bundle.old_kid)
+            .ok_or_else(|| anyhow::anyhow!("rotation signed by unknown kid {}", bundle.old_kid))?
+            .trusted_key()?;
+        if old.revoked || !old.valid_at(now) {
+            anyhow::bail!("rotation signed by unusable key {}", old.kid);
+        }
+        bundle.verify(&old.key)?;
+        self.add(bundle.new_key.clone())
+    }
+
+    /// `threshold` explícito tem precedência sobre o do arquivo.
+    pub fn trust_set(&self, threshold: Option<usize>) -> anyhow::Result<TrustSet> {
+        let keys = self.keys.iter().map(KeyEntry::trusted_key).collect::<anyhow::Result<Vec<_>>>()?;
+        TrustSet::new(keys, threshold.or(self.threshold).unwrap_or(1))
+    }
+}
+
+/// Nova chave endossada pela anterior (`<name>.rotation.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotationBundle {
+    pub format: String,
+    pub old_kid: String,
+    pub new_key: KeyEntry,
+    /// Ed25519 (base64) da chave antiga sobre `signed_bytes()`
+    pub signature: String,
+}
+
+impl RotationBundle {
+    pub fn sign(old: &dyn Signer, new_key: KeyEntry) -> anyhow::Result<Self> {
+        let mut bundle = Self {
+            format: ROTATION_FORMAT.into(),
+            old_kid: old.kid(),
+            new_key,
+            signature: String::new(),
+        };
+        bundle.signature = sign_b64(old, &bundle.signed_bytes())?;
+        Ok(bundle)
+    }
+
+    /// JSON canônico de tudo exceto `signature`.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let v = serde_json::json!({
+            "format": self.format,
+            "old_kid": self.old_kid,
+            "new_key": self.new_key,
+        });
+        canonical_json(&v)
+    }
+
+    pub fn verify(&self, old: &VerifyingKey) -> anyhow::Result<()> {
+        if self.format != ROTATION_FORMAT {
+            anyhow::bail!("unsupported rotation format: {}", self.format);
+        }
+        if !verify_sig(old, &self.signed_bytes(), &self.signature) {
+            anyhow::bail!("rotation signature mismatch");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Anti-rollback: maior (id, version) aceito por tenant, persistido em disco.
+//! Um pack mais antigo — ou com outro id — só entra com `pack.downgrade.json`
+//! assinado pelo trust set.
+
+use parking_lot::Mutex;
+use policy_pack::{compare_versions, DowngradeAuth, TrustSet};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::policy::PackInfo;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mark {
+    /// id do pack aceito; outro id no mesmo tenant precisa de autorização
+    #[serde(default)]
+    pub id: String,
+    pub version: String,
+    /// `created_at` assinado (v2) desempata a mesma versão
+    pub created_at: Option<u64>,
+    pub blake3: String,
+    pub accepted_at: i64,
+}
+
+/// Pack recusado por ser mais antigo que o maior aceito (ou de outro id).
+#[derive(Debug)]
+pub struct RollbackRejected {
+    pub id: String,
+    pub version: String,
+    /// `<id>@<version>` aceito no tenant
+    pub highest: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for RollbackRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pack {}@{} does not supersede accepted {}: {}", self.id, self.version, self.highest, self.reason)
+    }
+}
+
+impl std::error::Error for RollbackRejected {}
+
+/// Como o pack passou pela checagem
+#[derive(Debug)]
+pub enum Admission {
+    Forward,
+    /// Downgrade autorizado pelos kids listados
+    Downgrade(Vec<String>),
+}
+
+pub struct HighWater {
+    path: String,
+    marks: Mutex<BTreeMap<String, Mark>>, // chave: tenant
+}
+
+impl HighWater {
+    /// Arquivo inexistente = nenhum pack aceito ainda.
+    pub fn load(path: String) -> anyhow::Result<Self> {
+        let stored: BTreeMap<String, Mark> = match std::fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(anyhow::anyhow!("{}: {}", path, e)),
+        };
+        // formato antigo "<tenant>/<pack id>": fica a marca aceita por último
+        let mut marks: BTreeMap<String, Mark> = BTreeMap::new();
+        for (k, mut mark) in stored {
+            let tenant = match k.split_once('/') {
+                Some((tenant, id)) => {
+                    mark.id = id.to_string();
+                    tenant.to_string()
+                }
+                None => k,
+            };
+            if marks.get(&tenant).is_none_or(|m| m.accepted_at < mark.accepted_at) {
+                marks.insert(tenant, mark);
+            }
+        }
+        Ok(Self { path, marks: Mutex::new(marks) })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get(&self, tenant: &str) -> Option<Mark> {
+        self.marks.lock().get(tenant).cloned()
+    }
+
+    /// Recusa packs mais antigos que o marcado — ou com id diferente, que
+    /// não tem versão comparável — salvo downgrade autorizado
+    /// (`pack.downgrade.json` ao lado de `pack_path`).
+    pub fn admit(&self, tenant: &str, info: &PackInfo, pack_path: &str, trust: &TrustSet, now: u64) -> anyhow::Result<Admission> {
+        let Some(mark) = self.get(tenant) else { return Ok(Admission::Forward) };
+        let problem = if mark.id != info.id {
+            format!("pack id differs from accepted {}", mark.id)
+        } else {
+            let older = match compare_versions(&info.version, &mark.version) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => matches!((info.created_at, mark.created_at), (Some(a), Some(b)) if a < b),
+            };
+            if !older {
+                return Ok(Admission::Forward);
+            }
+            "older version".to_string()
+        };
+        let reject = |reason: String| RollbackRejected {
+            id: info.id.clone(),
+            version: info.version.clone(),
+            highest: format!("{}@{}", mark.id, mark.version),
+            reason: format!("{}; {}", problem, reason),
+        };
+        let auth_path = downgrade_path(pack_path);
+        let raw = std::fs::read_to_string(&auth_path).map_err(|_| reject(format!("no downgrade authorization at {}", auth_path)))?;
+        let auth = DowngradeAuth::from_json(&raw).map_err(|e| reject(e.to_string()))?;
+        let kids = auth.authorizes(trust, &info.id, &info.version, &info.blake3, now).map_err(|e| reject(e.to_string()))?;
+        Ok(Admission::Downgrade(kids))
+    }
+
+    /// Marca o pack instalado (também quando é um downgrade autorizado) e persiste.
+    pub fn record(&self, tenant: &str, info: &PackInfo, now: i64) -> anyhow::Result<()> {
+        let mut marks = self.marks.lock();
+        marks.insert(tenant.to_string(), Mark {
+            id: info.id.clone(),
+            version: info.version.clone(),
+            created_at: info.created_at,
+            blake3: info.blake3.clone(),
+            accepted_at: now,
+        });
+        if let Some(parent) = std::path::Path::new(&self.path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        // grava e renomeia: um crash no meio não deixa o arquivo truncado
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, serde_json::to_string_pretty(&*marks)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// `pack.json` → `pack.downgrade.json`, `pack.next.json` → `pack.next.downgrade.json`
+pub fn downgrade_path(pack_path: &str) -> String {
+    match pack_path.strip_suffix(".json") {
+        Some(stem) => format!("{}.downgrade.json", stem),
+        None => format!("{}.downgrade.json", pack_path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use policy_pack::{kid_for, TrustedKey};
+
+    fn info(version: &str, created_at: u64) -> PackInfo {
+        info_for("ubl_core", version, created_at)
+    }
+
+    fn info_for(id: &str, version: &str, created_at: u64) -> PackInfo {
+        PackInfo {
+            id: id.into(),
+            version: version.into(),
+            blake3: format!("b3-{}", version),
+            format: 2,
+            kid: None,
+            signed_by: vec![],
+            created_at: Some(created_at),
+        }
+    }
+
+    #[test]
+    fn older_pack_needs_signed_downgrade() {
+        let dir = std::env::temp_dir().join(format!("highwater-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pack_path = dir.join("pack.json").to_string_lossy().to_string();
+        let hw_path = dir.join("state/highwater.json").to_string_lossy().to_string();
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let trust = TrustSet::new(vec![TrustedKey::new(key.verifying_key())], 1).unwrap();
+
+        let hw = HighWater::load(hw_path.clone()).unwrap();
+        assert!(matches!(hw.admit("ubl", &info("3.1.0", 10), &pack_path, &trust, 100).unwrap(), Admission::Forward));
+        hw.record("ubl", &info("3.1.0", 10), 100).unwrap();
+
+        // persistido: um processo novo enxerga a marca
+        let hw = HighWater::load(hw_path).unwrap();
+        assert!(matches!(hw.admit("ubl", &info("3.1.0", 20), &pack_path, &trust, 100).unwrap(), Admission::Forward));
+        assert!(hw.admit("ubl", &info("3.1.0", 5), &pack_path, &trust, 100).is_err());
+        let err = hw.admit("ubl", &info("3.0.9", 30), &pack_path, &trust, 100).unwrap_err();
+        assert!(err.downcast_ref::<RollbackRejected>().is_some());
+        assert!(matches!(hw.admit("vvz", &info("1", 1), &pack_path, &trust, 100).unwrap(), Admission::Forward));
+
+        let old = info("3.0.9", 30);
+        let mut auth = DowngradeAuth::new(old.id.clone(), old.version.clone(), old.blake3.clone(), 90, 200);
+        auth.sign(&key, kid_for(&key.verifying_key())).unwrap();
+        std::fs::write(downgrade_path(&pack_path), serde_json::to_string(&auth).unwrap()).unwrap();
+        assert!(matches!(hw.admit("ubl", &old, &pack_path, &trust, 100).unwrap(), Admission::Downgrade(_)));
+        assert!(hw.admit("ubl", &old, &pack_path, &trust, 300).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Trocar o id do pack não contorna a marca: outro id precisa de autorização.
+    #[test]
+    fn different_pack_id_is_not_a_fresh_start() {
+        let dir = std::env::temp_dir().join(format!("highwater-id-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pack_path = dir.join("pack.json").to_string_lossy().to_string();
+        let hw_path = dir.join("highwater.json").to_string_lossy().to_string();
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let trust = TrustSet::new(vec![TrustedKey::new(key.verifying_key())], 1).unwrap();
+
+        // arquivo no formato antigo "<tenant>/<pack id>"
+        std::fs::write(&hw_path, r#"{"ubl/ubl_core":{"version":"3.1.0","created_at":10,"blake3":"x","accepted_at":100}}"#).unwrap();
+        let hw = HighWater::load(hw_path).unwrap();
+        assert_eq!(hw.get("ubl").unwrap().id, "ubl_core");
+
+        let other = info_for("ubl_core_legacy", "1.0.0", 1);
+        let err = hw.admit("ubl", &other, &pack_path, &trust, 100).unwrap_err();
+        assert!(err.downcast_ref::<RollbackRejected>().unwrap().reason.contains("pack id differs"));
+
+        let mut auth = DowngradeAuth::new(other.id.clone(), other.version.clone(), other.blake3.clone(), 90, 200);
+        auth.sign(&key, kid_for(&key.verifying_key())).unwrap();
+        std::fs::write(downgrade_path(&pack_path), serde_json::to_string(&auth).unwrap()).unwrap();
+        assert!(matches!(hw.admit("ubl", &other, &pack_path, &trust, 100).unwrap(), Admission::Downgrade(_)));
+        hw.record("ubl", &other, 100).unwrap();
+        assert!(hw.admit("ubl", &info("3.1.0", 10), &pack_path, &trust, 300).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,154 @@
+//! Ledger NDJSON local (hash BLAKE3 por linha), escrito por uma task dedicada
+//! com buffer; `close` drena a fila e faz flush/fsync antes do processo sair.
+//! A fila é limitada: linha que não cabe (ou que não pôde ser escrita) é
+//! descartada e contada em `policy_ledger_dropped_total{reason}`.
+
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::metrics::Metrics;
+
+/// Linhas aguardando a task de escrita.
+pub const QUEUE: usize = 8192;
+
+enum Msg {
+    Line(serde_json::Value),
+    Close(oneshot::Sender<()>),
+}
+
+#[derive(Clone)]
+pub struct Ledger {
+    tx: mpsc::Sender<Msg>,
+    metrics: Arc<Metrics>,
+}
+
+impl Ledger {
+    /// Abre o arquivo já aqui: ledger indisponível impede a subida.
+    pub fn spawn(path: String, queue: usize, metrics: Arc<Metrics>) -> anyhow::Result<Self> {
+        let out = open(&path).map_err(|e| anyhow::anyhow!("ledger {}: {}", path, e))?;
+        let (tx, rx) = mpsc::channel(queue);
+        tokio::spawn(writer(path, out, rx, metrics.clone()));
+        Ok(Self { tx, metrics })
+    }
+
+    pub fn append(&self, obj: serde_json::Value) {
+        let reason = match self.tx.try_send(Msg::Line(obj)) {
+            Ok(()) => return,
+            Err(mpsc::error::TrySendError::Full(_)) => "queue_full",
+            Err(mpsc::error::TrySendError::Closed(_)) => "closed",
+        };
+        self.metrics.ledger_dropped_total.with(&[reason]).inc();
+    }
+
+    /// Grava tudo que estiver na fila e espera o flush.
+    pub async fn close(&self) {
+        let (ack, done) = oneshot::channel();
+        if self.tx.send(Msg::Close(ack)).await.is_ok() {
+            let _ = done.await;
+        }
+    }
+}
+
+fn open(path: &str) -> std::io::Result<std::io::BufWriter<std::fs::File>> {
+    // Criar diretório se não existir
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let f = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(std::io::BufWriter::new(f))
+}
+
+fn hashed(mut obj: serde_json::Value) -> String {
+    let canon = serde_json::to_string(&obj).unwrap_or_default();
+    let dig = hex::encode(blake3::hash(canon.as_bytes()).as_bytes());
+    obj["hash"] = serde_json::Value::String(dig);
+    serde_json::to_string(&obj).unwrap_or(canon)
+}
+
+/// Depois de um erro de escrita o arquivo é reaberto a cada tick; enquanto
+/// isso as linhas contam como `unavailable`.
+async fn writer(path: String, out: std::io::BufWriter<std::fs::File>, mut rx: mpsc::Receiver<Msg>, metrics: Arc<Metrics>) {
+    let mut out = Some(out);
+    let write = |out: &mut Option<std::io::BufWriter<std::fs::File>>, obj| {
+        let Some(w) = out.as_mut() else {
+            metrics.ledger_dropped_total.with(&["unavailable"]).inc();
+            return;
+        };
+        if let Err(e) = writeln!(w, "{}", hashed(obj)) {
+            eprintln!("ledger {} write failed: {}; reopening", path, e);
+            metrics.ledger_dropped_total.with(&["write_error"]).inc();
+            *out = None;
+        }
+    };
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(Msg::Line(obj)) => write(&mut out, obj),
+                Some(Msg::Close(ack)) => {
+                    while let Ok(Msg::Line(obj)) = rx.try_recv() {
+                        write(&mut out, obj);
+                    }
+                    if let Some(w) = out.as_mut() {
+                        let _ = w.flush();
+                        let _ = w.get_ref().sync_all();
+                    }
+                    let _ = ack.send(());
+                    return;
+                }
+                None => break,
+            },
+            _ = tick.tick() => match out.as_mut() {
+                Some(w) => {
+                    let _ = w.flush();
+                }
+                None => out = open(&path).ok(),
+            }
+        }
+    }
+    if let Some(w) = out.as_mut() {
+        let _ = w.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dropped(m: &Metrics, reason: &str) -> u64 {
+        m.ledger_dropped_total.with(&[reason]).get()
+    }
+
+    /// A task só roda quando o teste cede: o que passa da fila é contado, o resto
+    /// chega ao arquivo no `close`.
+    #[tokio::test]
+    async fn close_drains_the_queue_and_counts_overflow() {
+        let dir = std::env::temp_dir().join(format!("ledger-test-{}", std::process::id()));
+        let path = dir.join("ledger.ndjson");
+        let _ = std::fs::remove_file(&path);
+        let metrics = Arc::new(Metrics::default());
+        let ledger = Ledger::spawn(path.to_string_lossy().into(), 3, metrics.clone()).unwrap();
+        for i in 0..5 {
+            ledger.append(serde_json::json!({ "n": i }));
+        }
+        assert_eq!(dropped(&metrics, "queue_full"), 2);
+        ledger.close().await;
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().enumerate().all(|(i, l)| l["n"] == i && l["hash"].is_string()));
+
+        ledger.append(serde_json::json!({ "n": 9 }));
+        assert_eq!(dropped(&metrics, "closed"), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn unopenable_ledger_fails_startup() {
+        let err = Ledger::spawn("/dev/null/ledger.ndjson".into(), QUEUE, Arc::new(Metrics::default())).err().unwrap();
+        assert!(err.to_string().contains("/dev/null/ledger.ndjson"), "{}", err);
+    }
+}
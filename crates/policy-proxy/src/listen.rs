@@ -0,0 +1,207 @@
+//! Listener configurável (TCP, Unix socket ou socket ativado pelo systemd)
+//! e serve com drenagem de conexões no shutdown.
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use std::future::Future;
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+use std::time::Duration;
+
+/// Primeiro fd passado pelo systemd (sd_listen_fds).
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+pub enum Listener {
+    Tcp(tokio::net::TcpListener),
+    Unix(tokio::net::UnixListener),
+}
+
+impl Listener {
+    /// Socket do systemd (LISTEN_FDS/LISTEN_PID) tem precedência; senão `LISTEN_ADDR`,
+    /// que aceita `host:port` ou `unix:/caminho.sock`.
+    pub async fn from_env() -> anyhow::Result<Self> {
+        if let Some(l) = Self::from_systemd()? {
+            return Ok(l);
+        }
+        let addr = std::env::var("LISTEN_ADDR").unwrap_or_else(|_| "127.0.0.1:9456".into());
+        Self::bind(&addr).await
+    }
+
+    pub async fn bind(addr: &str) -> anyhow::Result<Self> {
+        match addr.strip_prefix("unix:") {
+            Some(path) => {
+                // socket velho de um processo anterior impede o bind
+                let _ = std::fs::remove_file(path);
+                Ok(Self::Unix(tokio::net::UnixListener::bind(path)?))
+            }
+            None => Ok(Self::Tcp(tokio::net::TcpListener::bind(addr).await?)),
+        }
+    }
+
+    fn from_systemd() -> anyhow::Result<Option<Self>> {
+        let pid_ok = std::env::var("LISTEN_PID").ok()
+            .and_then(|p| p.parse::<u32>().ok())
+            .is_some_and(|p| p == std::process::id());
+        let fds: usize = std::env::var("LISTEN_FDS").ok().and_then(|n| n.parse().ok()).unwrap_or(0);
+        if !pid_ok || fds == 0 {
+            return Ok(None);
+        }
+        if fds > 1 {
+            anyhow::bail!("expected a single socket from systemd, got LISTEN_FDS={}", fds);
+        }
+        // SAFETY: o systemd nos entrega o fd 3 aberto e exclusivo deste processo.
+        let listener = unsafe { Self::from_fd(SD_LISTEN_FDS_START)? };
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_PID");
+        Ok(Some(listener))
+    }
+
+    /// Adota um socket já em listen: AF_UNIX ou, se não for, TCP.
+    ///
+    /// # Safety
+    /// `fd` precisa ser um socket em listen aberto e sem outro dono.
+    unsafe fn from_fd(fd: RawFd) -> anyhow::Result<Self> {
+        let unix = std::os::unix::net::UnixListener::from_raw_fd(fd);
+        if unix.local_addr().is_ok() {
+            unix.set_nonblocking(true)?;
+            return Ok(Self::Unix(tokio::net::UnixListener::from_std(unix)?));
+        }
+        // mesmo fd, que não é AF_UNIX; reinterpretado como TCP
+        let tcp = std::net::TcpListener::from_raw_fd(unix.into_raw_fd());
+        tcp.set_nonblocking(true)?;
+        Ok(Self::Tcp(tokio::net::TcpListener::from_std(tcp)?))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Tcp(l) => l.local_addr().map(|a| a.to_string()).unwrap_or_else(|_| "tcp:?".into()),
+            Self::Unix(l) => l.local_addr().ok()
+                .and_then(|a| a.as_pathname().map(|p| format!("unix:{}", p.display())))
+                .unwrap_or_else(|| "unix:?".into()),
+        }
+    }
+}
+
+/// Aceita conexões até `shutdown` resolver; depois para de aceitar e espera as
+/// requests em andamento terminarem por no máximo `drain`.
+pub async fn serve(listener: Listener, app: Router, shutdown: impl Future<Output = ()>, drain: Duration) {
+    let graceful = GracefulShutdown::new();
+    let builder = Builder::new(TokioExecutor::new());
+    tokio::pin!(shutdown);
+
+    loop {
+        macro_rules! spawn_conn {
+            ($stream:expr) => {{
+                let svc = TowerToHyperService::new(app.clone());
+                let conn = builder.serve_connection_with_upgrades(TokioIo::new($stream), svc).into_owned();
+                let fut = graceful.watch(conn);
+                tokio::spawn(async move {
+                    if let Err(e) = fut.await {
+                        eprintln!("connection error: {}", e);
+                    }
+                });
+            }};
+        }
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = accept(&listener) => match accepted {
+                Ok(Stream::Tcp(s)) => spawn_conn!(s),
+                Ok(Stream::Unix(s)) => spawn_conn!(s),
+                Err(e) => {
+                    eprintln!("accept error: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            },
+        }
+    }
+
+    drop(listener);
+    let in_flight = graceful.count();
+    println!("draining {} connection(s), deadline {}s", in_flight, drain.as_secs());
+    if tokio::time::timeout(drain, graceful.shutdown()).await.is_err() {
+        eprintln!("drain deadline reached; dropping remaining connections");
+    }
+}
+
+enum Stream {
+    Tcp(tokio::net::TcpStream),
+    Unix(tokio::net::UnixStream),
+}
+
+async fn accept(listener: &Listener) -> std::io::Result<Stream> {
+    match listener {
+        Listener::Tcp(l) => l.accept().await.map(|(s, _)| Stream::Tcp(s)),
+        Listener::Unix(l) => l.accept().await.map(|(s, _)| Stream::Unix(s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::io::{Read, Write};
+
+    /// Uma request HTTP/1.1 pelo socket (cliente bloqueante fora do runtime).
+    async fn get_ok<S: Read + Write + Send + 'static>(connect: impl FnOnce() -> S + Send + 'static) -> String {
+        tokio::task::spawn_blocking(move || {
+            let mut s = connect();
+            s.write_all(b"GET /ok HTTP/1.1\r\nHost: t\r\nConnection: close\r\n\r\n").unwrap();
+            let mut out = String::new();
+            s.read_to_string(&mut out).unwrap();
+            out
+        }).await.unwrap()
+    }
+
+    async fn serve_once(listener: Listener) -> (tokio::sync::oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let app = Router::new().route("/ok", get(|| async { "ok" }));
+        let server = tokio::spawn(serve(listener, app, async { let _ = stopped.await; }, Duration::from_secs(1)));
+        (stop, server)
+    }
+
+    #[tokio::test]
+    async fn serves_on_a_unix_socket() {
+        let path = std::env::temp_dir().join(format!("listen-test-{}.sock", std::process::id()));
+        let listener = Listener::bind(&format!("unix:{}", path.display())).await.unwrap();
+        assert_eq!(listener.describe(), format!("unix:{}", path.display()));
+        let (stop, server) = serve_once(listener).await;
+
+        let p = path.clone();
+        let resp = get_ok(move || std::os::unix::net::UnixStream::connect(p).unwrap()).await;
+        assert!(resp.starts_with("HTTP/1.1 200") && resp.ends_with("ok"), "{}", resp);
+        let _ = stop.send(());
+        server.await.unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// O caminho do systemd: o fd herdado pode ser AF_UNIX ou TCP.
+    #[tokio::test]
+    async fn adopts_inherited_unix_and_tcp_fds() {
+        let path = std::env::temp_dir().join(format!("listen-fd-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let fd = std::os::unix::net::UnixListener::bind(&path).unwrap().into_raw_fd();
+        // SAFETY: fd acabou de sair de um listener nosso
+        let unix = unsafe { Listener::from_fd(fd) }.unwrap();
+        assert!(matches!(unix, Listener::Unix(_)));
+        let (stop, server) = serve_once(unix).await;
+        let p = path.clone();
+        let resp = get_ok(move || std::os::unix::net::UnixStream::connect(p).unwrap()).await;
+        assert!(resp.starts_with("HTTP/1.1 200"), "{}", resp);
+        let _ = stop.send(());
+        server.await.unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+        // SAFETY: idem
+        let tcp = unsafe { Listener::from_fd(tcp.into_raw_fd()) }.unwrap();
+        assert_eq!(tcp.describe(), addr.to_string());
+        let (stop, server) = serve_once(tcp).await;
+        let resp = get_ok(move || std::net::TcpStream::connect(addr).unwrap()).await;
+        assert!(resp.starts_with("HTTP/1.1 200"), "{}", resp);
+        let _ = stop.send(());
+        server.await.unwrap();
+    }
+}
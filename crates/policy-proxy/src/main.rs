@@ -1,28 +1,41 @@
-    use axum::{routing::{get, post, any}, Router, extract::{State, Path, Request}, http::{HeaderMap, StatusCode, Method}, body::Bytes};
+    use axum::{routing::{get, post}, Router, extract::{State, Path, Request}, http::{HeaderMap, StatusCode}, body::Bytes};
     use parking_lot::RwLock;
-    use serde::{Deserialize, Serialize};
-    use std::{sync::Arc, net::SocketAddr, fs, time::{SystemTime, UNIX_EPOCH}};
-    use base64::{engine::general_purpose, Engine as _};
-    use ed25519_dalek::{Signature, VerifyingKey, pkcs8::DecodePublicKey, Verifier};
-    use blake3::Hasher;
+    use serde::Deserialize;
+    use std::{sync::Arc, time::Duration};
+    use tokio::signal::unix::{signal, SignalKind};
 
-    use policy_engine::{SemanticChip, RequestContext, decide};
+    use policy_engine::{Lookup, RequestContext};
+    use policy_assertion::{AssertionClaims, AssertionSigner, step_up::StepUpVerifier};
+
+    mod highwater;
+    mod ledger;
+    mod listen;
+    mod metrics;
+    mod obligations;
+    mod policy;
+    mod tenants;
+    use highwater::{Admission, HighWater, RollbackRejected};
+    use ledger::Ledger;
+    use metrics::Metrics;
+    use policy::{LoadedPolicy, PolicySource, Trust, load_and_verify};
+    use tenants::{Tenant, Tenants, TenantsConfig};
+
+    type Params = axum::extract::Query<std::collections::HashMap<String, String>>;
 
     #[derive(Clone)]
     struct AppState {
-        chip: Arc<RwLock<SemanticChip>>,
-        pubkey_pem_b64: String,
-        pack_json_path: String,
-        policy_yaml_path: String,
+        tenants: Arc<Tenants>,
+        trust: Arc<Trust>,
+        highwater: Arc<HighWater>,
         upstream_core: String,
         upstream_webhooks: String,
         panic_until: Arc<RwLock<i64>>,
         panic_reason: Arc<RwLock<String>>,
-        allow_total: Arc<RwLock<u64>>,
-        deny_total: Arc<RwLock<u64>>,
-        eval_ms_sum: Arc<RwLock<f64>>,
-        eval_ms_max: Arc<RwLock<f64>>,
-        eval_count: Arc<RwLock<u64>>,
+        metrics: Arc<Metrics>,
+        ledger: Ledger,
+        assertion: Option<Arc<AssertionSigner>>,
+        step_up: Option<Arc<StepUpVerifier>>,
+        watch_sec: u64,
     }
 
     #[derive(Deserialize)]
@@ -33,30 +46,79 @@
         // Blueprint 02: roteamento por prefixo
         let upstream_core = std::env::var("UPSTREAM_CORE").unwrap_or_else(|_| "http://127.0.0.1:9458".into());
         let upstream_webhooks = std::env::var("UPSTREAM_WEBHOOKS").unwrap_or_else(|_| "http://127.0.0.1:9460".into());
-        let pubkey_pem_b64 = std::env::var("POLICY_PUBKEY_PEM_B64").expect("set POLICY_PUBKEY_PEM_B64");
-        let policy_yaml_path = std::env::var("POLICY_YAML").unwrap_or("/etc/ubl/flagship/policy/ubl_core_v1.yaml".into());
-        let pack_json_path = std::env::var("POLICY_PACK").unwrap_or("/etc/ubl/flagship/policy/pack.json".into());
+        let trust = Trust::from_env()?;
+        // Intervalo de polling dos arquivos de política (0 = desligado)
+        let watch_sec: u64 = std::env::var("POLICY_WATCH_SEC").ok().and_then(|v| v.parse().ok()).unwrap_or(5);
+        // Prazo para drenar requests em andamento no SIGTERM
+        let drain_sec: u64 = std::env::var("SHUTDOWN_DRAIN_SEC").ok().and_then(|v| v.parse().ok()).unwrap_or(25);
+        let ledger_path = std::env::var("LEDGER_PATH").unwrap_or_else(|_| "/var/log/ubl/flagship-ledger.ndjson".into());
+        // Maior (id, version) aceito por tenant (anti-rollback)
+        let highwater = HighWater::load(std::env::var("POLICY_HIGHWATER_PATH").unwrap_or_else(|_| "/var/lib/ubl/policy-proxy/highwater.json".into()))?;
+        // Chave Ed25519 (PKCS#8 PEM) que assina X-Ubl-Assertion para os upstreams
+        let assertion = match std::env::var("POLICY_ASSERTION_KEY_PEM") {
+            Ok(path) => {
+                let kid = std::env::var("POLICY_ASSERTION_KID").unwrap_or_else(|_| "proxy-v1".into());
+                Some(Arc::new(AssertionSigner::from_pkcs8_pem(&std::fs::read_to_string(path)?, kid)?))
+            }
+            Err(_) => {
+                eprintln!("POLICY_ASSERTION_KEY_PEM not set: upstreams will not receive X-Ubl-Assertion");
+                None
+            }
+        };
+        // Chave pública do gateway para provas de step-up (saídas challenge)
+        let step_up = StepUpVerifier::from_env()?.map(Arc::new);
+        if step_up.is_none() {
+            eprintln!("POLICY_STEP_UP_PUBKEY_PEM_B64 not set: challenge outputs cannot be satisfied");
+        }
+        // POLICY_TENANTS (YAML) tem precedência; sem ele, um único tenant via POLICY_BUNDLE ou POLICY_YAML/POLICY_PACK
+        let tenants_cfg = match std::env::var("POLICY_TENANTS") {
+            Ok(path) => TenantsConfig::from_file(&path)?,
+            Err(_) => TenantsConfig::single(
+                &std::env::var("TENANT_DEFAULT").unwrap_or_else(|_| "ubl".into()),
+                match std::env::var("POLICY_BUNDLE") {
+                    Ok(path) => PolicySource::Bundle(path),
+                    Err(_) => PolicySource::Files {
+                        yaml: std::env::var("POLICY_YAML").unwrap_or("/etc/ubl/flagship/policy/ubl_core_v1.yaml".into()),
+                        pack: std::env::var("POLICY_PACK").unwrap_or("/etc/ubl/flagship/policy/pack.json".into()),
+                    },
+                },
+            ),
+        };
+
+        let tenants = Tenants::load(tenants_cfg, &trust.get(), &highwater, now_epoch())?;
+        let registry = Metrics::default();
+        for t in tenants.all() {
+            let active = t.policy.active();
+            registry.set_chip_info(&t.id, &active.info.id, &active.info.version, &active.chip.version, &active.info.blake3);
+            registry.reload_last_success.with(&[&t.id]).set(now_epoch());
+        }
 
-        let chip = load_and_verify(&policy_yaml_path, &pack_json_path, &pubkey_pem_b64)?;
+        let registry = Arc::new(registry);
+        let ledger = Ledger::spawn(ledger_path, ledger::QUEUE, registry.clone())?;
 
         let state = AppState{
-            chip: Arc::new(RwLock::new(chip)),
-            pubkey_pem_b64,
-            pack_json_path,
-            policy_yaml_path,
+            tenants: Arc::new(tenants),
+            trust: Arc::new(trust),
+            highwater: Arc::new(highwater),
             upstream_core,
             upstream_webhooks,
             panic_until: Arc::new(RwLock::new(0)),
             panic_reason: Arc::new(RwLock::new(String::new())),
-            allow_total: Arc::new(RwLock::new(0)),
-            deny_total: Arc::new(RwLock::new(0)),
-            eval_ms_sum: Arc::new(RwLock::new(0.0)),
-            eval_ms_max: Arc::new(RwLock::new(0.0)),
-            eval_count: Arc::new(RwLock::new(0)),
+            metrics: registry,
+            ledger,
+            assertion,
+            step_up,
+            watch_sec,
         };
 
+        if watch_sec > 0 {
+            tokio::spawn(watch_policy(state.clone()));
+        }
+
         let app = Router::new()
             .route("/_reload", get(reload))
+            .route("/_rollback", post(rollback))
+            .route("/_policy", get(policy_info))
             .route("/__breakglass", post(panic_on))
             .route("/__breakglass/clear", post(panic_off))
             .route("/metrics", get(metrics))
@@ -69,70 +131,189 @@
             .head(forward))
             .with_state(state.clone());
 
-        let addr: SocketAddr = "127.0.0.1:9456".parse().unwrap();
-        println!("policy-proxy (rs) on {}", addr);
-        axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
+        tokio::spawn(reload_on_sighup(state.clone()));
+
+        let listener = listen::Listener::from_env().await?;
+        println!("policy-proxy (rs) on {}", listener.describe());
+        listen::serve(listener, app, shutdown_signal(), Duration::from_secs(drain_sec)).await;
+        state.ledger.close().await;
+        println!("policy-proxy stopped");
         Ok(())
     }
 
-    fn load_and_verify(policy_yaml_path: &str, pack_json_path: &str, pubkey_pem_b64: &str) -> anyhow::Result<SemanticChip> {
-        let yaml = fs::read_to_string(policy_yaml_path)?;
-        let pack_raw = fs::read_to_string(pack_json_path)?;
-        let pack: serde_json::Value = serde_json::from_str(&pack_raw)?;
-        let msg = format!(
-            "id={}\nversion={}\nblake3={}\n",
-            pack.get("id").and_then(|v| v.as_str()).unwrap_or(""),
-            pack.get("version").and_then(|v| v.as_str()).unwrap_or(""),
-            pack.get("blake3").and_then(|v| v.as_str()).unwrap_or(""),
-        );
-        // Decodificar base64 para obter PEM (string)
-        let pem_str = String::from_utf8(general_purpose::STANDARD.decode(pubkey_pem_b64)?)
-            .map_err(|e| anyhow::anyhow!("invalid PEM base64: {}", e))?;
-        
-        // Extrair conteúdo base64 do PEM e decodificar para DER
-        let pem_lines: Vec<&str> = pem_str.lines()
-            .filter(|l| !l.starts_with("-----"))
-            .collect();
-        let pem_content: String = pem_lines.join("");
-        let der_bytes = general_purpose::STANDARD.decode(pem_content)
-            .map_err(|e| anyhow::anyhow!("failed to decode PEM content: {}", e))?;
-        
-        let vk = VerifyingKey::from_public_key_der(&der_bytes)?;
-        let sig_b64 = pack.get("signature").and_then(|v| v.as_str()).ok_or(anyhow::anyhow!("signature missing"))?;
-        let sig_bytes = general_purpose::STANDARD.decode(sig_b64)?;
-        let sig_array: [u8; 64] = sig_bytes.try_into().map_err(|_| anyhow::anyhow!("invalid signature length"))?;
-        let sig = Signature::from_bytes(&sig_array);
-        vk.verify(msg.as_bytes(), &sig)?;
-
-        // verify blake3
-        let hash = blake3::hash(yaml.as_bytes());
-        let digest = hex::encode(hash.as_bytes());
-        if digest != pack.get("blake3").and_then(|v| v.as_str()).unwrap_or("") {
-            return Err(anyhow::anyhow!("policy YAML does not match pack blake3"));
+    async fn shutdown_signal() {
+        let mut term = signal(SignalKind::terminate()).expect("install SIGTERM handler");
+        tokio::select! {
+            _ = term.recv() => println!("SIGTERM received, shutting down"),
+            _ = tokio::signal::ctrl_c() => println!("SIGINT received, shutting down"),
+        }
+    }
+
+    /// SIGHUP (systemctl reload) recarrega todos os tenants a partir dos arquivos ativos.
+    async fn reload_on_sighup(state: AppState) {
+        let mut hup = signal(SignalKind::hangup()).expect("install SIGHUP handler");
+        while hup.recv().await.is_some() {
+            for t in state.tenants.all() {
+                match apply_reload(&state, t, &t.source, "signal") {
+                    Ok(()) => println!("policy reloaded on SIGHUP: tenant={}", t.id),
+                    Err(e) => eprintln!("SIGHUP reload rejected for tenant={}, keeping last known-good: {}", t.id, e),
+                }
+            }
         }
-        let chip = SemanticChip::from_yaml(&yaml)?;
-        Ok(chip)
+    }
+
+    fn tenant_param<'a>(state: &'a AppState, params: &std::collections::HashMap<String, String>) -> Result<&'a Arc<Tenant>, (StatusCode, String)> {
+        state.tenants.by_param(params.get("tenant"))
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "unknown_tenant".into()))
     }
 
     async fn reload(
         State(state): State<AppState>,
-        axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+        axum::extract::Query(params): Params,
     ) -> Result<String, (StatusCode, String)> {
+        // ?tenant=<id> recarrega só aquele tenant (padrão: tenant default)
+        let tenant = tenant_param(&state, &params)?;
         // Blueprint 02: suporte a ?stage=next para shadow promotion
         let stage = params.get("stage").map(|s| s.as_str()).unwrap_or("active");
-        let (yaml_path, pack_path) = if stage == "next" {
-            // Tentar carregar pack.next.json e yaml.next.yaml
-            let next_pack = state.pack_json_path.replace("pack.json", "pack.next.json");
-            let next_yaml = state.policy_yaml_path.replace(".yaml", ".next.yaml").replace(".yml", ".next.yml");
-            (next_yaml, next_pack)
+        let source = if stage == "next" {
+            // pack.next.json + yaml.next.yaml, ou bundle.next.json
+            tenant.source.staged("next")
         } else {
-            (state.policy_yaml_path.clone(), state.pack_json_path.clone())
+            tenant.source.clone()
         };
-        
-        let chip = load_and_verify(&yaml_path, &pack_path, &state.pubkey_pem_b64)
+
+        apply_reload(&state, tenant, &source, "manual")
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        *state.chip.write() = chip;
-        Ok(format!(r#"{{"ok":true,"reloaded":true,"stage":"{}"}}"#, stage))
+        Ok(format!(r#"{{"ok":true,"reloaded":true,"stage":"{}","tenant":"{}"}}"#, stage, tenant.id))
+    }
+
+    /// Conta a falha; recusa da marca anti-rollback também vai para o ledger.
+    fn reload_failed(state: &AppState, tenant: &Tenant, source: &'static str, e: &anyhow::Error) {
+        if let Some(r) = e.downcast_ref::<RollbackRejected>() {
+            state.metrics.reload_total.with(&[&tenant.id, "rollback_rejected"]).inc();
+            state.metrics.rollback_rejected_total.with(&[&tenant.id]).inc();
+            state.ledger.append(serde_json::json!({
+                "event": "pack_rollback_rejected", "when": now_rfc3339(), "tenant": tenant.id, "source": source,
+                "id": r.id, "version": r.version, "highest": r.highest, "reason": r.reason,
+            }));
+        } else {
+            state.metrics.reload_total.with(&[&tenant.id, "failure"]).inc();
+        }
+    }
+
+    /// Verifica e instala um novo chip no tenant; em falha o último chip bom continua ativo.
+    fn apply_reload(state: &AppState, tenant: &Tenant, src: &PolicySource, source: &'static str) -> anyhow::Result<()> {
+        if let Err(e) = state.trust.refresh() {
+            eprintln!("trust store reload failed, keeping previous keys: {}", e);
+        }
+        let trust = state.trust.get();
+        let verified = load_and_verify(src, &trust).and_then(|(chip, info)| {
+            let admission = state.highwater.admit(&tenant.id, &info, src.pack_path(), &trust, now_epoch() as u64)?;
+            Ok((chip, info, admission))
+        });
+        let (chip, info, admission) = match verified {
+            Ok(v) => v,
+            Err(e) => {
+                reload_failed(state, tenant, source, &e);
+                return Err(e);
+            }
+        };
+        if let Admission::Downgrade(kids) = &admission {
+            state.ledger.append(serde_json::json!({
+                "event": "pack_downgrade_authorized", "when": now_rfc3339(), "tenant": tenant.id, "source": source,
+                "id": info.id, "version": info.version, "pack": info.blake3, "authorized_by": kids,
+            }));
+        }
+        if let Err(e) = state.highwater.record(&tenant.id, &info, now_epoch()) {
+            eprintln!("failed to persist highwater {}: {}", state.highwater.path(), e);
+        }
+        state.metrics.reload_total.with(&[&tenant.id, "success"]).inc();
+        state.metrics.reload_last_success.with(&[&tenant.id]).set(now_epoch());
+        state.metrics.set_chip_info(&tenant.id, &info.id, &info.version, &chip.version, &info.blake3);
+        tenant.policy.install(LoadedPolicy::new(chip, info, now_epoch(), source));
+        Ok(())
+    }
+
+    /// Polling dos arquivos de cada tenant: recarrega quando o par (yaml, pack) ou o bundle muda.
+    /// Um par inconsistente (ex.: só o YAML copiado) falha na verificação e é
+    /// tentado de novo quando o outro arquivo chegar. Tenants são independentes.
+    /// Mudança no trust store reverifica todos os tenants.
+    async fn watch_policy(state: AppState) {
+        let store_fp = || state.trust.store_path().and_then(|p| std::fs::read(p).ok()).map(|b| *blake3::hash(&b).as_bytes());
+        let mut last_store = store_fp();
+        let mut last: Vec<_> = state.tenants.all().iter()
+            .map(|t| t.source.fingerprint())
+            .collect();
+        let mut tick = tokio::time::interval(Duration::from_secs(state.watch_sec));
+        loop {
+            tick.tick().await;
+            let store = store_fp();
+            let store_changed = store.is_some() && store != last_store;
+            last_store = store;
+            for (t, last) in state.tenants.all().iter().zip(last.iter_mut()) {
+                let current = t.source.fingerprint();
+                if current.is_none() || (current == *last && !store_changed) {
+                    continue;
+                }
+                *last = current;
+                match apply_reload(&state, t, &t.source, "watch") {
+                    Ok(()) => println!("policy reloaded from watch: tenant={} blake3={}", t.id, t.policy.active().info.blake3),
+                    Err(e) => eprintln!("policy watch reload rejected for tenant={}, keeping last known-good: {}", t.id, e),
+                }
+            }
+        }
+    }
+
+    async fn rollback(
+        State(state): State<AppState>,
+        axum::extract::Query(params): Params,
+    ) -> Result<axum::Json<serde_json::Value>, (StatusCode, String)> {
+        let tenant = tenant_param(&state, &params)?;
+        // o anterior passa pela marca anti-rollback como qualquer pack (pack.downgrade.json)
+        let trust = state.trust.get();
+        let checked = tenant.policy.rollback_if(|prev| {
+            state.highwater.admit(&tenant.id, &prev.info, tenant.source.pack_path(), &trust, now_epoch() as u64)
+        });
+        let (active, admission) = match checked {
+            Ok(v) => v,
+            Err(e) => {
+                reload_failed(&state, tenant, "rollback", &e);
+                return Err((StatusCode::CONFLICT, e.to_string()));
+            }
+        };
+        let authorized_by = match &admission {
+            Admission::Downgrade(kids) => kids.clone(),
+            Admission::Forward => vec![],
+        };
+        state.ledger.append(serde_json::json!({
+            "event": "pack_rollback", "when": now_rfc3339(), "tenant": tenant.id,
+            "id": active.info.id, "version": active.info.version, "pack": active.info.blake3, "authorized_by": authorized_by,
+        }));
+        if let Err(e) = state.highwater.record(&tenant.id, &active.info, now_epoch()) {
+            eprintln!("failed to persist highwater {}: {}", state.highwater.path(), e);
+        }
+        state.metrics.reload_total.with(&[&tenant.id, "rollback"]).inc();
+        state.metrics.reload_last_success.with(&[&tenant.id]).set(now_epoch());
+        state.metrics.set_chip_info(&tenant.id, &active.info.id, &active.info.version, &active.chip.version, &active.info.blake3);
+        Ok(axum::Json(serde_json::json!({"ok": true, "tenant": tenant.id, "active": active.summary()})))
+    }
+
+    async fn policy_info(State(state): State<AppState>) -> axum::Json<serde_json::Value> {
+        let tenants: Vec<serde_json::Value> = state.tenants.all().iter().map(|t| serde_json::json!({
+            "tenant": t.id,
+            "source": t.source.summary(),
+            "hosts": t.hosts,
+            "path_prefixes": t.path_prefixes,
+            "active": t.policy.active().summary(),
+            "previous": t.policy.previous().map(|p| p.summary()),
+            "highest": state.highwater.get(&t.id),
+        })).collect();
+        axum::Json(serde_json::json!({
+            "default": state.tenants.default_tenant().id,
+            "tenants": tenants,
+            "trust": state.trust.summary(),
+            "watch_sec": state.watch_sec,
+        }))
     }
 
     async fn panic_on(State(state): State<AppState>, axum::Json(p): axum::Json<PanicReq>) -> Result<String, (StatusCode, String)> {
@@ -149,17 +330,15 @@
         Ok("{\"ok\":true}".into())
     }
 
-    async fn metrics(State(state): State<AppState>) -> String {
-        let allow = *state.allow_total.read();
-        let deny = *state.deny_total.read();
-        let eval_sum = *state.eval_ms_sum.read();
-        let eval_max = *state.eval_ms_max.read();
-        let eval_cnt = *state.eval_count.read();
+    async fn metrics(State(state): State<AppState>) -> ([(axum::http::header::HeaderName, &'static str); 1], String) {
         let panic_active = if now_epoch() <= *state.panic_until.read() { 1 } else { 0 };
-        format!(
-            "policy_allow_total {}\npolicy_deny_total {}\npolicy_eval_ms_sum {:.3}\npolicy_eval_ms_max {:.3}\npolicy_eval_count {}\npanic_active {}\n",
-            allow, deny, eval_sum, eval_max, eval_cnt, panic_active
-        )
+        state.metrics.panic_active.set(panic_active);
+        for t in state.tenants.all() {
+            let stats = t.policy.active().cache.stats();
+            state.metrics.decision_cache_entries.with(&[&t.id]).set(stats.entries as i64);
+            state.metrics.decision_cache_evictions.with(&[&t.id]).set(stats.evictions as i64);
+        }
+        ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], state.metrics.render())
     }
 
     async fn forward(
@@ -170,95 +349,177 @@
         let (parts, body) = req.into_parts();
         let headers = parts.headers.clone();
         let method = parts.method.clone();
-        let body_bytes = axum::body::to_bytes(body, usize::MAX).await
-            .map_err(|e| (StatusCode::BAD_REQUEST, format!("body read error: {}", e)))?;
         let email = headers.get("CF-Access-Authenticated-User-Email").and_then(|v| v.to_str().ok()).unwrap_or("");
         let groups_hdr = headers.get("CF-Access-Groups").and_then(|v| v.to_str().ok()).unwrap_or("");
         let groups: Vec<String> = groups_hdr.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
         let panic_mode = now_epoch() <= *state.panic_until.read();
+        let tenant = state.tenants.resolve(&headers, &format!("/{}", path));
+
+        // sessão elevada: prova do gateway para este mesmo usuário → webauthn
+        let mut auth = policy_engine::AuthCtx { method: "access-passkey".into(), rp_id: "app.ubl.agency".into() };
+        if let (Some(verifier), Some(token)) = (&state.step_up, obligations::step_up_token(&headers)) {
+            match verifier.verify(token, email) {
+                Ok(claims) if !email.is_empty() => {
+                    auth = policy_engine::AuthCtx { method: "webauthn".into(), rp_id: claims.rp_id };
+                    state.metrics.step_up_total.with(&[&tenant.id, "accepted"]).inc();
+                }
+                _ => state.metrics.step_up_total.with(&[&tenant.id, "rejected"]).inc(),
+            }
+        }
 
         let ctx = RequestContext {
             transport: policy_engine::TransportCtx { tls_version: 1.3 },
             mtls: policy_engine::MtlsCtx { verified: true, issuer: "UBL Local CA".into() },
-            auth: policy_engine::AuthCtx { method: "access-passkey".into(), rp_id: "app.ubl.agency".into() },
+            auth,
             user: policy_engine::UserCtx { groups },
             system: policy_engine::SystemCtx { panic_mode },
             who: Some(email.to_string()),
             did: Some(format!("{} /{}", method, path)),
-            req_id: headers.get("CF-Ray").and_then(|v| v.to_str().ok()).map(|s| s.to_string()),
+            req_id: Some(headers.get("CF-Ray").and_then(|v| v.to_str().ok()).map(|s| s.to_string()).unwrap_or_else(local_req_id)),
             req: Some(policy_engine::ReqCtx {
                 path: Some(format!("/{}", path)),
                 method: Some(method.to_string()),
             }),
+            ..Default::default()
         };
 
         let start = std::time::Instant::now();
-        let chip = state.chip.read().clone();
-        let dec = decide(&chip, &ctx);
-        let dt = start.elapsed().as_secs_f64()*1000.0;
-        {
-            *state.eval_ms_sum.write() += dt;
-            if dt > *state.eval_ms_max.read() { *state.eval_ms_max.write() = dt; }
-            *state.eval_count.write() += 1;
+        let active = tenant.policy.active();
+        let (dec, lookup) = active.cache.decide(&active.chip, &ctx);
+        state.metrics.eval_seconds.with(&[&tenant.id]).observe(start.elapsed().as_secs_f64());
+        if lookup != Lookup::Bypass {
+            state.metrics.decision_cache_total.with(&[&tenant.id, if lookup == Lookup::Hit { "hit" } else { "miss" }]).inc();
         }
 
         let hdr_out = HeaderMap::new();
 
+        // minimal ledger line (local file): every decision, including challenge and deny
+        let when = now_rfc3339();
+        let mut line = serde_json::json!({
+            "who": ctx.who, "did": ctx.did, "when": when, "tenant": tenant.id, "pack": active.info.blake3,
+            "decision": dec.decision, "why": dec.why, "trigger": dec.trigger, "chain": dec.chain,
+        });
+
+        if let Some(ch) = &dec.challenge {
+            state.metrics.challenge_total.with(&[&tenant.id, &dec.decision, &dec.trigger]).inc();
+            line["challenge"] = serde_json::json!(ch);
+            state.ledger.append(line);
+            let (status, h, body) = obligations::challenge(ch, &dec.decision, &format!("/{}", path), &ctx.auth.rp_id);
+            return Ok((status, h, Bytes::from(body)));
+        }
         if dec.decision.starts_with("deny") {
-            *state.deny_total.write() += 1;
+            state.metrics.deny_total.with(&[&tenant.id, &dec.decision, &dec.trigger]).inc();
+            state.ledger.append(line);
             return Err((StatusCode::FORBIDDEN, "policy_denied".into()));
         } else {
-            *state.allow_total.write() += 1;
+            state.metrics.allow_total.with(&[&tenant.id, &dec.decision, &dec.trigger]).inc();
         }
 
-        // append minimal ledger line (local file)
-        let when = now_rfc3339();
-        let line = serde_json::json!({
-            "who": ctx.who, "did": ctx.did, "when": when,
-            "decision": dec.decision, "why": dec.why, "trigger": dec.trigger, "chain": dec.chain,
-        }).to_string();
-        let _ = append_ledger(&line);
+        // obrigações da decisão: recusam a request ou reescrevem o que vai ao upstream
+        let mut pass = headers.clone();
+        let content_length = headers.get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let mut enforced = obligations::enforce(&dec.obligations, &obligations::Request {
+            method: &method,
+            path: &format!("/{}", path),
+            content_length,
+            auth_method: &ctx.auth.method,
+        }, &mut pass);
+        // corpo só depois da decisão, lido até o max_body_bytes da obrigação
+        let mut body_bytes = Bytes::new();
+        if enforced.is_ok() {
+            match obligations::read_body(body, obligations::body_limit(&dec.obligations)).await
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("body read error: {}", e)))? {
+                Ok(b) => body_bytes = b,
+                Err(refusal) => enforced = Err(refusal),
+            }
+        }
+
+        if !dec.obligations.is_empty() {
+            line["obligations"] = serde_json::json!(dec.obligations);
+        }
+        if !dec.advice.is_empty() {
+            line["advice"] = serde_json::json!(dec.advice);
+        }
+        match &enforced {
+            Ok(Some(severity)) => line["severity"] = severity.as_str().into(),
+            Ok(None) => {}
+            Err(refusal) => line["refused"] = refusal.kind().into(),
+        }
+        state.ledger.append(line);
+        if let Err(refusal) = enforced {
+            state.metrics.obligation_refused_total.with(&[&tenant.id, refusal.kind()]).inc();
+            let (status, h, body) = refusal.response();
+            return Ok((status, h, Bytes::from(body)));
+        }
 
         // forward upstream (Blueprint 02: roteamento por prefixo)
-        let upstream = if path.starts_with("/core/") || path.starts_with("/admin/") || path.starts_with("/files/") {
-            &state.upstream_core
+        let (upstream_name, upstream) = if path.starts_with("/core/") || path.starts_with("/admin/") || path.starts_with("/files/") {
+            ("core", &state.upstream_core)
         } else if path.starts_with("/webhooks/") {
-            &state.upstream_webhooks
+            ("webhooks", &state.upstream_webhooks)
         } else {
-            &state.upstream_core  // default
+            ("core", &state.upstream_core)  // default
         };
         let url = format!("{}/{}", upstream.trim_end_matches('/'), path);
         let client = reqwest::Client::new();
         let mut fwd = client.request(method.clone(), &url);
-        let mut pass = headers.clone();
-        pass.insert("X-Auth-Method", axum::http::HeaderValue::from_static("access-passkey"));
-        pass.insert("X-Auth-Rpid", axum::http::HeaderValue::from_static("app.ubl.agency"));
+        // identidade (e tenant) só vale se vier do proxy: descarta o que o cliente
+        // mandou (e o que um set_headers tenha tentado pôr)
+        for h in ["X-Who", "X-Auth-Method", "X-Auth-Rpid", "X-Ubl-Advice", tenants::TENANT_HEADER, policy_assertion::HEADER] {
+            pass.remove(h);
+        }
+        if !dec.advice.is_empty() {
+            if let Ok(v) = axum::http::HeaderValue::from_str(&serde_json::to_string(&dec.advice).unwrap_or_default()) {
+                pass.insert("X-Ubl-Advice", v);
+            }
+        }
+        if let Some(signer) = &state.assertion {
+            let claims = AssertionClaims {
+                iss: String::new(), iat: 0, exp: 0,
+                jti: ctx.req_id.clone().unwrap_or_default(),
+                tenant: tenant.id.clone(),
+                decision: dec.decision.clone(),
+                trigger: dec.trigger.clone(),
+                who: ctx.who.clone(),
+                groups: ctx.user.groups.clone(),
+                chip: active.info.blake3.clone(),
+                did: ctx.did.clone(),
+            };
+            let token = signer.sign(claims).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            if let Ok(v) = axum::http::HeaderValue::from_str(&token) {
+                pass.insert(policy_assertion::HEADER, v);
+            }
+        }
+        // como o usuário se autenticou nesta request (webauthn só com prova de step-up)
+        for (h, v) in [("X-Auth-Method", &ctx.auth.method), ("X-Auth-Rpid", &ctx.auth.rp_id)] {
+            if let Ok(v) = axum::http::HeaderValue::from_str(v) {
+                pass.insert(h, v);
+            }
+        }
         // leave CF-* as-is; add condensed groups/email
         if let Some(w) = ctx.who.clone() {
             pass.insert("X-Who", axum::http::HeaderValue::from_str(&w).unwrap_or(axum::http::HeaderValue::from_static("")));
         }
         fwd = fwd.headers(pass).body(body_bytes);
-        let resp = fwd.send().await.map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+        let up_start = std::time::Instant::now();
+        let resp = fwd.send().await.map_err(|e| {
+            state.metrics.upstream_errors_total.with(&[&tenant.id, upstream_name]).inc();
+            (StatusCode::BAD_GATEWAY, e.to_string())
+        })?;
+        state.metrics.upstream_seconds.with(&[&tenant.id, upstream_name]).observe(up_start.elapsed().as_secs_f64());
+        state.metrics.upstream_requests_total.with(&[&tenant.id, upstream_name, resp.status().as_str()]).inc();
         let status = StatusCode::from_u16(resp.status().as_u16()).unwrap();
         let bytes = resp.bytes().await.map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
         Ok((status, hdr_out, bytes))
     }
 
-    fn append_ledger(line: &str) -> std::io::Result<()> {
-        use std::io::Write;
-        let mut obj: serde_json::Value = serde_json::from_str(line).unwrap_or(serde_json::json!({}));
-        let canon = serde_json::to_string(&obj).unwrap_or_else(|_| line.to_string());
-        let hash = blake3::hash(canon.as_bytes());
-        let dig = hex::encode(hash.as_bytes());
-        obj["hash"] = serde_json::Value::String(dig);
-        let p = "/var/log/ubl/flagship-ledger.ndjson";
-        // Criar diretório se não existir
-        if let Some(parent) = std::path::Path::new(p).parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
-        let mut f = std::fs::OpenOptions::new().create(true).append(true).open(p)?;
-        writeln!(f, "{}", serde_json::to_string(&obj).unwrap())?;
-        Ok(())
+    /// id de request quando não há CF-Ray (acesso direto, testes)
+    fn local_req_id() -> String {
+        static SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        let n = SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        format!("pp-{:x}-{:x}", now_epoch(), n)
     }
 
     fn now_epoch() -> i64 {
@@ -0,0 +1,265 @@
+//! Registry de métricas Prometheus (text exposition 0.0.4)
+//! Contadores são atômicos; famílias rotuladas guardam um contador por combinação de labels.
+//! Toda série de política leva o label `tenant`.
+
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Buckets (segundos) para avaliação do chip: sub-milissegundo até 100ms.
+pub const EVAL_BUCKETS: &[f64] = &[0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.1];
+/// Buckets (segundos) para chamadas upstream.
+pub const UPSTREAM_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) { self.0.fetch_add(1, Ordering::Relaxed); }
+    pub fn get(&self) -> u64 { self.0.load(Ordering::Relaxed) }
+}
+
+#[derive(Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    pub fn set(&self, v: i64) { self.0.store(v, Ordering::Relaxed); }
+    pub fn get(&self) -> i64 { self.0.load(Ordering::Relaxed) }
+}
+
+pub struct Histogram {
+    bounds: &'static [f64],
+    buckets: Vec<AtomicU64>, // não-cumulativos; acumulados na renderização
+    count: AtomicU64,
+    sum_bits: AtomicU64,     // f64 em bits, atualizado via CAS
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_bits: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    pub fn observe(&self, v: f64) {
+        if let Some(i) = self.bounds.iter().position(|b| v <= *b) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let mut cur = self.sum_bits.load(Ordering::Relaxed);
+        loop {
+            let next = (f64::from_bits(cur) + v).to_bits();
+            match self.sum_bits.compare_exchange_weak(cur, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => cur = actual,
+            }
+        }
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut acc = 0u64;
+        for (b, n) in self.bounds.iter().zip(&self.buckets) {
+            acc += n.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, with_sep(labels), b, acc);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, with_sep(labels), count);
+        let _ = writeln!(out, "{}_sum{} {}", name, braces(labels), f64::from_bits(self.sum_bits.load(Ordering::Relaxed)));
+        let _ = writeln!(out, "{}_count{} {}", name, braces(labels), count);
+    }
+}
+
+/// Família rotulada: `values` na mesma ordem de `labels`.
+pub struct Family<T> {
+    labels: &'static [&'static str],
+    make: fn() -> T,
+    series: RwLock<BTreeMap<Vec<String>, Arc<T>>>,
+}
+
+impl<T> Family<T> {
+    pub fn new(labels: &'static [&'static str], make: fn() -> T) -> Self {
+        Self { labels, make, series: RwLock::new(BTreeMap::new()) }
+    }
+
+    pub fn with(&self, values: &[&str]) -> Arc<T> {
+        debug_assert_eq!(values.len(), self.labels.len());
+        let key: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        if let Some(s) = self.series.read().get(&key) {
+            return s.clone();
+        }
+        self.series.write().entry(key).or_insert_with(|| Arc::new((self.make)())).clone()
+    }
+
+    fn each(&self, mut f: impl FnMut(String, &T)) {
+        for (values, s) in self.series.read().iter() {
+            let labels = self.labels.iter().zip(values)
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+                .collect::<Vec<_>>()
+                .join(",");
+            f(labels, s);
+        }
+    }
+}
+
+/// Métricas do policy-proxy.
+pub struct Metrics {
+    pub allow_total: Family<Counter>,
+    pub deny_total: Family<Counter>,
+    pub eval_seconds: Family<Histogram>,
+    pub upstream_requests_total: Family<Counter>,
+    pub upstream_seconds: Family<Histogram>,
+    pub upstream_errors_total: Family<Counter>,
+    pub reload_total: Family<Counter>,
+    pub reload_last_success: Family<Gauge>,
+    pub rollback_rejected_total: Family<Counter>,
+    pub decision_cache_total: Family<Counter>,
+    pub obligation_refused_total: Family<Counter>,
+    pub challenge_total: Family<Counter>,
+    pub step_up_total: Family<Counter>,
+    /// Linhas do ledger perdidas (queue_full | write_error | unavailable | closed)
+    pub ledger_dropped_total: Family<Counter>,
+    /// Lidos do cache do chip ativo a cada scrape (zeram no reload)
+    pub decision_cache_entries: Family<Gauge>,
+    pub decision_cache_evictions: Family<Gauge>,
+    pub chip_info: RwLock<BTreeMap<String, Vec<(&'static str, String)>>>,
+    pub panic_active: Gauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            allow_total: Family::new(&["tenant", "decision", "trigger"], Counter::default),
+            deny_total: Family::new(&["tenant", "decision", "trigger"], Counter::default),
+            eval_seconds: Family::new(&["tenant"], || Histogram::new(EVAL_BUCKETS)),
+            upstream_requests_total: Family::new(&["tenant", "upstream", "status"], Counter::default),
+            upstream_seconds: Family::new(&["tenant", "upstream"], || Histogram::new(UPSTREAM_BUCKETS)),
+            upstream_errors_total: Family::new(&["tenant", "upstream"], Counter::default),
+            reload_total: Family::new(&["tenant", "result"], Counter::default),
+            reload_last_success: Family::new(&["tenant"], Gauge::default),
+            rollback_rejected_total: Family::new(&["tenant"], Counter::default),
+            decision_cache_total: Family::new(&["tenant", "result"], Counter::default),
+            obligation_refused_total: Family::new(&["tenant", "kind"], Counter::default),
+            challenge_total: Family::new(&["tenant", "decision", "trigger"], Counter::default),
+            step_up_total: Family::new(&["tenant", "result"], Counter::default),
+            ledger_dropped_total: Family::new(&["reason"], Counter::default),
+            decision_cache_entries: Family::new(&["tenant"], Gauge::default),
+            decision_cache_evictions: Family::new(&["tenant"], Gauge::default),
+            chip_info: RwLock::new(BTreeMap::new()),
+            panic_active: Gauge::default(),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn set_chip_info(&self, tenant: &str, id: &str, version: &str, chip_version: &str, blake3: &str) {
+        self.chip_info.write().insert(tenant.to_string(), vec![
+            ("tenant", tenant.to_string()),
+            ("id", id.to_string()),
+            ("version", version.to_string()),
+            ("chip_version", chip_version.to_string()),
+            ("blake3", blake3.to_string()),
+        ]);
+    }
+
+    /// Renderiza no formato de exposição texto do Prometheus.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "policy_allow_total", "counter", "Requests allowed by the chip");
+        self.allow_total.each(|l, c| { let _ = writeln!(out, "policy_allow_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_deny_total", "counter", "Requests denied by the chip");
+        self.deny_total.each(|l, c| { let _ = writeln!(out, "policy_deny_total{{{}}} {}", l, c.get()); });
+
+        header(&mut out, "policy_eval_duration_seconds", "histogram", "Chip evaluation latency");
+        self.eval_seconds.each(|l, h| h.render(&mut out, "policy_eval_duration_seconds", &l));
+
+        header(&mut out, "policy_upstream_requests_total", "counter", "Upstream responses by status code");
+        self.upstream_requests_total.each(|l, c| { let _ = writeln!(out, "policy_upstream_requests_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_upstream_errors_total", "counter", "Upstream transport errors");
+        self.upstream_errors_total.each(|l, c| { let _ = writeln!(out, "policy_upstream_errors_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_upstream_duration_seconds", "histogram", "Upstream request latency");
+        self.upstream_seconds.each(|l, h| h.render(&mut out, "policy_upstream_duration_seconds", &l));
+
+        header(&mut out, "policy_reload_total", "counter", "Policy reload attempts by result (success | failure | rollback_rejected | rollback)");
+        self.reload_total.each(|l, c| { let _ = writeln!(out, "policy_reload_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_reload_last_success_timestamp_seconds", "gauge", "Unix time of the last successful reload");
+        self.reload_last_success.each(|l, g| { let _ = writeln!(out, "policy_reload_last_success_timestamp_seconds{{{}}} {}", l, g.get()); });
+
+        header(&mut out, "policy_pack_rollback_rejected_total", "counter", "Packs refused for being older than the accepted version");
+        self.rollback_rejected_total.each(|l, c| { let _ = writeln!(out, "policy_pack_rollback_rejected_total{{{}}} {}", l, c.get()); });
+
+        header(&mut out, "policy_obligation_refused_total", "counter", "Allowed requests refused by a decision obligation (methods | max_body_bytes | step_up)");
+        self.obligation_refused_total.each(|l, c| { let _ = writeln!(out, "policy_obligation_refused_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_challenge_total", "counter", "Requests answered with a step-up challenge (401)");
+        self.challenge_total.each(|l, c| { let _ = writeln!(out, "policy_challenge_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_step_up_total", "counter", "Step-up proofs presented, by result (accepted | rejected)");
+        self.step_up_total.each(|l, c| { let _ = writeln!(out, "policy_step_up_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_ledger_dropped_total", "counter", "Ledger lines dropped, by reason (queue_full | write_error | unavailable | closed)");
+        self.ledger_dropped_total.each(|l, c| { let _ = writeln!(out, "policy_ledger_dropped_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_decision_cache_total", "counter", "Decision cache lookups by result (hit | miss)");
+        self.decision_cache_total.each(|l, c| { let _ = writeln!(out, "policy_decision_cache_total{{{}}} {}", l, c.get()); });
+        header(&mut out, "policy_decision_cache_entries", "gauge", "Decisions cached for the active chip");
+        self.decision_cache_entries.each(|l, g| { let _ = writeln!(out, "policy_decision_cache_entries{{{}}} {}", l, g.get()); });
+        header(&mut out, "policy_decision_cache_evictions", "gauge", "Evictions since the active chip was loaded");
+        self.decision_cache_evictions.each(|l, g| { let _ = writeln!(out, "policy_decision_cache_evictions{{{}}} {}", l, g.get()); });
+
+        header(&mut out, "policy_chip_info", "gauge", "Active policy pack and chip per tenant");
+        for info in self.chip_info.read().values() {
+            let l = info.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape(v))).collect::<Vec<_>>().join(",");
+            let _ = writeln!(out, "policy_chip_info{{{}}} 1", l);
+        }
+
+        header(&mut out, "panic_active", "gauge", "Break-glass mode active (1) or not (0)");
+        let _ = writeln!(out, "panic_active {}", self.panic_active.get());
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn with_sep(labels: &str) -> String {
+    if labels.is_empty() { String::new() } else { format!("{},", labels) }
+}
+
+fn braces(labels: &str) -> String {
+    if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) }
+}
+
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let m = Metrics::default();
+        let h = m.eval_seconds.with(&["ubl"]);
+        h.observe(0.00003);
+        h.observe(0.002);
+        h.observe(1.0);
+        let out = m.render();
+        assert!(out.contains("# TYPE policy_eval_duration_seconds histogram"));
+        assert!(out.contains("policy_eval_duration_seconds_bucket{tenant=\"ubl\",le=\"0.00005\"} 1"));
+        assert!(out.contains("policy_eval_duration_seconds_bucket{tenant=\"ubl\",le=\"0.0025\"} 2"));
+        assert!(out.contains("policy_eval_duration_seconds_bucket{tenant=\"ubl\",le=\"+Inf\"} 3"));
+        assert!(out.contains("policy_eval_duration_seconds_count{tenant=\"ubl\"} 3"));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let m = Metrics::default();
+        m.deny_total.with(&["ubl", "deny_invalid_access", "NOT(\"x\")"]).inc();
+        assert!(m.render().contains(r#"policy_deny_total{tenant="ubl",decision="deny_invalid_access",trigger="NOT(\"x\")"} 1"#));
+    }
+}
@@ -0,0 +1,232 @@
+//! Cumpre as obrigações de uma decisão allow antes de encaminhar: método,
+//! tamanho do corpo e step-up recusam a request; cabeçalhos são aplicados na
+//! cópia que vai para o upstream; `log` define a severidade da linha no ledger.
+//! O corpo só é lido depois da decisão, com `max_body_bytes` aplicado na leitura.
+//! Saídas do tipo challenge viram 401 com a URL de step-up do gateway.
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use policy_assertion::step_up;
+use policy_engine::{Challenge, Obligation};
+
+/// Request que não cumpre uma obrigação: vira resposta, não vai ao upstream.
+#[derive(Debug, PartialEq)]
+pub enum Refusal {
+    Method { allow: Vec<String> },
+    TooLarge { max: u64 },
+    StepUp { location: String },
+}
+
+impl Refusal {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Method { .. } => "methods",
+            Self::TooLarge { .. } => "max_body_bytes",
+            Self::StepUp { .. } => "step_up",
+        }
+    }
+
+    pub fn response(&self) -> (StatusCode, HeaderMap, String) {
+        let mut h = HeaderMap::new();
+        let (status, body) = match self {
+            Self::Method { allow } => {
+                if let Ok(v) = HeaderValue::from_str(&allow.join(", ")) {
+                    h.insert(header::ALLOW, v);
+                }
+                (StatusCode::METHOD_NOT_ALLOWED, serde_json::json!({ "error": "method_not_allowed", "allow": allow }))
+            }
+            Self::TooLarge { max } => (StatusCode::PAYLOAD_TOO_LARGE, serde_json::json!({ "error": "body_too_large", "max_body_bytes": max })),
+            Self::StepUp { location } => {
+                if let Ok(v) = HeaderValue::from_str(location) {
+                    h.insert(header::LOCATION, v);
+                }
+                (StatusCode::SEE_OTHER, serde_json::json!({ "error": "step_up_required", "location": location }))
+            }
+        };
+        h.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        (status, h, body.to_string())
+    }
+}
+
+/// Request a encaminhar, como a decisão pediu.
+pub struct Request<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    /// Content-Length declarado; recusa cedo, o limite vale de novo em [`read_body`]
+    pub content_length: Option<u64>,
+    /// `ctx.auth.method`: `webauthn` = sessão já passou pelo step-up
+    pub auth_method: &'a str,
+}
+
+/// Aplica as obrigações em `upstream` (cabeçalhos da request encaminhada)
+/// e devolve a severidade pedida por `log`, se houver.
+pub fn enforce(obligations: &[Obligation], req: &Request, upstream: &mut HeaderMap) -> Result<Option<String>, Refusal> {
+    let mut severity = None;
+    for o in obligations {
+        match o {
+            Obligation::Methods(allow) if !allow.iter().any(|m| m == req.method.as_str()) => {
+                return Err(Refusal::Method { allow: allow.clone() });
+            }
+            Obligation::MaxBodyBytes(max) if req.content_length.is_some_and(|n| n > *max) => {
+                return Err(Refusal::TooLarge { max: *max });
+            }
+            Obligation::StepUp { url } if req.auth_method != "webauthn" => {
+                return Err(Refusal::StepUp { location: return_to(url, req.path) });
+            }
+            Obligation::SetHeaders(set) => {
+                for (k, v) in set {
+                    if let (Ok(k), Ok(v)) = (HeaderName::try_from(k.as_str()), HeaderValue::from_str(v)) {
+                        upstream.insert(k, v);
+                    }
+                }
+            }
+            Obligation::RedactHeaders(names) => {
+                for k in names {
+                    upstream.remove(k.as_str());
+                }
+            }
+            Obligation::Log { severity: s } => severity = Some(s.clone()),
+            _ => {}
+        }
+    }
+    Ok(severity)
+}
+
+/// Menor `max_body_bytes` da decisão.
+pub fn body_limit(obligations: &[Obligation]) -> Option<u64> {
+    obligations.iter().filter_map(|o| match o {
+        Obligation::MaxBodyBytes(max) => Some(*max),
+        _ => None,
+    }).min()
+}
+
+/// Lê o corpo parando em `max` bytes: chunked ou Content-Length mentiroso
+/// não passam, e nada além do limite fica em memória. Erro de fora = falha
+/// de leitura; de dentro = recusa da obrigação.
+pub async fn read_body(body: Body, max: Option<u64>) -> Result<Result<Bytes, Refusal>, axum::BoxError> {
+    let Some(max) = max else { return body.collect().await.map(|c| Ok(c.to_bytes())).map_err(Into::into) };
+    match Limited::new(body, usize::try_from(max).unwrap_or(usize::MAX)).collect().await {
+        Ok(c) => Ok(Ok(c.to_bytes())),
+        Err(e) if e.is::<LengthLimitError>() => Ok(Err(Refusal::TooLarge { max })),
+        Err(e) => Err(e),
+    }
+}
+
+/// 401 estruturado: onde fazer o step-up e como trazer a prova de volta.
+pub fn challenge(ch: &Challenge, decision: &str, path: &str, rp_id: &str) -> (StatusCode, HeaderMap, String) {
+    let url = return_to(&ch.url, path);
+    let mut h = HeaderMap::new();
+    if let Ok(v) = HeaderValue::from_str(&format!("UBL-StepUp url=\"{}\"", url)) {
+        h.insert(header::WWW_AUTHENTICATE, v);
+    }
+    h.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let body = serde_json::json!({
+        "error": "step_up_required",
+        "decision": decision,
+        "challenge": {
+            "type": "webauthn",
+            "url": url,
+            "rp_id": rp_id,
+            "proof": { "cookie": step_up::COOKIE, "header": step_up::HEADER },
+        },
+    });
+    (StatusCode::UNAUTHORIZED, h, body.to_string())
+}
+
+/// Prova de step-up da request: header `X-Ubl-Step-Up` ou cookie `ubl_step_up`.
+pub fn step_up_token(headers: &HeaderMap) -> Option<&str> {
+    if let Some(v) = headers.get(step_up::HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(v.trim());
+    }
+    headers.get_all(header::COOKIE).iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|c| c.trim().strip_prefix(step_up::COOKIE)?.strip_prefix('='))
+}
+
+fn return_to(url: &str, path: &str) -> String {
+    let sep = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}return_to={}", url, sep, encode(path))
+}
+
+/// Percent-encoding do caminho para o `return_to`.
+fn encode(path: &str) -> String {
+    path.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn req<'a>(method: &'a Method, content_length: u64, auth_method: &'a str) -> Request<'a> {
+        Request { method, path: "/admin/x y", content_length: Some(content_length), auth_method }
+    }
+
+    #[test]
+    fn refuses_what_the_request_cannot_meet() {
+        let read_only = [Obligation::Methods(vec!["GET".into(), "HEAD".into()]), Obligation::MaxBodyBytes(4)];
+        let mut h = HeaderMap::new();
+        assert_eq!(enforce(&read_only, &req(&Method::GET, 4, ""), &mut h), Ok(None));
+        let refused = enforce(&read_only, &req(&Method::POST, 0, ""), &mut h).unwrap_err();
+        assert_eq!(refused.response().0, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(refused.response().1[header::ALLOW], "GET, HEAD");
+        assert_eq!(enforce(&read_only, &req(&Method::GET, 5, ""), &mut h), Err(Refusal::TooLarge { max: 4 }));
+
+        let step_up = [Obligation::StepUp { url: "/auth/passkey/register".into() }];
+        let Err(refused) = enforce(&step_up, &req(&Method::GET, 0, "access-passkey"), &mut h) else { panic!("expected step-up") };
+        assert_eq!(refused.response().1[header::LOCATION], "/auth/passkey/register?return_to=/admin/x%20y");
+        assert!(enforce(&step_up, &req(&Method::GET, 0, "webauthn"), &mut h).is_ok());
+    }
+
+    /// Sem Content-Length (chunked) ou com um que mente, o limite vale na leitura.
+    #[tokio::test]
+    async fn body_limit_is_enforced_while_reading() {
+        let limits = [Obligation::MaxBodyBytes(8), Obligation::MaxBodyBytes(4)];
+        assert_eq!(body_limit(&limits), Some(4));
+        let mut h = HeaderMap::new();
+        let chunked = Request { method: &Method::POST, path: "/x", content_length: None, auth_method: "" };
+        assert_eq!(enforce(&limits, &chunked, &mut h), Ok(None));
+        assert_eq!(enforce(&limits, &req(&Method::POST, 2, ""), &mut h), Ok(None));
+
+        assert_eq!(read_body(Body::from("xxxx"), Some(4)).await.unwrap(), Ok(Bytes::from_static(b"xxxx")));
+        assert_eq!(read_body(Body::from("xxxxxx"), Some(4)).await.unwrap(), Err(Refusal::TooLarge { max: 4 }));
+        assert_eq!(read_body(Body::from("xxxxxx"), None).await.unwrap().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn challenge_points_to_the_gateway_and_reads_the_proof_back() {
+        let ch = Challenge { url: "/auth/passkey/login".into() };
+        let (status, h, body) = challenge(&ch, "challenge_admin_step_up", "/admin/x", "app.ubl.agency");
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(h[header::WWW_AUTHENTICATE], "UBL-StepUp url=\"/auth/passkey/login?return_to=/admin/x\"");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["challenge"]["proof"]["cookie"], "ubl_step_up");
+
+        let mut h = HeaderMap::new();
+        assert_eq!(step_up_token(&h), None);
+        h.insert(header::COOKIE, HeaderValue::from_static("sid=1; ubl_step_up=a.b.c"));
+        assert_eq!(step_up_token(&h), Some("a.b.c"));
+        h.insert(step_up::HEADER, HeaderValue::from_static("x.y.z"));
+        assert_eq!(step_up_token(&h), Some("x.y.z"));
+    }
+
+    #[test]
+    fn rewrites_upstream_headers_and_reports_severity() {
+        let obligations = [
+            Obligation::SetHeaders(BTreeMap::from([("X-Ubl-Mode".to_string(), "read-only".to_string())])),
+            Obligation::RedactHeaders(vec!["Cookie".into()]),
+            Obligation::Log { severity: "high".into() },
+        ];
+        let mut h = HeaderMap::new();
+        h.insert(header::COOKIE, HeaderValue::from_static("sid=1"));
+        h.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        assert_eq!(enforce(&obligations, &req(&Method::PUT, 10, ""), &mut h), Ok(Some("high".into())));
+        assert_eq!(h.get("x-ubl-mode").unwrap(), "read-only");
+        assert!(h.get(header::COOKIE).is_none() && h.get(header::ACCEPT).is_some());
+    }
+}
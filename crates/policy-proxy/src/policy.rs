@@ -0,0 +1,308 @@
+//! Carregamento/verificação do chip e slot ativo/anterior (rollback)
+
+use parking_lot::RwLock;
+use policy_engine::{DecisionCache, SemanticChip};
+use policy_pack::{AnyPack, Bundle, TrustSet, TrustStore, TrustedKey};
+use serde::Serialize;
+use std::{fs, sync::Arc};
+
+/// Campos do pack.json expostos em métricas e em /_policy
+#[derive(Debug, Clone, Serialize)]
+pub struct PackInfo {
+    pub id: String,
+    pub version: String,
+    pub blake3: String,
+    pub format: u8,
+    pub kid: Option<String>,
+    /// kids confiáveis que assinaram (M-of-N)
+    pub signed_by: Vec<String>,
+    pub created_at: Option<u64>,
+}
+
+/// Chip verificado + metadados do pack que o assinou
+pub struct LoadedPolicy {
+    pub chip: SemanticChip,
+    pub info: PackInfo,
+    pub loaded_at: i64,
+    pub source: &'static str, // startup | manual | watch | signal
+    /// Decisões deste chip; reload instala outro LoadedPolicy, com cache vazio
+    pub cache: DecisionCache,
+}
+
+impl LoadedPolicy {
+    /// POLICY_DECISION_CACHE: máximo de decisões em cache por chip (padrão 0 = desligado)
+    pub fn new(chip: SemanticChip, info: PackInfo, loaded_at: i64, source: &'static str) -> Self {
+        let capacity = std::env::var("POLICY_DECISION_CACHE").ok().and_then(|v| v.parse().ok()).unwrap_or(0);
+        let cache = DecisionCache::new(&chip, capacity);
+        Self { chip, info, loaded_at, source, cache }
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.info.id,
+            "version": self.info.version,
+            "blake3": self.info.blake3,
+            "format": self.info.format,
+            "kid": self.info.kid,
+            "signed_by": self.info.signed_by,
+            "chip_version": self.chip.version,
+            "meta": self.chip.meta,
+            "loaded_at": self.loaded_at,
+            "source": self.source,
+            "decision_cache": self.cache.stats(),
+        })
+    }
+}
+
+/// Último chip bom conhecido + o anterior verificado (para /_rollback)
+pub struct PolicySlot {
+    active: RwLock<Arc<LoadedPolicy>>,
+    previous: RwLock<Option<Arc<LoadedPolicy>>>,
+}
+
+impl PolicySlot {
+    pub fn new(initial: LoadedPolicy) -> Self {
+        Self { active: RwLock::new(Arc::new(initial)), previous: RwLock::new(None) }
+    }
+
+    pub fn active(&self) -> Arc<LoadedPolicy> {
+        self.active.read().clone()
+    }
+
+    pub fn previous(&self) -> Option<Arc<LoadedPolicy>> {
+        self.previous.read().clone()
+    }
+
+    /// Instala um chip já verificado; o ativo vira o anterior.
+    pub fn install(&self, next: LoadedPolicy) {
+        let mut active = self.active.write();
+        let old = std::mem::replace(&mut *active, Arc::new(next));
+        *self.previous.write() = Some(old);
+    }
+
+    /// Troca ativo ↔ anterior. Chamar de novo desfaz o rollback.
+    #[cfg(test)]
+    pub fn rollback(&self) -> anyhow::Result<Arc<LoadedPolicy>> {
+        self.rollback_if(|_| Ok(())).map(|(active, ())| active)
+    }
+
+    /// Troca ativo ↔ anterior se `check` aceitar o anterior (ex.: a marca
+    /// anti-rollback); os dois ficam travados durante a checagem.
+    pub fn rollback_if<T>(&self, check: impl FnOnce(&LoadedPolicy) -> anyhow::Result<T>) -> anyhow::Result<(Arc<LoadedPolicy>, T)> {
+        let mut active = self.active.write();
+        let mut previous = self.previous.write();
+        let prev = previous.as_ref().ok_or_else(|| anyhow::anyhow!("no previous verified policy"))?;
+        let checked = check(prev)?;
+        let prev = previous.take().expect("checked above");
+        let old = std::mem::replace(&mut *active, prev);
+        *previous = Some(old);
+        Ok((active.clone(), checked))
+    }
+}
+
+/// De onde vem o chip de um tenant
+#[derive(Debug, Clone)]
+pub enum PolicySource {
+    /// POLICY_YAML + POLICY_PACK, com blake3 cruzado
+    Files { yaml: String, pack: String },
+    /// POLICY_BUNDLE: pack + chip num arquivo só (troca atômica por rename)
+    Bundle(String),
+}
+
+impl PolicySource {
+    /// Caminhos da sombra (`?stage=next`): pack.next.json + x.next.yaml, ou x.next.json.
+    pub fn staged(&self, stage: &str) -> Self {
+        match self {
+            Self::Files { yaml, pack } => Self::Files {
+                yaml: yaml.replace(".yaml", &format!(".{}.yaml", stage)).replace(".yml", &format!(".{}.yml", stage)),
+                pack: pack.replace("pack.json", &format!("pack.{}.json", stage)),
+            },
+            Self::Bundle(path) => Self::Bundle(match path.strip_suffix(".json") {
+                Some(stem) => format!("{}.{}.json", stem, stage),
+                None => format!("{}.{}", path, stage),
+            }),
+        }
+    }
+
+    /// Arquivo com as assinaturas (o `pack.downgrade.json` fica ao lado dele).
+    pub fn pack_path(&self) -> &str {
+        match self {
+            Self::Files { pack, .. } => pack,
+            Self::Bundle(path) => path,
+        }
+    }
+
+    /// BLAKE3 do conteúdo; `None` se algum arquivo não puder ser lido.
+    pub fn fingerprint(&self) -> Option<[u8; 32]> {
+        let mut h = blake3::Hasher::new();
+        match self {
+            Self::Files { yaml, pack } => {
+                h.update(&fs::read(yaml).ok()?);
+                h.update(&fs::read(pack).ok()?);
+            }
+            Self::Bundle(path) => {
+                h.update(&fs::read(path).ok()?);
+            }
+        }
+        Some(*h.finalize().as_bytes())
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        match self {
+            Self::Files { yaml, pack } => serde_json::json!({"yaml": yaml, "pack": pack}),
+            Self::Bundle(path) => serde_json::json!({"bundle": path}),
+        }
+    }
+}
+
+/// Trust set ativo. Com POLICY_TRUST_STORE (JSON com kid, janela e revoked) o
+/// arquivo é relido a cada reload, então rotação/revogação não exige redeploy.
+/// Sem ele: POLICY_TRUSTED_PUBKEYS_PEM_B64 (lista separada por vírgula) ou
+/// POLICY_PUBKEY_PEM_B64. POLICY_SIGNATURE_THRESHOLD sobrescreve o threshold.
+pub struct Trust {
+    store_path: Option<String>,
+    threshold: Option<usize>,
+    active: RwLock<Arc<TrustSet>>,
+}
+
+impl Trust {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let threshold = match std::env::var("POLICY_SIGNATURE_THRESHOLD") {
+            Ok(v) => Some(v.parse().map_err(|_| anyhow::anyhow!("invalid POLICY_SIGNATURE_THRESHOLD: {}", v))?),
+            Err(_) => None,
+        };
+        let store_path = std::env::var("POLICY_TRUST_STORE").ok();
+        let set = match &store_path {
+            Some(path) => TrustStore::from_file(path)?.trust_set(threshold)?,
+            None => {
+                let list = std::env::var("POLICY_TRUSTED_PUBKEYS_PEM_B64")
+                    .or_else(|_| std::env::var("POLICY_PUBKEY_PEM_B64"))
+                    .map_err(|_| anyhow::anyhow!("set POLICY_TRUST_STORE, POLICY_TRUSTED_PUBKEYS_PEM_B64 or POLICY_PUBKEY_PEM_B64"))?;
+                let keys = list.split(',')
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|b64| policy_pack::public_key_from_pem_b64(b64).map(TrustedKey::new))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                TrustSet::new(keys, threshold.unwrap_or(1))?
+            }
+        };
+        Ok(Self { store_path, threshold, active: RwLock::new(Arc::new(set)) })
+    }
+
+    pub fn get(&self) -> Arc<TrustSet> {
+        self.active.read().clone()
+    }
+
+    pub fn store_path(&self) -> Option<&str> {
+        self.store_path.as_deref()
+    }
+
+    /// Relê o trust store (se houver); em erro o trust set anterior continua valendo.
+    pub fn refresh(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.store_path {
+            let set = TrustStore::from_file(path)?.trust_set(self.threshold)?;
+            *self.active.write() = Arc::new(set);
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        let set = self.get();
+        serde_json::json!({
+            "store": self.store_path,
+            "threshold": set.threshold(),
+            "keys": set.keys().iter().map(|k| serde_json::json!({
+                "kid": k.kid,
+                "not_before": k.not_before,
+                "not_after": k.not_after,
+                "revoked": k.revoked,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Verifica o pack (v2; v1 só leitura) ou bundle contra o trust set e o YAML, e compila o chip.
+pub fn load_and_verify(src: &PolicySource, trust: &TrustSet) -> anyhow::Result<(SemanticChip, PackInfo)> {
+    let now = policy_pack::now_epoch();
+    let (yaml, verified) = match src {
+        PolicySource::Files { yaml, pack } => {
+            let yaml = fs::read_to_string(yaml)?;
+            let verified = AnyPack::from_json(&fs::read_to_string(pack)?)?.verify(trust, now)?;
+            verified.check_chip(yaml.as_bytes())?;
+            (yaml, verified)
+        }
+        PolicySource::Bundle(path) => {
+            let bundle = Bundle::from_json(&fs::read_to_string(path)?)?;
+            let verified = bundle.verify(trust, now)?;
+            (bundle.chip, verified)
+        }
+    };
+    let chip = SemanticChip::from_yaml(&yaml)?;
+    verified.check_chip_version(&chip.version)?;
+    Ok((chip, PackInfo {
+        id: verified.id,
+        version: verified.version,
+        blake3: verified.blake3,
+        format: verified.format,
+        kid: verified.kid,
+        signed_by: verified.signed_by,
+        created_at: verified.created_at,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded(blake3: &str) -> LoadedPolicy {
+        loaded_version(blake3, "1")
+    }
+
+    fn loaded_version(blake3: &str, version: &str) -> LoadedPolicy {
+        let chip = SemanticChip::from_yaml("version: tdln-chip/0.3\npolicies: []\nwiring: []\noutputs: []\n").unwrap();
+        let info = PackInfo { id: "t".into(), version: version.into(), blake3: blake3.into(), format: 2, kid: None, signed_by: vec![], created_at: None };
+        LoadedPolicy::new(chip, info, 0, "startup")
+    }
+
+    #[test]
+    fn rollback_swaps_active_and_previous() {
+        let slot = PolicySlot::new(loaded("a"));
+        assert!(slot.rollback().is_err());
+        slot.install(loaded("b"));
+        assert_eq!(slot.active().info.blake3, "b");
+        assert_eq!(slot.rollback().unwrap().info.blake3, "a");
+        assert_eq!(slot.previous().unwrap().info.blake3, "b");
+        assert_eq!(slot.rollback().unwrap().info.blake3, "b");
+    }
+
+    /// Voltar ao chip anterior é um downgrade: sem `pack.downgrade.json` a marca barra.
+    #[test]
+    fn rollback_respects_the_highwater_mark() {
+        use crate::highwater::{downgrade_path, Admission, HighWater};
+        use ed25519_dalek::SigningKey;
+        use policy_pack::{kid_for, DowngradeAuth};
+
+        let dir = std::env::temp_dir().join(format!("policy-rollback-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pack_path = dir.join("pack.json").to_string_lossy().to_string();
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let trust = TrustSet::new(vec![TrustedKey::new(key.verifying_key())], 1).unwrap();
+        let hw = HighWater::load(dir.join("highwater.json").to_string_lossy().to_string()).unwrap();
+
+        let slot = PolicySlot::new(loaded_version("a", "1.0.0"));
+        slot.install(loaded_version("b", "2.0.0"));
+        hw.record("ubl", &slot.active().info, 0).unwrap();
+        let admit = |p: &LoadedPolicy| hw.admit("ubl", &p.info, &pack_path, &trust, 100);
+        let err = slot.rollback_if(admit).err().unwrap();
+        assert!(err.downcast_ref::<crate::highwater::RollbackRejected>().is_some(), "{}", err);
+        assert_eq!(slot.active().info.blake3, "b");
+
+        let mut auth = DowngradeAuth::new("t".into(), "1.0.0".into(), "a".into(), 90, 200);
+        auth.sign(&key, kid_for(&key.verifying_key())).unwrap();
+        fs::write(downgrade_path(&pack_path), serde_json::to_string(&auth).unwrap()).unwrap();
+        let (active, admission) = slot.rollback_if(admit).unwrap();
+        assert_eq!(active.info.blake3, "a");
+        assert!(matches!(admission, Admission::Downgrade(_)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,259 @@
+//! Multitenant: um chip assinado por tenant, escolhido por request
+//! Ordem de resolução: Host → X-Ubl-Tenant (só com `trust_tenant_header`) → prefixo de path → default.
+//! O header vem do cliente e não é autenticado: por padrão é ignorado (e o
+//! proxy o remove antes de encaminhar); só ligue atrás de uma borda que o
+//! sobrescreve, e mesmo assim ele não vence o Host.
+
+use axum::http::HeaderMap;
+use serde::Deserialize;
+use policy_pack::TrustSet;
+use std::sync::Arc;
+
+use crate::highwater::{Admission, HighWater};
+use crate::policy::{LoadedPolicy, PolicySlot, PolicySource, load_and_verify};
+
+pub const TENANT_HEADER: &str = "X-Ubl-Tenant";
+
+/// Arquivo POLICY_TENANTS (YAML)
+#[derive(Debug, Deserialize)]
+pub struct TenantsConfig {
+    pub default: String,
+    pub tenants: Vec<TenantConfig>,
+    /// Aceita X-Ubl-Tenant (abaixo do Host); padrão desligado
+    #[serde(default)]
+    pub trust_tenant_header: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TenantConfig {
+    pub id: String,
+    #[serde(default)]
+    pub policy_yaml: Option<String>,
+    #[serde(default)]
+    pub policy_pack: Option<String>,
+    /// Alternativa a policy_yaml + policy_pack
+    #[serde(default)]
+    pub policy_bundle: Option<String>,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub path_prefixes: Vec<String>,
+}
+
+pub struct Tenant {
+    pub id: String,
+    pub source: PolicySource,
+    pub hosts: Vec<String>,
+    pub path_prefixes: Vec<String>,
+    pub policy: PolicySlot,
+}
+
+pub struct Tenants {
+    list: Vec<Arc<Tenant>>,
+    default: usize,
+    trust_header: bool,
+}
+
+impl TenantsConfig {
+    pub fn from_file(path: &str) -> anyhow::Result<Self> {
+        let cfg: TenantsConfig = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+        cfg.validate().map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        Ok(cfg)
+    }
+
+    /// ids, hosts e prefixos únicos: com repetição o primeiro venceria calado
+    /// (e dois tenants de mesmo id dividiriam a marca anti-rollback).
+    fn validate(&self) -> anyhow::Result<()> {
+        if !self.tenants.iter().any(|t| t.id == self.default) {
+            anyhow::bail!("default tenant '{}' not declared", self.default);
+        }
+        let mut ids: Vec<&str> = vec![];
+        let mut hosts: Vec<(String, &str)> = vec![];
+        let mut prefixes: Vec<(&str, &str)> = vec![];
+        for t in &self.tenants {
+            if ids.contains(&t.id.as_str()) {
+                anyhow::bail!("tenant {} declared twice", t.id);
+            }
+            ids.push(&t.id);
+            for h in &t.hosts {
+                let h = h.to_ascii_lowercase();
+                if let Some((_, other)) = hosts.iter().find(|(o, _)| *o == h) {
+                    anyhow::bail!("host {} claimed by tenants {} and {}", h, other, t.id);
+                }
+                hosts.push((h, &t.id));
+            }
+            for p in &t.path_prefixes {
+                if let Some((_, other)) = prefixes.iter().find(|(o, _)| o == p) {
+                    anyhow::bail!("path prefix {} claimed by tenants {} and {}", p, other, t.id);
+                }
+                prefixes.push((p, &t.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Modo legado: um único tenant a partir de POLICY_BUNDLE ou POLICY_YAML/POLICY_PACK.
+    pub fn single(id: &str, source: PolicySource) -> Self {
+        let (policy_yaml, policy_pack, policy_bundle) = match source {
+            PolicySource::Files { yaml, pack } => (Some(yaml), Some(pack), None),
+            PolicySource::Bundle(path) => (None, None, Some(path)),
+        };
+        Self {
+            default: id.to_string(),
+            tenants: vec![TenantConfig { id: id.to_string(), policy_yaml, policy_pack, policy_bundle, hosts: vec![], path_prefixes: vec![] }],
+            trust_tenant_header: false,
+        }
+    }
+}
+
+impl TenantConfig {
+    fn source(&self) -> anyhow::Result<PolicySource> {
+        match (&self.policy_bundle, &self.policy_yaml, &self.policy_pack) {
+            (Some(bundle), None, None) => Ok(PolicySource::Bundle(bundle.clone())),
+            (None, Some(yaml), Some(pack)) => Ok(PolicySource::Files { yaml: yaml.clone(), pack: pack.clone() }),
+            _ => anyhow::bail!("tenant {}: set either policy_bundle or policy_yaml + policy_pack", self.id),
+        }
+    }
+}
+
+impl Tenants {
+    /// Carrega e verifica o pack de cada tenant; qualquer falha (inclusive
+    /// rollback sem autorização) aborta o boot.
+    pub fn load(cfg: TenantsConfig, trust: &TrustSet, highwater: &HighWater, now: i64) -> anyhow::Result<Self> {
+        let mut list = Vec::with_capacity(cfg.tenants.len());
+        for t in cfg.tenants {
+            let source = t.source()?;
+            let (chip, info) = load_and_verify(&source, trust)
+                .map_err(|e| anyhow::anyhow!("tenant {}: {}", t.id, e))?;
+            match highwater.admit(&t.id, &info, source.pack_path(), trust, now as u64)
+                .map_err(|e| anyhow::anyhow!("tenant {}: {}", t.id, e))?
+            {
+                Admission::Forward => {}
+                Admission::Downgrade(kids) => eprintln!("tenant {}: authorized downgrade to {}@{} by {}", t.id, info.id, info.version, kids.join(", ")),
+            }
+            if let Some(declared) = chip.tenant().filter(|d| *d != t.id) {
+                eprintln!("tenant {}: chip {} declares meta.tenant {}", t.id, info.id, declared);
+            }
+            highwater.record(&t.id, &info, now)?;
+            list.push(Arc::new(Tenant {
+                id: t.id,
+                source,
+                hosts: t.hosts.into_iter().map(|h| h.to_ascii_lowercase()).collect(),
+                path_prefixes: t.path_prefixes,
+                policy: PolicySlot::new(LoadedPolicy::new(chip, info, now, "startup")),
+            }));
+        }
+        let default = list.iter().position(|t| t.id == cfg.default)
+            .ok_or_else(|| anyhow::anyhow!("default tenant '{}' not declared", cfg.default))?;
+        Ok(Self { list, default, trust_header: cfg.trust_tenant_header })
+    }
+
+    pub fn all(&self) -> &[Arc<Tenant>] {
+        &self.list
+    }
+
+    pub fn default_tenant(&self) -> &Arc<Tenant> {
+        &self.list[self.default]
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Arc<Tenant>> {
+        self.list.iter().find(|t| t.id == id)
+    }
+
+    /// `?tenant=` dos endpoints administrativos; ausente = tenant default.
+    pub fn by_param(&self, id: Option<&String>) -> Option<&Arc<Tenant>> {
+        match id {
+            Some(id) => self.get(id),
+            None => Some(self.default_tenant()),
+        }
+    }
+
+    /// Resolve o tenant da request. `path` já com `/` inicial.
+    pub fn resolve(&self, headers: &HeaderMap, path: &str) -> &Arc<Tenant> {
+        if let Some(host) = headers.get("Host").and_then(|v| v.to_str().ok()) {
+            let host = host.split(':').next().unwrap_or("").to_ascii_lowercase();
+            if let Some(t) = self.list.iter().find(|t| t.hosts.contains(&host)) {
+                return t;
+            }
+        }
+        if self.trust_header {
+            if let Some(t) = headers.get(TENANT_HEADER).and_then(|v| v.to_str().ok()).and_then(|id| self.get(id.trim())) {
+                return t;
+            }
+        }
+        if let Some(t) = self.list.iter().find(|t| t.path_prefixes.iter().any(|p| path.starts_with(p.as_str()))) {
+            return t;
+        }
+        self.default_tenant()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn tenant(id: &str, hosts: &[&str], prefixes: &[&str]) -> Arc<Tenant> {
+        let chip = policy_engine::SemanticChip::from_yaml("version: tdln-chip/0.3\npolicies: []\nwiring: []\noutputs: []\n").unwrap();
+        let info = crate::policy::PackInfo { id: id.into(), version: "1".into(), blake3: String::new(), format: 2, kid: None, signed_by: vec![], created_at: None };
+        Arc::new(Tenant {
+            id: id.into(),
+            source: PolicySource::Bundle(String::new()),
+            hosts: hosts.iter().map(|s| s.to_string()).collect(),
+            path_prefixes: prefixes.iter().map(|s| s.to_string()).collect(),
+            policy: PolicySlot::new(LoadedPolicy::new(chip, info, 0, "startup")),
+        })
+    }
+
+    fn tenants(trust_header: bool) -> Tenants {
+        Tenants {
+            list: vec![tenant("ubl", &["api.ubl.agency"], &[]), tenant("voulezvous", &["voulezvous.tv"], &["/vvz/"])],
+            default: 0,
+            trust_header,
+        }
+    }
+
+    #[test]
+    fn resolution_order() {
+        let tenants = tenants(true);
+        let mut h = HeaderMap::new();
+        assert_eq!(tenants.resolve(&h, "/core/x").id, "ubl");
+        assert_eq!(tenants.resolve(&h, "/vvz/x").id, "voulezvous");
+        h.insert(TENANT_HEADER, HeaderValue::from_static("voulezvous"));
+        assert_eq!(tenants.resolve(&h, "/core/x").id, "voulezvous");
+        h.insert(TENANT_HEADER, HeaderValue::from_static("unknown"));
+        assert_eq!(tenants.resolve(&h, "/vvz/x").id, "voulezvous");
+        h.insert("Host", HeaderValue::from_static("VoulezVous.tv:443"));
+        assert_eq!(tenants.resolve(&h, "/core/x").id, "voulezvous");
+    }
+
+    /// Cliente escolhendo o chip mais permissivo: o header não vence o Host,
+    /// e sem `trust_tenant_header` nem é lido.
+    #[test]
+    fn spoofed_tenant_header_does_not_override_host() {
+        let mut h = HeaderMap::new();
+        h.insert("Host", HeaderValue::from_static("voulezvous.tv"));
+        h.insert(TENANT_HEADER, HeaderValue::from_static("ubl"));
+        assert_eq!(tenants(true).resolve(&h, "/vvz/x").id, "voulezvous");
+        assert_eq!(tenants(false).resolve(&h, "/vvz/x").id, "voulezvous");
+
+        let mut h = HeaderMap::new();
+        h.insert(TENANT_HEADER, HeaderValue::from_static("voulezvous"));
+        assert_eq!(tenants(false).resolve(&h, "/core/x").id, "ubl");
+    }
+
+    #[test]
+    fn config_rejects_duplicate_ids_hosts_and_prefixes() {
+        let cfg = |tenants: &str| serde_yaml::from_str::<TenantsConfig>(&format!("default: ubl\ntenants:\n{}", tenants)).unwrap().validate();
+        let ubl = "  - { id: ubl, policy_bundle: a.json, hosts: [api.ubl.agency], path_prefixes: [/ubl/] }\n";
+        assert!(cfg(&format!("{}  - {{ id: vvz, policy_bundle: b.json, hosts: [voulezvous.tv], path_prefixes: [/vvz/] }}\n", ubl)).is_ok());
+
+        let err = cfg(&format!("{}  - {{ id: ubl, policy_bundle: b.json }}\n", ubl)).unwrap_err();
+        assert!(err.to_string().contains("declared twice"), "{}", err);
+        let err = cfg(&format!("{}  - {{ id: vvz, policy_bundle: b.json, hosts: [API.ubl.agency] }}\n", ubl)).unwrap_err();
+        assert!(err.to_string().contains("host api.ubl.agency claimed by tenants ubl and vvz"), "{}", err);
+        let err = cfg(&format!("{}  - {{ id: vvz, policy_bundle: b.json, path_prefixes: [/ubl/] }}\n", ubl)).unwrap_err();
+        assert!(err.to_string().contains("path prefix /ubl/"), "{}", err);
+        assert!(cfg("  - { id: vvz, policy_bundle: b.json }\n").is_err());
+    }
+}
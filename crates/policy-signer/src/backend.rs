@@ -0,0 +1,352 @@
+//! Backends de assinatura: arquivo PKCS#8 (PEM/DER, cifrado ou não),
+//! chave Ed25519 no ssh-agent e comando externo (HSM/KMS, vault…).
+//! Todos entregam um `policy_pack::Signer`; a chave privada só precisa
+//! estar em disco, em claro, no primeiro caso.
+
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use policy_pack::{kid_for, Signer};
+use std::fs;
+use std::io::{Read, Write};
+use std::process::Stdio;
+
+/// Passphrase do PKCS#8 cifrado quando não vem `--passphrase-file`
+pub const PASSPHRASE_ENV: &str = "POLICY_SIGNER_PASSPHRASE";
+
+/// Opções de chave comuns a sign, cosign e authorize-downgrade.
+pub fn args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("privkey_pem")
+            .long("privkey_pem")
+            .value_name("FILE")
+            .help("Ed25519 private key, PKCS#8 PEM or DER (encrypted PKCS#8 needs a passphrase)"),
+    )
+    .arg(
+        Arg::new("passphrase_file")
+            .long("passphrase-file")
+            .value_name("FILE")
+            .requires("privkey_pem")
+            .help(format!("Passphrase for an encrypted key (default: ${})", PASSPHRASE_ENV)),
+    )
+    .arg(
+        Arg::new("ssh_agent")
+            .long("ssh-agent")
+            .value_name("KID|COMMENT")
+            .num_args(0..=1)
+            .default_missing_value("")
+            .help("Sign with an Ed25519 key held by ssh-agent ($SSH_AUTH_SOCK); select by kid or comment"),
+    )
+    .arg(
+        Arg::new("sign_command")
+            .long("sign-command")
+            .value_name("CMD")
+            .requires("sign_command_pubkey")
+            .help("External signer: run `sh -c CMD` with the message on stdin, read the signature (base64 or raw 64 bytes) from stdout"),
+    )
+    .arg(
+        Arg::new("sign_command_pubkey")
+            .long("sign-command-pubkey")
+            .value_name("FILE")
+            .requires("sign_command")
+            .action(ArgAction::Set)
+            .help("Public key (PEM) of the external signer"),
+    )
+    .group(ArgGroup::new("signer").args(["privkey_pem", "ssh_agent", "sign_command"]).required(true))
+}
+
+pub fn from_matches(m: &ArgMatches) -> anyhow::Result<Box<dyn Signer>> {
+    if let Some(path) = m.get_one::<String>("privkey_pem") {
+        let passphrase = match m.get_one::<String>("passphrase_file") {
+            Some(f) => Some(fs::read_to_string(f).with_context(|| format!("read {}", f))?.trim_end_matches(['\r', '\n']).to_string()),
+            None => std::env::var(PASSPHRASE_ENV).ok(),
+        };
+        return Ok(Box::new(load_key_file(path, passphrase.as_deref())?));
+    }
+    if let Some(selector) = m.get_one::<String>("ssh_agent") {
+        let socket = std::env::var("SSH_AUTH_SOCK").context("--ssh-agent: SSH_AUTH_SOCK not set")?;
+        return Ok(Box::new(SshAgent::connect(&socket, Some(selector.as_str()).filter(|s| !s.is_empty()))?));
+    }
+    let cmd = m.get_one::<String>("sign_command").unwrap();
+    let pem_path = m.get_one::<String>("sign_command_pubkey").unwrap();
+    let public = policy_pack::public_key_from_pem(&fs::read_to_string(pem_path)?)
+        .map_err(|e| anyhow::anyhow!("{}: {}", pem_path, e))?;
+    Ok(Box::new(ExternalCommand { cmd: cmd.clone(), public }))
+}
+
+/// PKCS#8 PEM ou DER; `ENCRYPTED PRIVATE KEY` (PBES2) exige passphrase.
+pub fn load_key_file(path: &str, passphrase: Option<&str>) -> anyhow::Result<SigningKey> {
+    let bytes = fs::read(path).with_context(|| format!("read {}", path))?;
+    let need_pass = || anyhow::anyhow!("{}: key is encrypted; use --passphrase-file or ${}", path, PASSPHRASE_ENV);
+    let key = match std::str::from_utf8(&bytes).ok().filter(|s| s.trim_start().starts_with("-----BEGIN")) {
+        Some(pem) if pem.contains("BEGIN OPENSSH PRIVATE KEY") => {
+            anyhow::bail!("{}: OpenSSH key format; load it into ssh-agent and use --ssh-agent", path)
+        }
+        Some(pem) if pem.contains("BEGIN ENCRYPTED PRIVATE KEY") => {
+            SigningKey::from_pkcs8_encrypted_pem(pem, passphrase.ok_or_else(need_pass)?)
+        }
+        Some(pem) => SigningKey::from_pkcs8_pem(pem),
+        None if pkcs8::EncryptedPrivateKeyInfo::try_from(bytes.as_slice()).is_ok() => {
+            SigningKey::from_pkcs8_encrypted_der(&bytes, passphrase.ok_or_else(need_pass)?)
+        }
+        None => SigningKey::from_pkcs8_der(&bytes),
+    };
+    key.map_err(|e| anyhow::anyhow!("{}: not an Ed25519 PKCS#8 key (or wrong passphrase): {}", path, e))
+}
+
+/// Comando externo: mensagem no stdin, assinatura no stdout.
+pub struct ExternalCommand {
+    cmd: String,
+    public: VerifyingKey,
+}
+
+impl Signer for ExternalCommand {
+    fn public_key(&self) -> VerifyingKey {
+        self.public
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> anyhow::Result<Signature> {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.cmd)
+            .env("POLICY_SIGNER_KID", kid_for(&self.public))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("spawn sign command `{}`", self.cmd))?;
+        child.stdin.take().unwrap().write_all(msg)?;
+        let out = child.wait_with_output()?;
+        if !out.status.success() {
+            anyhow::bail!("sign command `{}` exited with {}", self.cmd, out.status);
+        }
+        let raw = if out.stdout.len() == 64 {
+            out.stdout
+        } else {
+            general_purpose::STANDARD.decode(String::from_utf8_lossy(&out.stdout).trim())
+                .map_err(|e| anyhow::anyhow!("sign command output is not base64: {}", e))?
+        };
+        Signature::from_slice(&raw).map_err(|e| anyhow::anyhow!("sign command output: {}", e))
+    }
+}
+
+// Protocolo do agente (draft-miller-ssh-agent): u32 len | u8 tipo | payload
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+const SSH_ED25519: &[u8] = b"ssh-ed25519";
+
+/// Chave Ed25519 do ssh-agent; a assinatura `ssh-ed25519` é Ed25519 puro
+/// sobre os bytes enviados, então serve direto para o pack.
+pub struct SshAgent {
+    socket: String,
+    blob: Vec<u8>,
+    public: VerifyingKey,
+}
+
+impl SshAgent {
+    /// Sem seletor, exige exatamente uma chave Ed25519 no agente.
+    pub fn connect(socket: &str, selector: Option<&str>) -> anyhow::Result<Self> {
+        let reply = agent_call(socket, SSH_AGENTC_REQUEST_IDENTITIES, &[])?;
+        let mut r = Reader(&reply);
+        if r.u8()? != SSH_AGENT_IDENTITIES_ANSWER {
+            anyhow::bail!("ssh-agent refused to list identities");
+        }
+        let mut keys = Vec::new();
+        for _ in 0..r.u32()? {
+            let blob = r.string()?.to_vec();
+            let comment = String::from_utf8_lossy(r.string()?).to_string();
+            let mut b = Reader(&blob);
+            if b.string()? != SSH_ED25519 {
+                continue;
+            }
+            let raw: [u8; 32] = b.string()?.try_into().map_err(|_| anyhow::anyhow!("ssh-agent: bad ed25519 key blob"))?;
+            let public = VerifyingKey::from_bytes(&raw)?;
+            keys.push((kid_for(&public), comment, blob, public));
+        }
+        let mut matching: Vec<_> = keys.iter()
+            .filter(|(kid, comment, _, _)| selector.is_none_or(|s| s == kid || s == comment))
+            .collect();
+        match (matching.len(), selector) {
+            (1, _) => {
+                let (_, _, blob, public) = matching.remove(0);
+                Ok(Self { socket: socket.into(), blob: blob.clone(), public: *public })
+            }
+            (0, Some(s)) => anyhow::bail!("ssh-agent has no Ed25519 key matching {}", s),
+            (0, None) => anyhow::bail!("ssh-agent has no Ed25519 keys"),
+            (_, _) => anyhow::bail!(
+                "ssh-agent has several Ed25519 keys; pick one with --ssh-agent <kid|comment>: {}",
+                keys.iter().map(|(kid, c, _, _)| format!("{} ({})", kid, c)).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+impl Signer for SshAgent {
+    fn public_key(&self) -> VerifyingKey {
+        self.public
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> anyhow::Result<Signature> {
+        let mut req = Vec::new();
+        put_string(&mut req, &self.blob);
+        put_string(&mut req, msg);
+        req.extend_from_slice(&0u32.to_be_bytes()); // flags
+        let reply = agent_call(&self.socket, SSH_AGENTC_SIGN_REQUEST, &req)?;
+        let mut r = Reader(&reply);
+        match r.u8()? {
+            SSH_AGENT_SIGN_RESPONSE => {}
+            SSH_AGENT_FAILURE => anyhow::bail!("ssh-agent refused to sign (key locked or confirmation denied?)"),
+            t => anyhow::bail!("ssh-agent: unexpected reply type {}", t),
+        }
+        let sig_blob = r.string()?;
+        let mut s = Reader(sig_blob);
+        if s.string()? != SSH_ED25519 {
+            anyhow::bail!("ssh-agent returned a non-ed25519 signature");
+        }
+        Signature::from_slice(s.string()?).map_err(|e| anyhow::anyhow!("ssh-agent signature: {}", e))
+    }
+}
+
+fn agent_call(socket: &str, kind: u8, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)
+        .with_context(|| format!("connect ssh-agent at {}", socket))?;
+    stream.write_all(&(payload.len() as u32 + 1).to_be_bytes())?;
+    stream.write_all(&[kind])?;
+    stream.write_all(payload)?;
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let mut reply = vec![0u8; u32::from_be_bytes(len) as usize];
+    stream.read_exact(&mut reply)?;
+    Ok(reply)
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.0.len() < n {
+            anyhow::bail!("ssh-agent: truncated message");
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> anyhow::Result<&'a [u8]> {
+        let n = self.u32()? as usize;
+        self.take(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use std::os::unix::net::UnixListener;
+
+    fn tmp(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("policy-signer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn key_files_pem_der_and_encrypted() {
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let der = key.to_pkcs8_der().unwrap();
+        let pem = key.to_pkcs8_pem(Default::default()).unwrap();
+        // PBKDF2 com poucas iterações: o scrypt padrão é lento demais em debug
+        let params = pkcs8::pkcs5::pbes2::Parameters::pbkdf2_sha256_aes256cbc(2048, &[7u8; 16], &[1u8; 16]).unwrap();
+        let enc = pkcs8::PrivateKeyInfo::try_from(der.as_bytes()).unwrap().encrypt_with_params(params, "s3cret").unwrap();
+
+        for (name, bytes) in [
+            ("k.der", der.as_bytes().to_vec()),
+            ("k.pem", pem.as_bytes().to_vec()),
+            ("k.enc.der", enc.as_bytes().to_vec()),
+            ("k.enc.pem", enc.to_pem("ENCRYPTED PRIVATE KEY", Default::default()).unwrap().as_bytes().to_vec()),
+        ] {
+            let path = tmp(name);
+            fs::write(&path, bytes).unwrap();
+            let path = path.to_str().unwrap();
+            assert_eq!(load_key_file(path, Some("s3cret")).unwrap().to_bytes(), key.to_bytes(), "{}", name);
+            if name.contains(".enc.") {
+                assert!(load_key_file(path, None).is_err());
+                assert!(load_key_file(path, Some("wrong")).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn external_command_and_ssh_agent() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let msg = b"canonical envelope";
+
+        let sig = general_purpose::STANDARD.encode(ed25519_dalek::Signer::sign(&key, msg).to_bytes());
+        let ext = ExternalCommand { cmd: format!("cat >/dev/null; echo {}", sig), public: key.verifying_key() };
+        assert_eq!(ext.sign_message(msg).unwrap(), ed25519_dalek::Signer::sign(&key, msg));
+        let failing = ExternalCommand { cmd: "exit 3".into(), public: key.verifying_key() };
+        assert!(failing.sign_message(msg).is_err());
+
+        // agente mínimo: lista uma chave e assina
+        let socket = tmp("agent.sock");
+        let _ = fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let agent_key = key.clone();
+        std::thread::spawn(move || {
+            let mut blob = Vec::new();
+            put_string(&mut blob, SSH_ED25519);
+            put_string(&mut blob, agent_key.verifying_key().as_bytes());
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut len = [0u8; 4];
+                stream.read_exact(&mut len).unwrap();
+                let mut req = vec![0u8; u32::from_be_bytes(len) as usize];
+                stream.read_exact(&mut req).unwrap();
+                let mut reply = Vec::new();
+                if req[0] == SSH_AGENTC_REQUEST_IDENTITIES {
+                    reply.push(SSH_AGENT_IDENTITIES_ANSWER);
+                    reply.extend_from_slice(&1u32.to_be_bytes());
+                    put_string(&mut reply, &blob);
+                    put_string(&mut reply, b"ci@build");
+                } else {
+                    let mut r = Reader(&req[1..]);
+                    assert_eq!(r.string().unwrap(), blob.as_slice());
+                    let data = r.string().unwrap();
+                    let mut sig_blob = Vec::new();
+                    put_string(&mut sig_blob, SSH_ED25519);
+                    put_string(&mut sig_blob, &ed25519_dalek::Signer::sign(&agent_key, data).to_bytes());
+                    reply.push(SSH_AGENT_SIGN_RESPONSE);
+                    put_string(&mut reply, &sig_blob);
+                }
+                stream.write_all(&(reply.len() as u32).to_be_bytes()).unwrap();
+                stream.write_all(&reply).unwrap();
+            }
+        });
+
+        let socket = socket.to_str().unwrap();
+        assert!(SshAgent::connect(socket, Some("someone-else")).is_err());
+        let agent = SshAgent::connect(socket, Some("ci@build")).unwrap();
+        assert_eq!(agent.kid(), kid_for(&key.verifying_key()));
+        let env = policy_pack::PackEnvelope::for_chip("p".into(), "1".into(), b"y", "tdln-chip/0.3".into(), agent.kid());
+        let pack = env.sign(&agent).unwrap();
+        let trust = policy_pack::TrustSet::single(key.verifying_key());
+        policy_pack::AnyPack::V2(pack).verify(&trust, policy_pack::now_epoch()).unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+//! Policy Signer — Ed25519 + BLAKE3 para policy packs
+//! Gera pack.json assinado a partir de YAML; `cosign` acrescenta aprovações
+//! e `verify` mostra quais chaves confiáveis assinaram. `authorize-downgrade`
+//! assina a permissão para o proxy voltar a um pack mais antigo. Com `--bundle`
+//! também gera o artefato único (pack + chip); todos os subcomandos aceitam os dois.
+//! A chave vem de arquivo PKCS#8, ssh-agent ou comando externo (ver `backend`).
+//! Chip com `imports:` é resolvido antes: o pack assina o chip plano, com o
+//! blake3 de cada biblioteca fixado, e esse é o YAML a publicar.
+//!
+//! Lib + bin: o `ubl-policy` monta os mesmos comandos a partir daqui.
+
+pub mod backend;
+
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use policy_engine::{resolve_imports, SemanticChip};
+use policy_pack::{AnyPack, Bundle, DowngradeAuth, PackEnvelope, SigStatus, TrustSet, TrustStore, TrustedKey};
+use std::fs;
+use std::path::Path;
+use base64::{Engine as _, engine::general_purpose};
+
+/// CLI completo do `policy-signer`: assinar no topo, demais como subcomandos.
+pub fn command() -> Command {
+    sign_command("policy-signer")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(cosign_command())
+        .subcommand(verify_command())
+        .subcommand(authorize_downgrade_command())
+}
+
+pub fn run(matches: &ArgMatches) -> anyhow::Result<()> {
+    match matches.subcommand() {
+        Some(("cosign", m)) => cosign(m),
+        Some(("verify", m)) => verify(m),
+        Some(("authorize-downgrade", m)) => authorize_downgrade(m),
+        _ => sign(matches),
+    }
+}
+
+pub fn sign_command(name: &'static str) -> Command {
+    backend::args(Command::new(name))
+        .about("Sign policy YAML with Ed25519 + BLAKE3")
+        .arg(
+            Arg::new("yaml")
+                .long("yaml")
+                .value_name("FILE")
+                .help("Path to policy YAML file")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output")
+                .long("out")
+                .value_name("FILE")
+                .help("Output pack.json path")
+                .default_value("pack.json"),
+        )
+        .arg(
+            Arg::new("id")
+                .long("id")
+                .value_name("ID")
+                .help("Pack ID")
+                .required(true),
+        )
+        .arg(
+            Arg::new("version")
+                .long("version")
+                .value_name("VERSION")
+                .help("Pack version")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("bundle")
+                .long("bundle")
+                .value_name("FILE")
+                .help("Also write a single-file bundle (signed pack + embedded chip YAML)"),
+        )
+        .arg(
+            Arg::new("kid")
+                .long("kid")
+                .value_name("KID")
+                .help("Signer key id (default: ed25519:<blake3 of public key>)"),
+        )
+        .arg(
+            Arg::new("not_before")
+                .long("not-before")
+                .value_name("UNIX_SECS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Pack is rejected before this time"),
+        )
+        .arg(
+            Arg::new("not_after")
+                .long("not-after")
+                .value_name("UNIX_SECS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Pack is rejected after this time"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .help("Reject unknown top-level (and meta:) keys in the chip"),
+        )
+        .arg(
+            Arg::new("require_owners")
+                .long("require-owners")
+                .action(ArgAction::SetTrue)
+                .help("Refuse to sign a chip without meta.owners"),
+        )
+        .arg(
+            Arg::new("resolved")
+                .long("resolved")
+                .value_name("FILE")
+                .help("Where to write the resolved chip when it has imports (default: <yaml>.resolved.yaml)"),
+        )
+        .arg(
+            Arg::new("locked")
+                .long("locked")
+                .action(ArgAction::SetTrue)
+                .help("Require every import to be pinned by blake3 in the source chip"),
+        )
+}
+
+pub fn cosign_command() -> Command {
+    backend::args(Command::new("cosign"))
+        .about("Add a signature to an existing pack (M-of-N approval)")
+        .arg(Arg::new("pack").long("pack").value_name("FILE").help("Signed pack.json").required(true))
+        .arg(Arg::new("yaml").long("yaml").value_name("FILE").help("Check the pack blake3 against this YAML before approving"))
+        .arg(Arg::new("kid").long("kid").value_name("KID").help("Signer key id (default: ed25519:<blake3 of public key>)"))
+        .arg(Arg::new("output").long("out").value_name("FILE").help("Output path (default: overwrite --pack)"))
+}
+
+pub fn verify_command() -> Command {
+    Command::new("verify")
+        .about("Report which trusted keys signed a pack")
+        .arg(Arg::new("pack").long("pack").value_name("FILE").help("Signed pack.json").required(true))
+        .arg(
+            Arg::new("pubkey_pem")
+                .long("pubkey_pem")
+                .value_name("FILE")
+                .help("Trusted Ed25519 public key (PEM); repeat for each key")
+                .action(ArgAction::Append),
+        )
+        .arg(Arg::new("trust_store").long("trust-store").value_name("FILE").help("Trust store JSON (kids, validity, revocation)"))
+        .group(ArgGroup::new("trust").args(["pubkey_pem", "trust_store"]).required(true))
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Distinct trusted signatures required (default: trust store threshold or 1)"),
+        )
+        .arg(Arg::new("yaml").long("yaml").value_name("FILE").help("Also check the pack blake3 against this YAML"))
+}
+
+pub fn authorize_downgrade_command() -> Command {
+    backend::args(Command::new("authorize-downgrade"))
+        .about("Sign permission for policy-proxy to install this (older) pack; run once per approver")
+        .arg(Arg::new("pack").long("pack").value_name("FILE").help("The older pack.json to allow").required(true))
+        .arg(Arg::new("kid").long("kid").value_name("KID").help("Signer key id (default: ed25519:<blake3 of public key>)"))
+        .arg(
+            Arg::new("ttl_hours")
+                .long("ttl-hours")
+                .value_name("HOURS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Authorization lifetime")
+                .default_value("24"),
+        )
+        .arg(Arg::new("output").long("out").value_name("FILE").help("Output path (default: <pack>.downgrade.json; existing file gets another signature)"))
+}
+
+pub fn sign(matches: &ArgMatches) -> anyhow::Result<()> {
+    // Ler YAML
+    let yaml_path = matches.get_one::<String>("yaml").unwrap();
+    let source = fs::read_to_string(yaml_path)?;
+
+    // imports: resolvidos a partir do diretório do chip; o pack cobre o resultado
+    let base = Path::new(yaml_path).parent().unwrap_or(Path::new("."));
+    let resolved = resolve_imports(&source, base).map_err(|e| anyhow::anyhow!("{}: {}", yaml_path, e))?;
+    if matches.get_flag("locked") {
+        if let Some(i) = resolved.imports.iter().find(|i| i.blake3.is_none()) {
+            anyhow::bail!("{}: import {} is not pinned by blake3 (required by --locked)", yaml_path, i.path.as_deref().unwrap_or("?"));
+        }
+    }
+    let yaml_content = resolved.yaml;
+    let resolved_path = if resolved.pins.is_empty() {
+        None
+    } else {
+        let path = matches.get_one::<String>("resolved").cloned().unwrap_or_else(|| {
+            format!("{}.resolved.yaml", yaml_path.strip_suffix(".yaml").or_else(|| yaml_path.strip_suffix(".yml")).unwrap_or(yaml_path))
+        });
+        fs::write(&path, &yaml_content)?;
+        Some(path)
+    };
+
+    // O chip precisa compilar; a versão declarada entra no envelope assinado
+    let chip = if matches.get_flag("strict") {
+        SemanticChip::from_yaml_strict(&yaml_content)
+    } else {
+        SemanticChip::from_yaml(&yaml_content)
+    }.map_err(|e| anyhow::anyhow!("{}: {}", yaml_path, e))?;
+    if matches.get_flag("require_owners") && chip.owners().is_empty() {
+        anyhow::bail!("{}: chip declares no meta.owners (required by --require-owners)", yaml_path);
+    }
+    let chip_version = chip.version.clone();
+
+    // Chave: arquivo, ssh-agent ou comando externo
+    let signer = backend::from_matches(matches)?;
+    let verifying_key = signer.public_key();
+
+    // Envelope v2 canônico (ver crate policy-pack)
+    let pack_id = matches.get_one::<String>("id").unwrap().clone();
+    let pack_version = matches.get_one::<String>("version").unwrap().clone();
+    let kid = matches.get_one::<String>("kid").cloned().unwrap_or_else(|| signer.kid());
+    let mut envelope = PackEnvelope::for_chip(pack_id, pack_version, yaml_content.as_bytes(), chip_version, kid);
+    envelope.not_before = matches.get_one::<u64>("not_before").copied();
+    envelope.not_after = matches.get_one::<u64>("not_after").copied();
+    let pack = envelope.sign(signer.as_ref())?;
+
+    // Salvar pack.json
+    let output_path = matches.get_one::<String>("output").unwrap();
+    fs::write(output_path, serde_json::to_string_pretty(&pack)?)?;
+
+    // Bundle: mesmo pack com o YAML embutido (um arquivo só para deploy atômico)
+    if let Some(bundle_path) = matches.get_one::<String>("bundle") {
+        let mut bundle = Bundle::new(pack.clone(), yaml_content.clone());
+        bundle.meta.insert("source".into(), yaml_path.clone());
+        bundle.meta.insert("tool".into(), format!("policy-signer {}", env!("CARGO_PKG_VERSION")));
+        fs::write(bundle_path, serde_json::to_string_pretty(&bundle)?)?;
+        println!("📦 Bundle criado: {}", bundle_path);
+    }
+
+    // Mostrar chave pública (para copiar no wrangler.toml/service)
+    let pubkey_b64 = public_key_pem_b64(&verifying_key)?;
+
+    println!("✅ Pack criado: {}", output_path);
+    if let Some(path) = &resolved_path {
+        println!("   Resolved chip (deploy this one): {}", path);
+    }
+    println!("   Format: {}", pack.envelope.format);
+    println!("   ID: {}", pack.envelope.id);
+    println!("   Version: {}", pack.envelope.version);
+    println!("   Chip: {}", pack.envelope.chip_version);
+    if !chip.owners().is_empty() {
+        println!("   Owners: {}", chip.owners().join(", "));
+    }
+    for pin in &resolved.pins {
+        println!("   Import: {} {} ({})", pin.library, pin.path.as_deref().unwrap_or(""), pin.blake3);
+    }
+    println!("   BLAKE3: {}", pack.envelope.blake3);
+    println!("   Kid: {}", pack.envelope.kid);
+    println!("   Signature: {}...", &pack.signature[..16]);
+    println!();
+    println!("📋 Public key (base64 PEM) para wrangler.toml e service:");
+    println!("{}", pubkey_b64);
+
+    Ok(())
+}
+
+/// `POLICY_PUBKEY_PEM_B64`: base64 do PEM SPKI, o que `public_key_from_pem_b64` lê.
+fn public_key_pem_b64(key: &ed25519_dalek::VerifyingKey) -> anyhow::Result<String> {
+    use ed25519_dalek::pkcs8::{EncodePublicKey, spki::der::pem::LineEnding};
+    let pem = key.to_public_key_pem(LineEnding::LF)?;
+    Ok(general_purpose::STANDARD.encode(pem.as_bytes()))
+}
+
+/// pack.json ou bundle
+enum Artifact {
+    Pack(AnyPack),
+    Bundle(Bundle),
+}
+
+impl Artifact {
+    fn pack(&self) -> AnyPack {
+        match self {
+            Self::Pack(p) => p.clone(),
+            Self::Bundle(b) => AnyPack::V2(b.pack.clone()),
+        }
+    }
+}
+
+fn read_pack(path: &str) -> anyhow::Result<Artifact> {
+    let raw = fs::read_to_string(path)?;
+    let artifact = if Bundle::is_bundle(&raw) {
+        Bundle::from_json(&raw).map(Artifact::Bundle)
+    } else {
+        AnyPack::from_json(&raw).map(Artifact::Pack)
+    };
+    artifact.map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+}
+
+fn check_yaml(pack: &AnyPack, yaml_path: &str) -> anyhow::Result<()> {
+    let digest = policy_pack::blake3_hex(&fs::read(yaml_path)?);
+    let expected = match pack {
+        AnyPack::V1(p) => &p.blake3,
+        AnyPack::V2(p) => &p.envelope.blake3,
+    };
+    if &digest != expected {
+        anyhow::bail!("{} does not match pack blake3", yaml_path);
+    }
+    Ok(())
+}
+
+pub fn cosign(matches: &ArgMatches) -> anyhow::Result<()> {
+    let pack_path = matches.get_one::<String>("pack").unwrap();
+    let mut artifact = read_pack(pack_path)?;
+    if let Some(yaml_path) = matches.get_one::<String>("yaml") {
+        check_yaml(&artifact.pack(), yaml_path)?;
+    }
+    let signer = backend::from_matches(matches)?;
+    let kid = matches.get_one::<String>("kid").cloned().unwrap_or_else(|| signer.kid());
+
+    let output_path = matches.get_one::<String>("output").unwrap_or(pack_path);
+    let pack = match &mut artifact {
+        Artifact::Pack(AnyPack::V2(pack)) => pack,
+        Artifact::Bundle(bundle) => &mut bundle.pack,
+        Artifact::Pack(AnyPack::V1(_)) => anyhow::bail!("v1 packs cannot be cosigned; re-sign with policy-signer first"),
+    };
+    pack.cosign(signer.as_ref(), kid.clone())?;
+    let pack = pack.clone();
+    match &artifact {
+        Artifact::Bundle(bundle) => fs::write(output_path, serde_json::to_string_pretty(bundle)?)?,
+        Artifact::Pack(_) => fs::write(output_path, serde_json::to_string_pretty(&pack)?)?,
+    }
+    println!("✅ Cosignature added: {}", output_path);
+    println!("   ID: {}@{}", pack.envelope.id, pack.envelope.version);
+    println!("   Kid: {}", kid);
+    println!("   Signatures: {}", pack.signatures().count());
+    Ok(())
+}
+
+pub fn verify(matches: &ArgMatches) -> anyhow::Result<()> {
+    let artifact = read_pack(matches.get_one::<String>("pack").unwrap())?;
+    let pack = artifact.pack();
+    let threshold = matches.get_one::<usize>("threshold").copied();
+    let trust = match matches.get_one::<String>("trust_store") {
+        Some(path) => TrustStore::from_file(path)?.trust_set(threshold)?,
+        None => {
+            let keys = matches.get_many::<String>("pubkey_pem").unwrap()
+                .map(|path| {
+                    let pem = fs::read_to_string(path)?;
+                    policy_pack::public_key_from_pem(&pem)
+                        .map(TrustedKey::new)
+                        .map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            TrustSet::new(keys, threshold.unwrap_or(1))?
+        }
+    };
+
+    let now = policy_pack::now_epoch();
+    for check in pack.check_signatures(&trust, now) {
+        let kid = check.kid.as_deref().unwrap_or("(v1)");
+        match check.status {
+            SigStatus::Trusted { kid: trusted } => println!("✅ {} — trusted key {}", kid, trusted),
+            SigStatus::Invalid => println!("❌ {} — signature does not match", kid),
+            SigStatus::Revoked { .. } => println!("❌ {} — key revoked", kid),
+            SigStatus::Expired { .. } => println!("❌ {} — key outside its validity window", kid),
+            SigStatus::Untrusted => println!("⚠️  {} — not in trust set", kid),
+        }
+    }
+    if let Some(yaml_path) = matches.get_one::<String>("yaml") {
+        check_yaml(&pack, yaml_path)?;
+        println!("✅ YAML matches pack blake3");
+    }
+    if let Artifact::Bundle(bundle) = &artifact {
+        if policy_pack::blake3_hex(bundle.chip.as_bytes()) != bundle.pack.envelope.blake3 {
+            anyhow::bail!("embedded chip does not match pack blake3");
+        }
+        println!("✅ Embedded chip matches pack blake3");
+    }
+
+    let verified = pack.verify(&trust, now)?;
+    println!();
+    println!("Pack {}@{} OK: {} of {} trusted keys signed (threshold {})",
+        verified.id, verified.version, verified.signed_by.len(), trust.keys().len(), trust.threshold());
+    Ok(())
+}
+
+pub fn authorize_downgrade(matches: &ArgMatches) -> anyhow::Result<()> {
+    let pack_path = matches.get_one::<String>("pack").unwrap();
+    let (id, version, blake3) = match read_pack(pack_path)?.pack() {
+        AnyPack::V1(p) => (p.id, p.version, p.blake3),
+        AnyPack::V2(p) => (p.envelope.id, p.envelope.version, p.envelope.blake3),
+    };
+    let output_path = matches.get_one::<String>("output").cloned().unwrap_or_else(|| {
+        format!("{}.downgrade.json", pack_path.strip_suffix(".json").unwrap_or(pack_path))
+    });
+
+    // Arquivo existente: outro aprovador assinando a mesma autorização
+    let mut auth = match fs::read_to_string(&output_path) {
+        Ok(raw) => {
+            let auth = DowngradeAuth::from_json(&raw).map_err(|e| anyhow::anyhow!("{}: {}", output_path, e))?;
+            let e = &auth.envelope;
+            if (e.id.as_str(), e.version.as_str(), e.blake3.as_str()) != (id.as_str(), version.as_str(), blake3.as_str()) {
+                anyhow::bail!("{} authorizes {}@{}, not {}@{}", output_path, e.id, e.version, id, version);
+            }
+            auth
+        }
+        Err(_) => {
+            let now = policy_pack::now_epoch();
+            let ttl = matches.get_one::<u64>("ttl_hours").unwrap() * 3600;
+            DowngradeAuth::new(id, version, blake3, now, now + ttl)
+        }
+    };
+
+    let signer = backend::from_matches(matches)?;
+    let kid = matches.get_one::<String>("kid").cloned().unwrap_or_else(|| signer.kid());
+    auth.sign(signer.as_ref(), kid.clone())?;
+    fs::write(&output_path, serde_json::to_string_pretty(&auth)?)?;
+
+    println!("✅ Downgrade authorization: {}", output_path);
+    println!("   Pack: {}@{} ({})", auth.envelope.id, auth.envelope.version, auth.envelope.blake3);
+    println!("   Expires: {}", auth.envelope.not_after);
+    println!("   Signed by: {}", auth.signatures.iter().map(|s| s.kid.as_str()).collect::<Vec<_>>().join(", "));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printed_public_key_is_spki_the_proxy_accepts() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        let b64 = public_key_pem_b64(&key).unwrap();
+        assert_eq!(policy_pack::public_key_from_pem_b64(&b64).unwrap(), key);
+    }
+}
@@ -0,0 +1,125 @@
+//! Casos de decisão em YAML:
+//!
+//! ```yaml
+//! chip: ../ubl_core_v1.yaml        # relativo ao arquivo de casos
+//! defaults: { transport: { tls_version: 1.3 } }
+//! cases:
+//!   - name: admin sem grupo
+//!     context: { req: { path: /admin/x } }
+//!     expect: allow_standard_access
+//! ```
+//!
+//! `context` é mesclado sobre `defaults`, que é mesclado sobre
+//! `RequestContext::default()` — cada caso só escreve o que importa.
+
+use clap::ArgMatches;
+use policy_engine::{decide, RequestContext};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+pub struct CaseFile {
+    #[serde(default)]
+    pub chip: Option<String>,
+    #[serde(default)]
+    pub defaults: Value,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Case {
+    pub name: String,
+    #[serde(default)]
+    pub context: Value,
+    pub expect: String,
+}
+
+impl CaseFile {
+    /// `chip` volta resolvido relativo ao diretório do arquivo.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        let mut file: Self = serde_yaml::from_str(&raw).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        if let Some(chip) = &file.chip {
+            let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+            file.chip = Some(dir.join(chip).to_string_lossy().to_string());
+        }
+        Ok(file)
+    }
+}
+
+/// `RequestContext::default()` ← defaults ← overrides
+pub fn context(defaults: &Value, overrides: &Value) -> anyhow::Result<RequestContext> {
+    let mut base = serde_json::to_value(RequestContext::default())?;
+    merge(&mut base, defaults);
+    merge(&mut base, overrides);
+    serde_json::from_value(base).map_err(|e| anyhow::anyhow!("context: {}", e))
+}
+
+fn merge(base: &mut Value, over: &Value) {
+    match (base, over) {
+        (Value::Object(b), Value::Object(o)) => {
+            for (k, v) in o {
+                merge(b.entry(k.clone()).or_insert(Value::Null), v);
+            }
+        }
+        (_, Value::Null) => {}
+        (b, o) => *b = o.clone(),
+    }
+}
+
+pub fn test(m: &ArgMatches) -> anyhow::Result<()> {
+    let (mut passed, mut failed) = (0, 0);
+    for path in m.get_many::<String>("cases").unwrap() {
+        let file = CaseFile::load(path)?;
+        let chip_path = m.get_one::<String>("chip").or(file.chip.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("{}: no `chip:` in file and no --chip", path))?;
+        let chip = crate::chip::load(chip_path)?;
+        println!("{} ({})", path, chip_path);
+        for case in &file.cases {
+            let got = decide(&chip, &context(&file.defaults, &case.context)?);
+            if got.decision == case.expect {
+                println!("  ✅ {}", case.name);
+                passed += 1;
+            } else {
+                println!("  ❌ {} — expected {}, got {} (trigger {}, chain [{}])",
+                    case.name, case.expect, got.decision, got.trigger, got.chain.join(" → "));
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        anyhow::bail!("{} case(s) failed", failed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_cases_pass() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../policies/tests");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path().to_string_lossy().to_string();
+            let file = CaseFile::load(&path).unwrap();
+            let chip = crate::chip::load(file.chip.as_ref().unwrap()).unwrap();
+            for case in &file.cases {
+                let got = decide(&chip, &context(&file.defaults, &case.context).unwrap());
+                assert_eq!(got.decision, case.expect, "{}: {}", path, case.name);
+            }
+        }
+    }
+
+    #[test]
+    fn context_merges_over_defaults() {
+        let defaults = serde_json::json!({ "transport": { "tls_version": 1.3 }, "user": { "groups": ["a"] } });
+        let ctx = context(&defaults, &serde_json::json!({ "user": { "groups": ["ubl-ops"] }, "req": { "path": "/admin/x" } })).unwrap();
+        assert_eq!(ctx.transport.tls_version, 1.3);
+        assert_eq!(ctx.user.groups, vec!["ubl-ops"]);
+        assert_eq!(ctx.req.unwrap().path.as_deref(), Some("/admin/x"));
+        assert!(!ctx.mtls.verified);
+    }
+}
@@ -0,0 +1,261 @@
+//! lint / eval / explain / diff sobre chips locais (YAML ou bundle).
+
+use clap::ArgMatches;
+use policy_engine::{decide, resolve_imports, ChipMeta, Diagnostic, OutputAction, PolicyBitDefinition, Reach, RequestContext, SemanticChip, Severity, WiringStructure};
+use policy_pack::Bundle;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cases;
+
+/// YAML do chip ou bundle (`ubl-policy-bundle/1`); no bundle o chip precisa
+/// bater com o blake3 do pack embutido. `imports:` são resolvidos a partir
+/// do diretório do arquivo.
+pub fn load(path: &str) -> anyhow::Result<SemanticChip> {
+    SemanticChip::from_yaml(&load_yaml(path)?).map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+}
+
+fn load_yaml(path: &str) -> anyhow::Result<String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    let yaml = if Bundle::is_bundle(&raw) {
+        let bundle = Bundle::from_json(&raw).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        if policy_pack::blake3_hex(bundle.chip.as_bytes()) != bundle.pack.envelope.blake3 {
+            anyhow::bail!("{}: embedded chip does not match pack blake3", path);
+        }
+        bundle.chip
+    } else {
+        let base = Path::new(path).parent().unwrap_or(Path::new("."));
+        resolve_imports(&raw, base).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?.yaml
+    };
+    Ok(yaml)
+}
+
+fn load_context(path: &str) -> anyhow::Result<RequestContext> {
+    let raw = if path == "-" {
+        let mut s = String::new();
+        std::io::stdin().read_to_string(&mut s)?;
+        s
+    } else {
+        std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?
+    };
+    let value: serde_json::Value = serde_json::from_str(&raw).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    cases::context(&serde_json::Value::Null, &value)
+}
+
+pub fn lint(m: &ArgMatches) -> anyhow::Result<()> {
+    let deny_warnings = m.get_flag("deny_warnings");
+    let mut report = Vec::new();
+    let (mut errors, mut warnings) = (0, 0);
+    for path in m.get_many::<String>("chip").unwrap() {
+        // lint lê em modo estrito: chave desconhecida é erro, não silêncio
+        let diags = match load_yaml(path).and_then(|y| SemanticChip::from_yaml_strict(&y)) {
+            Ok(chip) => policy_engine::lint(&chip),
+            Err(e) => vec![Diagnostic { severity: Severity::Error, code: "parse", at: "chip".into(), message: e.to_string() }],
+        };
+        errors += diags.iter().filter(|d| d.severity == Severity::Error).count();
+        warnings += diags.iter().filter(|d| d.severity == Severity::Warning).count();
+        if !m.get_flag("json") {
+            for d in &diags {
+                println!("{}: {}", path, d);
+            }
+        }
+        report.push(serde_json::json!({ "file": path, "diagnostics": diags }));
+    }
+    if m.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    if errors > 0 || (deny_warnings && warnings > 0) {
+        anyhow::bail!("lint failed");
+    }
+    Ok(())
+}
+
+pub fn analyze(m: &ArgMatches) -> anyhow::Result<()> {
+    let a = policy_engine::analyze(&load(m.get_one::<String>("chip").unwrap())?)?;
+    let warnings = a.outputs.iter().filter(|o| o.reach != Reach::Reachable).count();
+    if m.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&a)?);
+    } else {
+        println!("{} assignment(s) over [{}]", a.assignments, a.variables.join(", "));
+        if !a.constant_false.is_empty() {
+            println!("always false (no evaluator): [{}]", a.constant_false.join(", "));
+        }
+        for o in &a.outputs {
+            let status = match &o.reach {
+                Reach::Reachable => "reachable".to_string(),
+                Reach::Unreachable => "UNREACHABLE: trigger is never true".to_string(),
+                Reach::Shadowed(by) => format!("SHADOWED by [{}]", by.join(", ")),
+            };
+            println!("  {:<24} decides {:>5}, fires {:>5}  {}", o.output, o.decides, o.fires, status);
+        }
+        println!("  {:<24} decides {:>5}", "(default deny)", a.default_deny);
+        for v in &a.violations {
+            let example: Vec<String> = a.variables.iter().map(|b| format!("{}={}", b, v.example[b])).collect();
+            println!("❌ invariant {}: {} decides in {} assignment(s), e.g. {}", v.invariant, v.output, v.count, example.join(" "));
+        }
+    }
+    if !a.violations.is_empty() || (m.get_flag("deny_warnings") && warnings > 0) {
+        anyhow::bail!("analysis failed");
+    }
+    Ok(())
+}
+
+pub fn eval(m: &ArgMatches) -> anyhow::Result<()> {
+    let chip = load(m.get_one::<String>("chip").unwrap())?;
+    let ctx = load_context(m.get_one::<String>("ctx").unwrap())?;
+    println!("{}", serde_json::to_string_pretty(&decide(&chip, &ctx))?);
+    Ok(())
+}
+
+pub fn explain(m: &ArgMatches) -> anyhow::Result<()> {
+    let chip = load(m.get_one::<String>("chip").unwrap())?;
+    let ctx = load_context(m.get_one::<String>("ctx").unwrap())?;
+    let explanation = policy_engine::explain(&chip, &ctx);
+    if m.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&explanation)?);
+    } else {
+        print!("{}", explanation);
+    }
+    Ok(())
+}
+
+fn structure(s: &WiringStructure) -> String {
+    match s {
+        WiringStructure::Sequence { sequence } => format!("sequence [{}]", sequence.join(", ")),
+        WiringStructure::Parallel { parallel } => format!("parallel {} [{}]", parallel.aggregator, parallel.policies.join(", ")),
+    }
+}
+
+pub fn migrate(m: &ArgMatches) -> anyhow::Result<()> {
+    let (write, check) = (m.get_flag("write"), m.get_flag("check"));
+    let mut pending = 0;
+    for path in m.get_many::<String>("files").unwrap() {
+        let raw = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        if Bundle::is_bundle(&raw) {
+            anyhow::bail!("{}: bundles are signed; migrate the chip YAML and sign again", path);
+        }
+        let mig = policy_engine::migrate(&raw).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        if !mig.changed() {
+            eprintln!("{}: already {}", path, policy_engine::CHIP_VERSION);
+            if !write && !check {
+                print!("{}", mig.yaml);
+            }
+            continue;
+        }
+        pending += 1;
+        for note in &mig.notes {
+            eprintln!("{}: {}", path, note);
+        }
+        if !mig.comments_preserved {
+            eprintln!("{}: layout not recognized; rewritten without comments", path);
+        }
+        if write {
+            std::fs::write(path, &mig.yaml)?;
+            eprintln!("✅ {} rewritten; sign it again (blake3 changed)", path);
+        } else if !check {
+            print!("{}", mig.yaml);
+        }
+    }
+    if check && pending > 0 {
+        anyhow::bail!("{} file(s) need migration", pending);
+    }
+    Ok(())
+}
+
+fn meta(m: &ChipMeta) -> String {
+    format!("owners [{}], tenant {}, intent {:?}", m.owners.join(", "), m.tenant.as_deref().unwrap_or("-"), m.intent.as_deref().unwrap_or("-"))
+}
+
+fn bits(c: &SemanticChip) -> BTreeMap<&str, &PolicyBitDefinition> {
+    c.policies.iter().map(|p| (p.id.as_str(), p)).collect()
+}
+
+fn wires(c: &SemanticChip) -> BTreeMap<&str, &WiringStructure> {
+    c.wiring.iter().map(|w| (w.id.as_str(), &w.structure)).collect()
+}
+
+fn outputs(c: &SemanticChip) -> BTreeMap<&str, &OutputAction> {
+    c.outputs.iter().flat_map(|o| o.0.iter()).map(|(k, a)| (k.as_str(), a)).collect()
+}
+
+fn outputs_in_order(c: &SemanticChip) -> Vec<&str> {
+    c.outputs.iter().flat_map(|o| o.0.keys().map(String::as_str)).collect()
+}
+
+/// `+` só no novo, `-` só no antigo, `~` mudou (valores formatados por `show`).
+fn diff_section<T: PartialEq>(kind: &str, old: &BTreeMap<&str, T>, new: &BTreeMap<&str, T>, show: impl Fn(&T) -> String) -> usize {
+    let mut changes = 0;
+    for (id, o) in old {
+        match new.get(id) {
+            None => println!("- {} {}: {}", kind, id, show(o)),
+            Some(n) if n != o => println!("~ {} {}: {} → {}", kind, id, show(o), show(n)),
+            Some(_) => continue,
+        }
+        changes += 1;
+    }
+    for (id, n) in new.iter().filter(|(id, _)| !old.contains_key(*id)) {
+        println!("+ {} {}: {}", kind, id, show(n));
+        changes += 1;
+    }
+    changes
+}
+
+pub fn diff(m: &ArgMatches) -> anyhow::Result<()> {
+    let (old_path, new_path) = (m.get_one::<String>("old").unwrap(), m.get_one::<String>("new").unwrap());
+    let (old, new) = (load(old_path)?, load(new_path)?);
+    let mut changes = 0;
+
+    if old.version != new.version {
+        println!("~ version: {} → {}", old.version, new.version);
+        changes += 1;
+    }
+    if old.meta != new.meta {
+        println!("~ meta: {} → {}", meta(&old.meta), meta(&new.meta));
+        changes += 1;
+    }
+    changes += diff_section("bit", &bits(&old), &bits(&new), |p| {
+        let params = if p.params.is_empty() { String::new() } else { format!(" {}", serde_json::to_string(&p.params).unwrap_or_default()) };
+        format!("{}{} [{}]", p.logic.as_deref().unwrap_or("-"), params, p.description.as_deref().unwrap_or("-"))
+    });
+    let pins = |c: &SemanticChip| c.imported.iter().map(|p| format!("{}@{}", p.library, &p.blake3[..p.blake3.len().min(12)])).collect::<Vec<_>>().join(", ");
+    if old.imported != new.imported {
+        println!("~ imported: [{}] → [{}]", pins(&old), pins(&new));
+        changes += 1;
+    }
+    changes += diff_section("wire", &wires(&old), &wires(&new), |w| structure(w));
+    changes += diff_section("output", &outputs(&old), &outputs(&new), |a| {
+        let list = |l: &[policy_engine::Obligation]| l.iter().map(|o| serde_json::to_string(o).unwrap_or_default()).collect::<Vec<_>>().join(", ");
+        let mut s = format!("{} ⇒ {}", a.trigger, a.action);
+        if !a.obligations.is_empty() {
+            s += &format!(" obligations [{}]", list(&a.obligations));
+        }
+        if !a.advice.is_empty() {
+            s += &format!(" advice [{}]", list(&a.advice));
+        }
+        s
+    });
+    let (old_order, new_order) = (outputs_in_order(&old), outputs_in_order(&new));
+    if old_order != new_order {
+        println!("~ output order: [{}] → [{}]", old_order.join(", "), new_order.join(", "));
+        changes += 1;
+    }
+
+    for path in m.get_many::<String>("cases").into_iter().flatten() {
+        let file = cases::CaseFile::load(path)?;
+        for case in &file.cases {
+            let ctx = cases::context(&file.defaults, &case.context)?;
+            let (a, b) = (decide(&old, &ctx).decision, decide(&new, &ctx).decision);
+            if a != b {
+                println!("≠ case {:?} ({}): {} → {}", case.name, path, a, b);
+                changes += 1;
+            }
+        }
+    }
+    if changes == 0 {
+        println!("no differences");
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! ubl-policy — CLI único para o ciclo de vida do chip: chaves, assinatura,
+//! verificação, lint, análise exaustiva, avaliação local, casos de teste, diff,
+//! migração de versão e reload do proxy.
+//! keygen/sign/verify são os mesmos comandos de `policy-keygen`/`policy-signer`;
+//! lint/eval/explain usam o `policy-engine` que roda no proxy e no Worker.
+
+mod cases;
+mod chip;
+mod push;
+
+use clap::{Arg, ArgAction, Command, CommandFactory, FromArgMatches};
+
+fn main() -> anyhow::Result<()> {
+    let matches = Command::new("ubl-policy")
+        .about("Policy chip tooling: keys, signing, lint, evaluation, tests and proxy reload")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(policy_keygen::Opts::command().name("keygen"))
+        .subcommand(policy_signer::sign_command("sign"))
+        .subcommand(policy_signer::cosign_command())
+        .subcommand(policy_signer::verify_command())
+        .subcommand(policy_signer::authorize_downgrade_command())
+        .subcommand(
+            Command::new("lint")
+                .about("Static checks: broken references, cycles, constructs that always evaluate false")
+                .arg(chip_arg().num_args(1..).action(ArgAction::Append))
+                .arg(Arg::new("deny_warnings").long("deny-warnings").action(ArgAction::SetTrue).help("Exit non-zero on warnings too"))
+                .arg(json_arg()),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("Enumerate every bit assignment: unreachable and shadowed outputs, invariant violations")
+                .arg(chip_arg())
+                .arg(Arg::new("deny_warnings").long("deny-warnings").action(ArgAction::SetTrue).help("Exit non-zero on unreachable/shadowed outputs too"))
+                .arg(json_arg()),
+        )
+        .subcommand(
+            Command::new("eval")
+                .about("Evaluate a request context (JSON) against a chip and print the decision")
+                .arg(chip_arg())
+                .arg(ctx_arg()),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Show each output tried in order, the bits and context values behind it, and the minimal change that flips the decision")
+                .arg(chip_arg())
+                .arg(ctx_arg())
+                .arg(json_arg()),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Run decision test cases (YAML) against a chip")
+                .arg(Arg::new("cases").value_name("CASES").num_args(1..).required(true).help("Case files (see policies/tests/)"))
+                .arg(Arg::new("chip").long("chip").value_name("FILE").help("Chip YAML or bundle (default: `chip:` in each case file)")),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare two chips: bits, wires and outputs; with --cases also decisions")
+                .arg(Arg::new("old").value_name("OLD").required(true))
+                .arg(Arg::new("new").value_name("NEW").required(true))
+                .arg(Arg::new("cases").long("cases").value_name("FILE").action(ArgAction::Append).help("Also report cases whose decision changes")),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Upgrade chip YAML to the current format version (prints to stdout unless --write)")
+                .arg(Arg::new("files").value_name("CHIP").num_args(1..).required(true))
+                .arg(Arg::new("write").long("write").action(ArgAction::SetTrue).help("Rewrite the files in place"))
+                .arg(Arg::new("check").long("check").action(ArgAction::SetTrue).conflicts_with("write").help("Only report; exit non-zero if any file needs migration")),
+        )
+        .subcommand(
+            Command::new("push")
+                .about("Reload a running policy-proxy and confirm the active chip")
+                .arg(Arg::new("url").long("url").value_name("URL").default_value("http://127.0.0.1:9456").help("policy-proxy base URL"))
+                .arg(Arg::new("tenant").long("tenant").value_name("TENANT").help("Tenant to reload (default: proxy default tenant)"))
+                .arg(Arg::new("stage").long("stage").value_name("STAGE").value_parser(["active", "next"]).default_value("active"))
+                .arg(Arg::new("expect").long("expect").value_name("FILE").help("Pack or bundle that must be active after the reload (compares blake3)")),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        Some(("keygen", m)) => policy_keygen::run(&policy_keygen::Opts::from_arg_matches(m)?),
+        Some(("sign", m)) => policy_signer::sign(m),
+        Some(("cosign", m)) => policy_signer::cosign(m),
+        Some(("verify", m)) => policy_signer::verify(m),
+        Some(("authorize-downgrade", m)) => policy_signer::authorize_downgrade(m),
+        Some(("lint", m)) => chip::lint(m),
+        Some(("analyze", m)) => chip::analyze(m),
+        Some(("eval", m)) => chip::eval(m),
+        Some(("explain", m)) => chip::explain(m),
+        Some(("test", m)) => cases::test(m),
+        Some(("diff", m)) => chip::diff(m),
+        Some(("migrate", m)) => chip::migrate(m),
+        Some(("push", m)) => push::push(m),
+        _ => unreachable!("subcommand_required"),
+    }
+}
+
+fn chip_arg() -> Arg {
+    Arg::new("chip").long("chip").value_name("FILE").required(true).help("Chip YAML or single-file bundle")
+}
+
+fn ctx_arg() -> Arg {
+    Arg::new("ctx").long("ctx").value_name("FILE").required(true).help("RequestContext JSON (`-` for stdin); missing fields take defaults")
+}
+
+fn json_arg() -> Arg {
+    Arg::new("json").long("json").action(ArgAction::SetTrue).help("Machine-readable output")
+}
@@ -0,0 +1,48 @@
+//! `push`: GET /_reload no policy-proxy e confere o chip ativo em /_policy.
+//! Os arquivos já precisam estar onde o proxy lê (ou no stage `next`).
+
+use clap::ArgMatches;
+use policy_pack::{AnyPack, Bundle};
+
+pub fn push(m: &ArgMatches) -> anyhow::Result<()> {
+    let base = m.get_one::<String>("url").unwrap().trim_end_matches('/');
+    let stage = m.get_one::<String>("stage").unwrap();
+    let tenant = m.get_one::<String>("tenant");
+
+    let mut req = ureq::get(&format!("{}/_reload", base)).query("stage", stage);
+    if let Some(t) = tenant {
+        req = req.query("tenant", t);
+    }
+    match req.call() {
+        Ok(resp) => println!("✅ reload: {}", resp.into_string()?.trim()),
+        // o proxy devolve o motivo (assinatura, rollback…) no corpo
+        Err(ureq::Error::Status(code, resp)) => anyhow::bail!("reload rejected ({}): {}", code, resp.into_string()?.trim()),
+        Err(e) => anyhow::bail!("reload: {}", e),
+    }
+
+    let info: serde_json::Value = ureq::get(&format!("{}/_policy", base)).call()?.into_json()?;
+    let tenant_id = tenant.map(String::as_str).or(info["default"].as_str()).unwrap_or_default();
+    let active = info["tenants"].as_array().into_iter().flatten()
+        .find(|t| t["tenant"] == tenant_id)
+        .map(|t| &t["active"])
+        .ok_or_else(|| anyhow::anyhow!("tenant {} not in /_policy", tenant_id))?;
+    println!("   tenant {}: {}@{} ({})", tenant_id, active["id"].as_str().unwrap_or("?"), active["version"].as_str().unwrap_or("?"),
+        active["blake3"].as_str().unwrap_or("?"));
+
+    if let Some(path) = m.get_one::<String>("expect") {
+        let raw = std::fs::read_to_string(path)?;
+        let expected = if Bundle::is_bundle(&raw) {
+            Bundle::from_json(&raw)?.pack.envelope.blake3
+        } else {
+            match AnyPack::from_json(&raw)? {
+                AnyPack::V1(p) => p.blake3,
+                AnyPack::V2(p) => p.envelope.blake3,
+            }
+        };
+        if active["blake3"].as_str() != Some(expected.as_str()) {
+            anyhow::bail!("active chip is not {} (expected blake3 {})", path, expected);
+        }
+        println!("✅ active chip matches {}", path);
+    }
+    Ok(())
+}
@@ -25,23 +25,20 @@ echo "✅ Chave pública (base64) salva em /tmp/PUB_BASE64.txt"
 echo "   ${PUB_BASE64:0:50}..."
 echo ""
 
-# 2. Salvar a política
-echo "📝 2. Copiando política..."
-sudo cp policies/ubl_core_v1.yaml /etc/ubl/nova/policy/ubl_core_v1.yaml
-sudo chmod 644 /etc/ubl/nova/policy/ubl_core_v1.yaml
-echo "✅ Política salva em /etc/ubl/nova/policy/ubl_core_v1.yaml"
-echo ""
-
-# 3. Assinar e gerar pack.json
-echo "📝 3. Assinando política..."
+# 2-3. Assinar a partir de policies/ (imports: lib/ resolvido ali) e instalar
+# o chip resolvido — é ele que o pack cobre, não o fonte com `imports:`
+echo "📝 2-3. Assinando e instalando política..."
 sudo ./target/release/policy-signer \
   --id ubl_access_chip_v1 \
   --version 1 \
-  --yaml /etc/ubl/nova/policy/ubl_core_v1.yaml \
+  --yaml policies/ubl_core_v1.yaml \
+  --resolved /etc/ubl/nova/policy/ubl_core_v1.yaml \
   --privkey_pem /etc/ubl/nova/keys/policy_signing_private.pem \
   --out /etc/ubl/nova/policy/pack.json
+sudo chmod 644 /etc/ubl/nova/policy/ubl_core_v1.yaml
 
 echo "✅ pack.json gerado em /etc/ubl/nova/policy/pack.json"
+echo "✅ Chip resolvido instalado em /etc/ubl/nova/policy/ubl_core_v1.yaml"
 echo ""
 
 # 4. Build do proxy
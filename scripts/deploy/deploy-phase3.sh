@@ -119,12 +119,13 @@ else
     exit 1
 fi
 
-# Tentar encontrar ubl_core_v1.yaml
+# Chip resolvido (o que o pack assina): o instalado pela fase 2 ou o
+# <yaml>.resolved.yaml do signer — nunca o fonte com `imports:`
 POLICY_YAML=""
 if [ -f /etc/ubl/nova/policy/ubl_core_v1.yaml ]; then
     POLICY_YAML="/etc/ubl/nova/policy/ubl_core_v1.yaml"
-elif [ -f "$PROJECT_ROOT/policies/ubl_core_v1.yaml" ]; then
-    POLICY_YAML="$PROJECT_ROOT/policies/ubl_core_v1.yaml"
+elif [ -f "$PROJECT_ROOT/policies/ubl_core_v1.resolved.yaml" ]; then
+    POLICY_YAML="$PROJECT_ROOT/policies/ubl_core_v1.resolved.yaml"
 elif sudo test -f /etc/ubl/nova/policy/ubl_core_v1.yaml 2>/dev/null; then
     sudo cp /etc/ubl/nova/policy/ubl_core_v1.yaml /tmp/ubl_core_v1.yaml 2>/dev/null && POLICY_YAML="/tmp/ubl_core_v1.yaml" || true
 fi
@@ -133,7 +134,7 @@ if [ -n "$POLICY_YAML" ] && [ -f "$POLICY_YAML" ]; then
     wrangler kv key put policy_yaml --binding=UBL_FLAGS --path="$POLICY_YAML"
     echo "✅ ubl_core_v1.yaml publicado na KV"
 else
-    echo "⚠️  AVISO: ubl_core_v1.yaml não encontrado"
+    echo "⚠️  AVISO: chip resolvido não encontrado (policy-signer gera policies/ubl_core_v1.resolved.yaml)"
     exit 1
 fi
 
@@ -168,6 +168,7 @@ if [ -f "policies/ubl_core_v3.yaml" ] && [ -f "policies/vvz_core_v1.yaml" ]; the
     --id ubl_access_chip_v3 \
     --version v3 \
     --privkey_pem "${POLICY_PRIVKEY_PATH:-/etc/ubl/keys/policy_priv.pem}" \
+    --resolved /tmp/ubl_core_v3.resolved.yaml \
     --out /tmp/pack_ubl_v3.json 2>&1 | tee /tmp/signer-ubl.log; then
     echo "   ✅ ubl_core_v3 assinado"
     
@@ -187,6 +188,7 @@ if [ -f "policies/ubl_core_v3.yaml" ] && [ -f "policies/vvz_core_v1.yaml" ]; the
     --id vvz_core_v1 \
     --version v1 \
     --privkey_pem "${POLICY_PRIVKEY_PATH:-/etc/ubl/keys/policy_priv.pem}" \
+    --resolved /tmp/vvz_core_v1.resolved.yaml \
     --out /tmp/pack_vvz_v1.json 2>&1 | tee /tmp/signer-vvz.log; then
     echo "   ✅ vvz_core_v1 assinado"
     
@@ -194,7 +196,8 @@ if [ -f "policies/ubl_core_v3.yaml" ] && [ -f "policies/vvz_core_v1.yaml" ]; the
     if [ -n "${UBL_FLAGS_KV_ID:-}" ]; then
       echo "   📤 Publicando no KV (policy_voulezvous_pack_active)..."
       wrangler kv:key put "policy_voulezvous_pack_active" --namespace-id="$UBL_FLAGS_KV_ID" --path=/tmp/pack_vvz_v1.json 2>&1 | tee /tmp/kv-vvz-pack.log || true
-      wrangler kv:key put "policy_voulezvous_yaml_active" --namespace-id="$UBL_FLAGS_KV_ID" --value="$(cat policies/vvz_core_v1.yaml)" 2>&1 | tee /tmp/kv-vvz-yaml.log || true
+      # chip resolvido (o que o pack assina), não o fonte com imports
+      wrangler kv:key put "policy_voulezvous_yaml_active" --namespace-id="$UBL_FLAGS_KV_ID" --path=/tmp/vvz_core_v1.resolved.yaml 2>&1 | tee /tmp/kv-vvz-yaml.log || true
       echo "   ✅ Policy Voulezvous publicada"
     fi
   else
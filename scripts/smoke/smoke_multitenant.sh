@@ -109,7 +109,7 @@ echo "  • Access control: Check above"
 echo ""
 echo "⚠️  Next steps:"
 echo "  1. Publish voulezvous policy to KV:"
-echo "     wrangler kv key put --binding=UBL_FLAGS policy_voulezvous_yaml @policies/vvz_core_v1.yaml"
+echo "     wrangler kv key put --binding=UBL_FLAGS policy_voulezvous_yaml @policies/vvz_core_v1.resolved.yaml"
 echo "     wrangler kv key put --binding=UBL_FLAGS policy_voulezvous_pack @/tmp/pack_vvz_v1.json"
 echo "  2. Reload voulezvous policy:"
 echo "     curl -XPOST '${EDGE_HOST}/_reload?tenant=${TENANT_VVZ}&stage=next'"
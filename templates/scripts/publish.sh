@@ -33,6 +33,7 @@ echo "[1/4] Assinando política..."
   --version 3 \
   --yaml policies/ubl_core_v3.yaml \
   --privkey_pem "$POLICY_PRIVKEY_PEM" \
+  --resolved "/tmp/${APP_ID}_chip_v3.yaml" \
   --out "/tmp/${APP_ID}_pack_v3.json"
 
 if [ ! -f "/tmp/${APP_ID}_pack_v3.json" ]; then
@@ -40,13 +41,13 @@ if [ ! -f "/tmp/${APP_ID}_pack_v3.json" ]; then
   exit 1
 fi
 
-# 2) Publicar como 'next'
+# 2) Publicar como 'next' (chip resolvido: o que o pack assina)
 echo "[2/4] Publicando em KV (stage=next)..."
 wrangler kv:key put \
   --namespace-id "$KV_NAMESPACE_ID" \
   --binding=UBL_FLAGS \
   --key=policy_yaml_next \
-  --path=policies/ubl_core_v3.yaml
+  --path="/tmp/${APP_ID}_chip_v3.yaml"
 
 wrangler kv:key put \
   --namespace-id "$KV_NAMESPACE_ID" \
@@ -70,24 +70,16 @@ async function warmupTenant(tenant, env, stage = "active") {
   }
   
   try {
-    // Load pack and YAML for tenant (Blueprint 17: policy:{tenant}:pack/yaml)
+    // Load pack and YAML for tenant (Blueprint 17: policy:{tenant}:pack/yaml, or a single bundle)
     const targetStage = stage || cached?.stage || "active"; // active, next, prev
-    const packKey = targetStage === "active" ? `policy_${tenant}_pack` : `policy_${tenant}_pack_${targetStage}`;
-    const yamlKey = targetStage === "active" ? `policy_${tenant}_yaml` : `policy_${tenant}_yaml_${targetStage}`;
+    const loaded = await loadPolicy(env, tenant, targetStage);
     
-    // Fallback to legacy keys (for backward compatibility)
-    const packRaw = await env.UBL_FLAGS.get(packKey) || 
-                    (tenant === "ubl" ? (await env.UBL_FLAGS.get("policy_pack_active") || await env.UBL_FLAGS.get("policy_pack")) : null);
-    const yaml = await env.UBL_FLAGS.get(yamlKey) || 
-                 (tenant === "ubl" ? (await env.UBL_FLAGS.get("policy_yaml_active") || await env.UBL_FLAGS.get("policy_yaml")) : null);
-    
-    if (!packRaw || !yaml) {
-      const error = `policy_missing: tenant=${tenant}, keys=${packKey}/${yamlKey}`;
-      tenantWarmup.set(tenant, { done: false, error, blake3: null, wasm: null, stage });
-      return { ok: false, error };
+    if (loaded.error) {
+      tenantWarmup.set(tenant, { done: false, error: loaded.error, blake3: null, wasm: null, stage });
+      return { ok: false, error: loaded.error };
     }
     
-    const pack = JSON.parse(packRaw);
+    const { pack, yaml } = loaded;
     
     // Verify signature
     await verifyPack(pack, env.POLICY_PUBKEY_B64);
@@ -277,15 +269,18 @@ export default {
         const stage = body.stage || url.searchParams.get("stage") || "next";
         
         // Promote: copy next -> active
+        const nextBundleKey = `policy_${targetTenant}_bundle_${stage}`;
         const nextPackKey = `policy_${targetTenant}_pack_${stage}`;
         const nextYamlKey = `policy_${targetTenant}_yaml_${stage}`;
+        const activeBundleKey = `policy_${targetTenant}_bundle`;
         const activePackKey = `policy_${targetTenant}_pack`;
         const activeYamlKey = `policy_${targetTenant}_yaml`;
         
+        const nextBundle = await env.UBL_FLAGS.get(nextBundleKey);
         const nextPack = await env.UBL_FLAGS.get(nextPackKey);
         const nextYaml = await env.UBL_FLAGS.get(nextYamlKey);
         
-        if (!nextPack || !nextYaml) {
+        if (!nextBundle && (!nextPack || !nextYaml)) {
           return new Response(JSON.stringify({
             ok: false,
             error: `stage_not_found: tenant=${targetTenant}, stage=${stage}`
@@ -295,9 +290,13 @@ export default {
           });
         }
         
-        // Copy next -> active
-        await env.UBL_FLAGS.put(activePackKey, nextPack);
-        await env.UBL_FLAGS.put(activeYamlKey, nextYaml);
+        // Copy next -> active (bundle is a single put, so it is atomic)
+        if (nextBundle) {
+          await env.UBL_FLAGS.put(activeBundleKey, nextBundle);
+        } else {
+          await env.UBL_FLAGS.put(activePackKey, nextPack);
+          await env.UBL_FLAGS.put(activeYamlKey, nextYaml);
+        }
         
         // Invalidate cache
         const cached = tenantWarmup.get(targetTenant);
@@ -446,9 +445,7 @@ export default {
     const wasm = cached?.wasm || (await getEngine(env));
     if (!wasm.__inited || wasm.__tenant !== tenant) {
       // Re-initialize if tenant changed
-      const yamlKey = cached?.stage === "active" ? `policy_${tenant}_yaml` : `policy_${tenant}_yaml_${cached?.stage || "active"}`;
-      const yaml = await env.UBL_FLAGS.get(yamlKey) || 
-                   (tenant === "ubl" ? (await env.UBL_FLAGS.get("policy_yaml_active") || await env.UBL_FLAGS.get("policy_yaml")) : null);
+      const { yaml } = await loadPolicy(env, tenant, cached?.stage || "active");
       if (yaml) {
         initPolicyWasm(wasm, yaml);
         wasm.__inited = true;
@@ -555,13 +552,57 @@ export default {
   }
 };
 
+/**
+ * Load pack + chip YAML for a tenant/stage.
+ * Prefers the single-file bundle (policy_{tenant}_bundle[_{stage}], format ubl-policy-bundle/1);
+ * falls back to separate pack/yaml keys and then legacy keys.
+ */
+async function loadPolicy(env, tenant, stage) {
+  const suffix = stage === "active" ? "" : `_${stage}`;
+  const bundleRaw = await env.UBL_FLAGS.get(`policy_${tenant}_bundle${suffix}`);
+  if (bundleRaw) {
+    const bundle = JSON.parse(bundleRaw);
+    if (bundle.format !== "ubl-policy-bundle/1") return { error: "policy_bundle_format_unsupported" };
+    return { pack: bundle.pack, yaml: bundle.chip };
+  }
+  
+  const packKey = `policy_${tenant}_pack${suffix}`;
+  const yamlKey = `policy_${tenant}_yaml${suffix}`;
+  // Fallback to legacy keys (for backward compatibility)
+  const packRaw = await env.UBL_FLAGS.get(packKey) || 
+                  (tenant === "ubl" ? (await env.UBL_FLAGS.get("policy_pack_active") || await env.UBL_FLAGS.get("policy_pack")) : null);
+  const yaml = await env.UBL_FLAGS.get(yamlKey) || 
+               (tenant === "ubl" ? (await env.UBL_FLAGS.get("policy_yaml_active") || await env.UBL_FLAGS.get("policy_yaml")) : null);
+  if (!packRaw || !yaml) {
+    return { error: `policy_missing: tenant=${tenant}, keys=${packKey}/${yamlKey}` };
+  }
+  return { pack: JSON.parse(packRaw), yaml };
+}
+
+// Campos cobertos pela assinatura v2 (crate policy-pack), em ordem lexicográfica
+const PACK_V2_FIELDS = ["alg", "blake3", "chip_version", "created_at", "format", "id", "kid", "not_after", "not_before", "version"];
+
+function packMessage(pack) {
+  if (pack.format === undefined) {
+    return `id=${pack.id}\nversion=${pack.version}\nblake3=${pack.blake3}\n`;
+  }
+  if (pack.format !== "ubl-policy-pack/2") throw new Error("policy_pack_format_unsupported");
+  if (pack.alg !== "Ed25519") throw new Error("policy_pack_alg_unsupported");
+  const envelope = {};
+  for (const k of PACK_V2_FIELDS) envelope[k] = pack[k] ?? null;
+  return JSON.stringify(envelope);
+}
+
 async function verifyPack(pack, pubkeyB64) {
-  const msg = `id=${pack.id}\nversion=${pack.version}\nblake3=${pack.blake3}\n`;
+  const msg = packMessage(pack);
   const keyData = Uint8Array.from(atob(pubkeyB64), c=>c.charCodeAt(0));
   const key = await crypto.subtle.importKey("spki", keyData.buffer, {name:"Ed25519"}, false, ["verify"]);
   const sig = Uint8Array.from(atob(pack.signature), c=>c.charCodeAt(0)).buffer;
   const ok = await crypto.subtle.verify("Ed25519", key, sig, new TextEncoder().encode(msg));
   if (!ok) throw new Error("policy_pack_invalid");
+  const now = Math.floor(Date.now() / 1000);
+  if (pack.not_before != null && now < pack.not_before) throw new Error("policy_pack_not_yet_valid");
+  if (pack.not_after != null && now > pack.not_after) throw new Error("policy_pack_expired");
 }
 
 let _engine;